@@ -0,0 +1,155 @@
+//! Benchmarks of key generation, proving and verification through the public API, across K.
+//! The keypair is derived from a fixed seed and the proofs use a seeded RNG, and the params
+//! are computed in memory, so the benchmarks need no network or filesystem state.
+//! The proof sizes are printed with the times, as they also grow with K.
+//!
+//! Run with `cargo bench --bench prove`, and with `--features parallel` for the comparison of
+//! sequential and parallel proofs of the blocks of a message.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use halo2_proofs::plonk::{Circuit, ProvingKey};
+use halo2_proofs::poly::commitment::Params;
+use halo2_verifiable_encryption::circuits::verifiable_encryption::{
+    VeEncCircuit, VeEncCircuitBuilder, VeEncInstance,
+};
+use halo2_verifiable_encryption::circuits::verifiable_encryption_batch::{
+    VeEncBatchCircuit, VeEncBatchInstance,
+};
+use halo2_verifiable_encryption::circuits::{
+    keygen, prove_encryption_with_rng, recommended_k, verify_encryption,
+};
+use halo2_verifiable_encryption::elgamal::elgamal::ElGamalKeypair;
+use pasta_curves::{pallas, vesta};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+// K of the single-block benchmarks: the recommended K and the next ones
+const EXTRA_K: u32 = 2;
+
+// Number of blocks of the batch benchmarks
+const BATCH_SIZES: [usize; 2] = [2, 4];
+
+const SEED: [u8; 32] = [7; 32];
+
+// The circuit and the instance of the encryption of message i to the seeded keypair
+fn block(i: u64) -> (VeEncCircuit, VeEncInstance) {
+    let keypair = ElGamalKeypair::from_seed(SEED);
+    let (circuit, instance, _) =
+        VeEncCircuitBuilder::new(pallas::Base::from(i), keypair.public_key)
+            .build()
+            .unwrap();
+    (circuit, instance)
+}
+
+// A proof with a seeded RNG, so that its size is the same on each run
+fn prove<C: Circuit<pallas::Base> + Clone>(
+    params: &Params<vesta::Affine>,
+    pk: &ProvingKey<vesta::Affine>,
+    circuit: &C,
+    instance: &[&[vesta::Scalar]],
+) -> Vec<u8> {
+    let rng = ChaCha20Rng::from_seed(SEED);
+    prove_encryption_with_rng(params, pk, circuit.clone(), instance, rng).unwrap()
+}
+
+fn single_block(c: &mut Criterion) {
+    let (circuit, instance) = block(1);
+    let instance = instance.to_halo2_instance().unwrap();
+    let instance_refs: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+    let min_k = recommended_k(1);
+
+    let mut group = c.benchmark_group("single block");
+    group.sample_size(10);
+    for k in min_k..=min_k + EXTRA_K {
+        let params = Params::new(k);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove(&params, &pk, &circuit, &instance_refs);
+        println!("single block, K = {}: proof of {} bytes", k, proof.len());
+
+        group.bench_with_input(BenchmarkId::new("keygen", k), &params, |b, params| {
+            b.iter(|| keygen(params, &circuit.without_witnesses()).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("prove", k), &params, |b, params| {
+            let mut rng = ChaCha20Rng::from_seed(SEED);
+            b.iter(|| {
+                prove_encryption_with_rng(params, &pk, circuit.clone(), &instance_refs, &mut rng)
+                    .unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("verify", k), &params, |b, params| {
+            b.iter(|| verify_encryption(params, &vk, &instance_refs, &proof).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch");
+    group.sample_size(10);
+    for num_blocks in BATCH_SIZES {
+        let (circuits, instances): (Vec<_>, Vec<_>) = (0..num_blocks as u64).map(block).unzip();
+        let circuit = VeEncBatchCircuit::new(circuits);
+        let instance = VeEncBatchInstance::new(instances).to_halo2_instance().unwrap();
+        let instance_refs: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        let k = recommended_k(num_blocks);
+
+        let params = Params::new(k);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove(&params, &pk, &circuit, &instance_refs);
+        println!("{} blocks, K = {}: proof of {} bytes", num_blocks, k, proof.len());
+
+        let id = format!("{} blocks, K = {}", num_blocks, k);
+        group.bench_function(BenchmarkId::new("prove", &id), |b| {
+            let mut rng = ChaCha20Rng::from_seed(SEED);
+            b.iter(|| {
+                prove_encryption_with_rng(&params, &pk, circuit.clone(), &instance_refs, &mut rng)
+                    .unwrap()
+            })
+        });
+        group.bench_function(BenchmarkId::new("verify", &id), |b| {
+            b.iter(|| verify_encryption(&params, &vk, &instance_refs, &proof).unwrap())
+        });
+    }
+    group.finish();
+}
+
+// Number of blocks of the parallel benchmark
+#[cfg(feature = "parallel")]
+const PARALLEL_BLOCKS: u64 = 8;
+
+// The proofs of the blocks one after the other, and with prove_all_blocks
+#[cfg(feature = "parallel")]
+fn parallel(c: &mut Criterion) {
+    use halo2_verifiable_encryption::circuits::{prove_all_blocks, prove_encryption};
+
+    let (circuits, instances): (Vec<_>, Vec<_>) = (0..PARALLEL_BLOCKS).map(block).unzip();
+    let instances: Vec<_> = instances
+        .iter()
+        .map(|instance| instance.to_halo2_instance().unwrap())
+        .collect();
+    let params = Params::new(recommended_k(1));
+    let (_, pk) = keygen(&params, &circuits[0].without_witnesses()).unwrap();
+
+    let mut group = c.benchmark_group("parallel");
+    group.sample_size(10);
+    let id = format!("{} blocks", PARALLEL_BLOCKS);
+    group.bench_function(BenchmarkId::new("sequential", &id), |b| {
+        b.iter(|| {
+            for (circuit, instance) in circuits.iter().zip(instances.iter()) {
+                let instance_refs: Vec<&[vesta::Scalar]> =
+                    instance.iter().map(|c| &c[..]).collect();
+                prove_encryption(&params, &pk, circuit.clone(), &instance_refs).unwrap();
+            }
+        })
+    });
+    group.bench_function(BenchmarkId::new("prove_all_blocks", &id), |b| {
+        b.iter(|| prove_all_blocks(&params, &pk, &circuits).unwrap())
+    });
+    group.finish();
+}
+
+#[cfg(not(feature = "parallel"))]
+criterion_group!(benches, single_block, batch);
+#[cfg(feature = "parallel")]
+criterion_group!(benches, single_block, batch, parallel);
+criterion_main!(benches);
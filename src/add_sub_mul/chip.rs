@@ -1,19 +1,31 @@
+use ff::Field;
 use halo2_proofs::{
     circuit::{AssignedCell, Chip, Layouter, SimpleFloorPlanner, Value},
     pasta::{pallas, EqAffine, Fp},
     plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, SingleVerifier},
-    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance, Selector},
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Instance, Selector,
+    },
     poly::{commitment::Params, Rotation},
     transcript::{Blake2bRead, Blake2bWrite, Challenge255},
 };
 use pasta_curves::vesta;
 use rand::rngs::OsRng;
 
-// We define three chips (one for add, one for sub and one for mul).
+// We define nine chips (one for add, one for sub, one for mul, one for square,
+// one for mul by constant, one for add constant, one for div, one for is_zero and one for select).
 
 /// Define instructions that we would like to use with our chip AddSubMulChip
 pub(crate) trait AddSubMulInstructions:
-AddInstructions + SubInstructions + MulInstructions
+AddInstructions
+    + SubInstructions
+    + MulInstructions
+    + SquareInstructions
+    + MulByConstantInstructions
+    + AddConstantInstructions
+    + DivInstructions
+    + IsZeroInstructions
+    + SelectInstructions
 {
     /// Variable representing a number.
     type Num;
@@ -32,13 +44,32 @@ AddInstructions + SubInstructions + MulInstructions
         constant: Fp,
     ) -> Result<<Self as AddSubMulInstructions>::Num, Error>;
 
-    /// Check that a number is equal to a public input.
+    /// Check that a number is equal to the public input at the given row of the instance
+    /// column, with an equality constraint between its cell and the instance cell.
+    /// Any row can be used, e.g. the rows of a block in a batch circuit.
     fn check_result(
         &self,
         layouter: impl Layouter<Fp>,
         num: <Self as AddSubMulInstructions>::Num,
         row: usize,
     ) -> Result<(), Error>;
+
+    /// Check that a number is zero, against the public input at row 0, which is zero in the
+    /// instances of the encryption circuits. A circuit whose row 0 is not zero, or a block
+    /// at an offset of a batch circuit, uses `check_result` with the row of its zero.
+    fn check_zero(
+        &self,
+        layouter: impl Layouter<Fp>,
+        num: <Self as AddSubMulInstructions>::Num,
+    ) -> Result<(), Error>;
+
+    /// Check that two numbers are equal, with an equality constraint between their cells.
+    fn constrain_equal(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: <Self as AddSubMulInstructions>::Num,
+        b: <Self as AddSubMulInstructions>::Num,
+    ) -> Result<(), Error>;
 }
 
 pub(crate) trait AddInstructions {
@@ -53,6 +84,12 @@ pub(crate) trait AddInstructions {
         a: Self::Num,
         b: Self::Num,
     ) -> Result<Self::Num, Error>;
+
+    /// Define and assign a region for an add gate enforcing out + a = 0
+    /// - Copy the input value in the lhs cell
+    /// - Evaluate the output value (-a) and store it in the rhs cell
+    /// - Constrain the sum cell to be zero
+    fn neg(&self, layouter: impl Layouter<Fp>, a: Self::Num) -> Result<Self::Num, Error>;
 }
 
 pub(crate) trait SubInstructions {
@@ -83,6 +120,98 @@ pub(crate) trait MulInstructions {
     ) -> Result<Self::Num, Error>;
 }
 
+pub(crate) trait SquareInstructions {
+    type Num;
+
+    /// Define and assign a region for a square gate
+    /// - Copy the input value in the input cell
+    /// - Evaluate the output value (a*a) and store it in the output cell
+    /// This uses a single row, instead of copying a twice into the mul gate.
+    fn square(&self, layouter: impl Layouter<Fp>, a: Self::Num) -> Result<Self::Num, Error>;
+}
+
+pub(crate) trait MulByConstantInstructions {
+    type Num;
+
+    /// Define and assign a region for a mul by constant gate
+    /// - Copy the input value in the input cell and the constant in a fixed cell
+    /// - Evaluate the output value (a*k) and store it in the output cell
+    /// This uses a single row, instead of loading the constant and using the mul gate.
+    fn mul_by_constant(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: Self::Num,
+        k: Fp,
+    ) -> Result<Self::Num, Error>;
+}
+
+pub(crate) trait AddConstantInstructions {
+    type Num;
+
+    /// Define and assign a region for an add constant gate
+    /// - Copy the input value in the input cell and the constant in a fixed cell
+    /// - Evaluate the output value (a+k) and store it in the output cell
+    /// The constant is part of the gate, instead of being loaded and added with the add gate.
+    fn add_constant(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: Self::Num,
+        k: Fp,
+    ) -> Result<Self::Num, Error>;
+}
+
+pub(crate) trait DivInstructions {
+    type Num;
+
+    /// Define and assign a region for a div gate
+    /// - Copy the input values in the input cells
+    /// - Evaluate the output value (a/b) and the inverse of b, and store them in the output cells
+    /// - The gate is not satisfied if b = 0
+    fn div(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+}
+
+pub(crate) trait IsZeroInstructions {
+    type Num;
+
+    /// Define and assign a region for an is_zero gate
+    /// - Copy the input value in the input cell
+    /// - Evaluate the inverse of a (or 0 if a = 0) and the output value (1 if a = 0, 0 otherwise),
+    ///   and store them in the output cells
+    /// The output is constrained to be boolean, so it can be used as a selector in further constraints.
+    fn is_zero(&self, layouter: impl Layouter<Fp>, a: Self::Num) -> Result<Self::Num, Error>;
+}
+
+pub(crate) trait SelectInstructions {
+    type Num;
+
+    /// Define and assign a region for a select gate
+    /// - Copy the input values in the input cells
+    /// - Evaluate the output value (a if cond = 1, b if cond = 0) and store it in the output cell
+    /// - Constrain cond to be boolean
+    fn select(
+        &self,
+        layouter: impl Layouter<Fp>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+
+    /// Same as `select`, for a cond which is already constrained to be boolean
+    /// (e.g. the output of `is_zero`), so that the boolean constraint is not repeated.
+    fn select_bit(
+        &self,
+        layouter: impl Layouter<Fp>,
+        bit: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+}
+
 /// AddSubMulChip will implement our operations.
 pub(crate) struct AddSubMulChip {
     config: AddSubMulConfig,
@@ -100,6 +229,30 @@ pub(crate) struct MulChip {
     config: MulConfig,
 }
 
+pub(crate) struct SquareChip {
+    config: SquareConfig,
+}
+
+pub(crate) struct MulByConstantChip {
+    config: MulByConstantConfig,
+}
+
+pub(crate) struct AddConstantChip {
+    config: AddConstantConfig,
+}
+
+pub(crate) struct DivChip {
+    config: DivConfig,
+}
+
+pub(crate) struct IsZeroChip {
+    config: IsZeroConfig,
+}
+
+pub(crate) struct SelectChip {
+    config: SelectConfig,
+}
+
 /// Chip state is stored in a config struct.
 /// AddSubMulConfig is generated by the chip during configuration, and then stored inside the chip.
 #[derive(Clone, Debug)]
@@ -109,12 +262,25 @@ pub(crate) struct AddSubMulConfig {
     /// - 1 public input column
     /// - 1 selector to enable the addition gate
     /// - 1 selector to enable the subtraction gate
+    /// - 1 fixed column for constants
     /// - 1 selector to enable the multiplication gate
+    /// - 1 selector to enable the square gate
+    /// - 1 selector to enable the multiplication by constant gate
+    /// - 1 selector to enable the add constant gate
+    /// - 1 selector to enable the division gate
+    /// - 1 selector to enable the is_zero gate
+    /// - 2 selectors to enable the select gate and its boolean check
     advice: [Column<Advice>; 2],
     instance: Column<Instance>,
     add_config: AddConfig,
     sub_config: SubConfig,
     mul_config: MulConfig,
+    square_config: SquareConfig,
+    mul_by_constant_config: MulByConstantConfig,
+    add_constant_config: AddConstantConfig,
+    div_config: DivConfig,
+    is_zero_config: IsZeroConfig,
+    select_config: SelectConfig,
 }
 
 #[derive(Clone, Debug)]
@@ -144,6 +310,66 @@ pub(crate) struct MulConfig {
     s_mul: Selector,
 }
 
+#[derive(Clone, Debug)]
+pub(crate) struct SquareConfig {
+    /// For our chip, we will use
+    /// - 2 advice columns
+    /// - 1 selector to enable the square gate
+    advice: [Column<Advice>; 2],
+    s_square: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct MulByConstantConfig {
+    /// For our chip, we will use
+    /// - 2 advice columns
+    /// - 1 fixed column for the constant
+    /// - 1 selector to enable the mul by constant gate
+    advice: [Column<Advice>; 2],
+    constant: Column<Fixed>,
+    s_mul_by_constant: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct AddConstantConfig {
+    /// For our chip, we will use
+    /// - 2 advice columns
+    /// - 1 fixed column for the constant
+    /// - 1 selector to enable the add constant gate
+    advice: [Column<Advice>; 2],
+    constant: Column<Fixed>,
+    s_add_constant: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct DivConfig {
+    /// For our chip, we will use
+    /// - 2 advice columns
+    /// - 1 selector to enable the div gate
+    advice: [Column<Advice>; 2],
+    s_div: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct IsZeroConfig {
+    /// For our chip, we will use
+    /// - 2 advice columns
+    /// - 1 selector to enable the is_zero gate
+    advice: [Column<Advice>; 2],
+    s_is_zero: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SelectConfig {
+    /// For our chip, we will use
+    /// - 2 advice columns
+    /// - 1 selector to enable the select gate
+    /// - 1 selector to enable the boolean check on the condition
+    advice: [Column<Advice>; 2],
+    s_select: Selector,
+    s_bool: Selector,
+}
+
 impl Chip<Fp> for AddSubMulChip {
     type Config = AddSubMulConfig;
     type Loaded = ();
@@ -195,6 +421,84 @@ impl Chip<Fp> for MulChip {
         &()
     }
 }
+
+impl Chip<Fp> for SquareChip {
+    type Config = SquareConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Chip<Fp> for MulByConstantChip {
+    type Config = MulByConstantConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Chip<Fp> for AddConstantChip {
+    type Config = AddConstantConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Chip<Fp> for DivChip {
+    type Config = DivConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Chip<Fp> for IsZeroChip {
+    type Config = IsZeroConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Chip<Fp> for SelectChip {
+    type Config = SelectConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
 impl AddChip {
     pub(crate) fn new(config: AddConfig) -> Self {
         Self { config }
@@ -230,6 +534,11 @@ impl AddInstructions for AddSubMulChip {
         let add_chip = AddChip::new(self.config.add_config.clone());
         add_chip.add(layouter, a, b)
     }
+
+    fn neg(&self, layouter: impl Layouter<Fp>, a: Self::Num) -> Result<Self::Num, Error> {
+        let add_chip = AddChip::new(self.config.add_config.clone());
+        add_chip.neg(layouter, a)
+    }
 }
 
 impl AddInstructions for AddChip {
@@ -255,6 +564,28 @@ impl AddInstructions for AddChip {
             },
         )
     }
+
+    fn neg(&self, mut layouter: impl Layouter<Fp>, a: Self::Num) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "neg",
+            |mut region| {
+                self.config.s_add.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "lhs", &mut region, self.config.advice[0], 0)?;
+
+                let value = a.value().map(|a| -*a);
+                let out = region.assign_advice(|| "-lhs", self.config.advice[1], 0, || value)?;
+
+                region.assign_advice_from_constant(
+                    || "lhs + out",
+                    self.config.advice[0],
+                    1,
+                    Fp::zero(),
+                )?;
+                Ok(out)
+            },
+        )
+    }
 }
 
 impl SubChip {
@@ -383,6 +714,437 @@ impl MulInstructions for MulChip {
     }
 }
 
+impl SquareChip {
+    pub(crate) fn new(config: SquareConfig) -> Self {
+        Self { config }
+    }
+
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; 2],
+    ) -> SquareConfig {
+        let s_square = meta.selector();
+
+        meta.create_gate("square", |meta| {
+            let value = meta.query_advice(advice[0], Rotation::cur());
+            let out = meta.query_advice(advice[1], Rotation::cur());
+            let s_square = meta.query_selector(s_square);
+            vec![s_square * (value.clone() * value - out)]
+        });
+
+        SquareConfig { advice, s_square }
+    }
+}
+
+impl SquareInstructions for AddSubMulChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn square(&self, layouter: impl Layouter<Fp>, a: Self::Num) -> Result<Self::Num, Error> {
+        let square_chip = SquareChip::new(self.config.square_config.clone());
+        square_chip.square(layouter, a)
+    }
+}
+
+impl SquareInstructions for SquareChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn square(&self, mut layouter: impl Layouter<Fp>, a: Self::Num) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "square",
+            |mut region| {
+                self.config.s_square.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "value", &mut region, self.config.advice[0], 0)?;
+
+                let value = a.value().map(|a| a.square());
+
+                region.assign_advice(|| "value * value", self.config.advice[1], 0, || value)
+            },
+        )
+    }
+}
+
+impl MulByConstantChip {
+    pub(crate) fn new(config: MulByConstantConfig) -> Self {
+        Self { config }
+    }
+
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; 2],
+        constant: Column<Fixed>,
+    ) -> MulByConstantConfig {
+        let s_mul_by_constant = meta.selector();
+
+        meta.create_gate("mul by constant", |meta| {
+            let lhs = meta.query_advice(advice[0], Rotation::cur());
+            let k = meta.query_fixed(constant, Rotation::cur());
+            let out = meta.query_advice(advice[1], Rotation::cur());
+            let s_mul_by_constant = meta.query_selector(s_mul_by_constant);
+            vec![s_mul_by_constant * (lhs * k - out)]
+        });
+
+        MulByConstantConfig {
+            advice,
+            constant,
+            s_mul_by_constant,
+        }
+    }
+}
+
+impl MulByConstantInstructions for AddSubMulChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn mul_by_constant(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: Self::Num,
+        k: Fp,
+    ) -> Result<Self::Num, Error> {
+        let mul_by_constant_chip =
+            MulByConstantChip::new(self.config.mul_by_constant_config.clone());
+        mul_by_constant_chip.mul_by_constant(layouter, a, k)
+    }
+}
+
+impl MulByConstantInstructions for MulByConstantChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn mul_by_constant(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: Self::Num,
+        k: Fp,
+    ) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "mul by constant",
+            |mut region| {
+                self.config.s_mul_by_constant.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "lhs", &mut region, self.config.advice[0], 0)?;
+                region.assign_fixed(|| "k", self.config.constant, 0, || Value::known(k))?;
+
+                let value = a.value().map(|a| *a * k);
+
+                region.assign_advice(|| "lhs * k", self.config.advice[1], 0, || value)
+            },
+        )
+    }
+}
+
+impl AddConstantChip {
+    pub(crate) fn new(config: AddConstantConfig) -> Self {
+        Self { config }
+    }
+
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; 2],
+        constant: Column<Fixed>,
+    ) -> AddConstantConfig {
+        let s_add_constant = meta.selector();
+
+        meta.create_gate("add constant", |meta| {
+            let lhs = meta.query_advice(advice[0], Rotation::cur());
+            let k = meta.query_fixed(constant, Rotation::cur());
+            let out = meta.query_advice(advice[1], Rotation::cur());
+            let s_add_constant = meta.query_selector(s_add_constant);
+            vec![s_add_constant * (lhs + k - out)]
+        });
+
+        AddConstantConfig {
+            advice,
+            constant,
+            s_add_constant,
+        }
+    }
+}
+
+impl AddConstantInstructions for AddSubMulChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn add_constant(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: Self::Num,
+        k: Fp,
+    ) -> Result<Self::Num, Error> {
+        let add_constant_chip = AddConstantChip::new(self.config.add_constant_config.clone());
+        add_constant_chip.add_constant(layouter, a, k)
+    }
+}
+
+impl AddConstantInstructions for AddConstantChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn add_constant(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: Self::Num,
+        k: Fp,
+    ) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "add constant",
+            |mut region| {
+                self.config.s_add_constant.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "lhs", &mut region, self.config.advice[0], 0)?;
+                region.assign_fixed(|| "k", self.config.constant, 0, || Value::known(k))?;
+
+                let value = a.value().map(|a| *a + k);
+
+                region.assign_advice(|| "lhs + k", self.config.advice[1], 0, || value)
+            },
+        )
+    }
+}
+
+impl DivChip {
+    pub(crate) fn new(config: DivConfig) -> Self {
+        Self { config }
+    }
+
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; 2],
+    ) -> DivConfig {
+        let s_div = meta.selector();
+
+        meta.create_gate("div", |meta| {
+            let lhs = meta.query_advice(advice[0], Rotation::cur());
+            let rhs = meta.query_advice(advice[1], Rotation::cur());
+            let out = meta.query_advice(advice[0], Rotation::next());
+            let rhs_inv = meta.query_advice(advice[1], Rotation::next());
+            let s_div = meta.query_selector(s_div);
+            vec![
+                // out * rhs = lhs
+                s_div.clone() * (out * rhs.clone() - lhs),
+                // rhs * rhs_inv = 1, i.e. rhs != 0
+                s_div * (rhs * rhs_inv - Expression::Constant(Fp::one())),
+            ]
+        });
+
+        DivConfig { advice, s_div }
+    }
+}
+
+impl DivInstructions for AddSubMulChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn div(
+        &self,
+        layouter: impl Layouter<Fp>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        let div_chip = DivChip::new(self.config.div_config.clone());
+        div_chip.div(layouter, a, b)
+    }
+}
+
+impl DivInstructions for DivChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn div(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "div",
+            |mut region| {
+                self.config.s_div.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "lhs", &mut region, self.config.advice[0], 0)?;
+                b.copy_advice(|| "rhs", &mut region, self.config.advice[1], 0)?;
+
+                // if b = 0, there is no inverse and the gate is not satisfied
+                let rhs_inv = b.value().map(|b| b.invert().unwrap_or(Fp::zero()));
+                region.assign_advice(|| "1 / rhs", self.config.advice[1], 1, || rhs_inv)?;
+
+                let value = a.value().copied() * rhs_inv;
+
+                region.assign_advice(|| "lhs / rhs", self.config.advice[0], 1, || value)
+            },
+        )
+    }
+}
+
+impl IsZeroChip {
+    pub(crate) fn new(config: IsZeroConfig) -> Self {
+        Self { config }
+    }
+
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; 2],
+    ) -> IsZeroConfig {
+        let s_is_zero = meta.selector();
+
+        meta.create_gate("is_zero", |meta| {
+            let value = meta.query_advice(advice[0], Rotation::cur());
+            let value_inv = meta.query_advice(advice[1], Rotation::cur());
+            let out = meta.query_advice(advice[0], Rotation::next());
+            let s_is_zero = meta.query_selector(s_is_zero);
+            vec![
+                // value * value_inv = 1 - out, so out = 1 if value = 0
+                s_is_zero.clone()
+                    * (value.clone() * value_inv - (Expression::Constant(Fp::one()) - out.clone())),
+                // value * out = 0, so out = 0 if value != 0
+                s_is_zero * (value * out),
+            ]
+        });
+
+        IsZeroConfig { advice, s_is_zero }
+    }
+}
+
+impl IsZeroInstructions for AddSubMulChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn is_zero(&self, layouter: impl Layouter<Fp>, a: Self::Num) -> Result<Self::Num, Error> {
+        let is_zero_chip = IsZeroChip::new(self.config.is_zero_config.clone());
+        is_zero_chip.is_zero(layouter, a)
+    }
+}
+
+impl IsZeroInstructions for IsZeroChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn is_zero(&self, mut layouter: impl Layouter<Fp>, a: Self::Num) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "is_zero",
+            |mut region| {
+                self.config.s_is_zero.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "value", &mut region, self.config.advice[0], 0)?;
+
+                let value_inv = a.value().map(|a| a.invert().unwrap_or(Fp::zero()));
+                region.assign_advice(|| "1 / value", self.config.advice[1], 0, || value_inv)?;
+
+                let out = a
+                    .value()
+                    .map(|a| if a.is_zero_vartime() { Fp::one() } else { Fp::zero() });
+
+                region.assign_advice(|| "value == 0", self.config.advice[0], 1, || out)
+            },
+        )
+    }
+}
+
+impl SelectChip {
+    pub(crate) fn new(config: SelectConfig) -> Self {
+        Self { config }
+    }
+
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; 2],
+    ) -> SelectConfig {
+        let s_select = meta.selector();
+        let s_bool = meta.selector();
+
+        meta.create_gate("select", |meta| {
+            let cond = meta.query_advice(advice[0], Rotation::cur());
+            let a = meta.query_advice(advice[1], Rotation::cur());
+            let b = meta.query_advice(advice[0], Rotation::next());
+            let out = meta.query_advice(advice[1], Rotation::next());
+            let s_select = meta.query_selector(s_select);
+            vec![s_select * (cond * (a - b.clone()) + b - out)]
+        });
+
+        meta.create_gate("select bool", |meta| {
+            let cond = meta.query_advice(advice[0], Rotation::cur());
+            let s_bool = meta.query_selector(s_bool);
+            vec![s_bool * cond.clone() * (Expression::Constant(Fp::one()) - cond)]
+        });
+
+        SelectConfig {
+            advice,
+            s_select,
+            s_bool,
+        }
+    }
+
+    fn assign(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        cond: AssignedCell<Fp, Fp>,
+        a: AssignedCell<Fp, Fp>,
+        b: AssignedCell<Fp, Fp>,
+        check_bool: bool,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "select",
+            |mut region| {
+                self.config.s_select.enable(&mut region, 0)?;
+                if check_bool {
+                    self.config.s_bool.enable(&mut region, 0)?;
+                }
+
+                cond.copy_advice(|| "cond", &mut region, self.config.advice[0], 0)?;
+                a.copy_advice(|| "a", &mut region, self.config.advice[1], 0)?;
+                b.copy_advice(|| "b", &mut region, self.config.advice[0], 1)?;
+
+                let value = cond.value().copied() * (a.value().copied() - b.value()) + b.value();
+
+                region.assign_advice(|| "cond ? a : b", self.config.advice[1], 1, || value)
+            },
+        )
+    }
+}
+
+impl SelectInstructions for AddSubMulChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn select(
+        &self,
+        layouter: impl Layouter<Fp>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        let select_chip = SelectChip::new(self.config.select_config.clone());
+        select_chip.select(layouter, cond, a, b)
+    }
+
+    fn select_bit(
+        &self,
+        layouter: impl Layouter<Fp>,
+        bit: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        let select_chip = SelectChip::new(self.config.select_config.clone());
+        select_chip.select_bit(layouter, bit, a, b)
+    }
+}
+
+impl SelectInstructions for SelectChip {
+    type Num = AssignedCell<Fp, Fp>;
+
+    fn select(
+        &self,
+        layouter: impl Layouter<Fp>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        self.assign(layouter, cond, a, b, true)
+    }
+
+    fn select_bit(
+        &self,
+        layouter: impl Layouter<Fp>,
+        bit: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        self.assign(layouter, bit, a, b, false)
+    }
+}
+
 impl AddSubMulChip {
     pub(crate) fn new(config: AddSubMulConfig) -> Self {
         Self { config }
@@ -397,6 +1159,12 @@ impl AddSubMulChip {
         let add_config = AddChip::configure(meta, advice);
         let sub_config = SubChip::configure(meta, advice);
         let mul_config = MulChip::configure(meta, advice);
+        let square_config = SquareChip::configure(meta, advice);
+        let mul_by_constant_config = MulByConstantChip::configure(meta, advice, constant);
+        let add_constant_config = AddConstantChip::configure(meta, advice, constant);
+        let div_config = DivChip::configure(meta, advice);
+        let is_zero_config = IsZeroChip::configure(meta, advice);
+        let select_config = SelectChip::configure(meta, advice);
 
         meta.enable_equality(instance);
         meta.enable_constant(constant);
@@ -410,6 +1178,12 @@ impl AddSubMulChip {
             add_config,
             sub_config,
             mul_config,
+            square_config,
+            mul_by_constant_config,
+            add_constant_config,
+            div_config,
+            is_zero_config,
+            select_config,
         }
     }
 }
@@ -450,7 +1224,7 @@ impl AddSubMulInstructions for AddSubMulChip {
         )
     }
 
-    /// Check that a number is equal to a public input.
+    /// Check that a number is equal to the public input at the given row.
     fn check_result(
         &self,
         mut layouter: impl Layouter<Fp>,
@@ -459,6 +1233,28 @@ impl AddSubMulInstructions for AddSubMulChip {
     ) -> Result<(), Error> {
         layouter.constrain_instance(num.cell(), self.config.instance, row)
     }
+
+    /// Check that a number is zero, against the public input at row 0.
+    fn check_zero(
+        &self,
+        layouter: impl Layouter<Fp>,
+        num: <Self as AddSubMulInstructions>::Num,
+    ) -> Result<(), Error> {
+        self.check_result(layouter, num, 0)
+    }
+
+    /// Check that two numbers are equal, with an equality constraint between their cells.
+    fn constrain_equal(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: <Self as AddSubMulInstructions>::Num,
+        b: <Self as AddSubMulInstructions>::Num,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "constrain equal",
+            |mut region| region.constrain_equal(a.cell(), b.cell()),
+        )
+    }
 }
 
 /// The full circuit implementation.
@@ -570,3 +1366,302 @@ pub fn run_add_sub_example() {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::dev::{MockProver, VerifyFailure};
+
+    /// The instruction checked by the test circuit
+    #[derive(Clone, Copy, Debug, Default)]
+    enum Op {
+        #[default]
+        Div,
+        // neg(a) + a
+        Neg,
+        // a, constrained to be equal to b
+        ConstrainEqual,
+        // square(a), constrained to be equal to mul(a, a)
+        Square,
+        // (a + b) * k, with k a constant
+        MulByConstant,
+        // (a + b) * k, with k loaded as a constant
+        MulLoadedConstant,
+        // (a + b) + k, with k a constant
+        AddConstant,
+        // a^3 + k - b^2, zero if (a, b) is on the curve y^2 = x^3 + k
+        CurveEquation,
+        IsZero,
+        // select(c, a, b)
+        Select,
+        // select_bit(is_zero(c), a, b)
+        SelectIsZero,
+        // a - b, checked with check_zero
+        CheckZero,
+    }
+
+    /// Apply an instruction to a, b and c, and check that the result is equal to the public input.
+    #[derive(Default, Clone)]
+    struct TestCircuit {
+        op: Op,
+        // constant of the circuit
+        k: Fp,
+        // row of the instance checked against the result
+        row: usize,
+        a: Value<Fp>,
+        b: Value<Fp>,
+        c: Value<Fp>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = AddSubMulConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                op: self.op,
+                k: self.k,
+                row: self.row,
+                ..Self::default()
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advice = [meta.advice_column(), meta.advice_column()];
+            let instance = meta.instance_column();
+            let constant = meta.fixed_column();
+
+            AddSubMulChip::configure(meta, advice, instance, constant)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip = AddSubMulChip::new(config);
+
+            let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+            let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+            let c = chip.load_private(layouter.namespace(|| "load c"), self.c)?;
+
+            let out = match self.op {
+                Op::Div => chip.div(layouter.namespace(|| "a / b"), a, b)?,
+                Op::ConstrainEqual => {
+                    chip.constrain_equal(layouter.namespace(|| "a == b"), a.clone(), b)?;
+                    a
+                }
+                Op::Neg => {
+                    let neg = chip.neg(layouter.namespace(|| "-a"), a.clone())?;
+                    chip.add(layouter.namespace(|| "-a + a"), neg, a)?
+                }
+                Op::Square => {
+                    let square = chip.square(layouter.namespace(|| "square(a)"), a.clone())?;
+                    let mul = chip.mul(layouter.namespace(|| "a * a"), a.clone(), a)?;
+                    chip.constrain_equal(
+                        layouter.namespace(|| "square(a) == a * a"),
+                        square.clone(),
+                        mul,
+                    )?;
+                    square
+                }
+                Op::MulByConstant | Op::MulLoadedConstant => {
+                    let sum = chip.add(layouter.namespace(|| "a + b"), a, b)?;
+                    if let Op::MulByConstant = self.op {
+                        chip.mul_by_constant(layouter.namespace(|| "(a + b) * k"), sum, self.k)?
+                    } else {
+                        let k = chip.load_constant(layouter.namespace(|| "load k"), self.k)?;
+                        chip.mul(layouter.namespace(|| "(a + b) * k"), sum, k)?
+                    }
+                }
+                Op::AddConstant => {
+                    let sum = chip.add(layouter.namespace(|| "a + b"), a, b)?;
+                    chip.add_constant(layouter.namespace(|| "(a + b) + k"), sum, self.k)?
+                }
+                Op::CurveEquation => {
+                    let a_square = chip.square(layouter.namespace(|| "a^2"), a.clone())?;
+                    let a_cube = chip.mul(layouter.namespace(|| "a^3"), a_square, a)?;
+                    let rhs = chip.add_constant(layouter.namespace(|| "a^3 + k"), a_cube, self.k)?;
+                    let b_square = chip.square(layouter.namespace(|| "b^2"), b)?;
+                    chip.sub(layouter.namespace(|| "a^3 + k - b^2"), rhs, b_square)?
+                }
+                Op::IsZero => chip.is_zero(layouter.namespace(|| "a == 0"), a)?,
+                Op::Select => chip.select(layouter.namespace(|| "c ? a : b"), c, a, b)?,
+                Op::SelectIsZero => {
+                    let bit = chip.is_zero(layouter.namespace(|| "c == 0"), c)?;
+                    chip.select_bit(layouter.namespace(|| "c == 0 ? a : b"), bit, a, b)?
+                }
+                Op::CheckZero => {
+                    let res = chip.sub(layouter.namespace(|| "a - b"), a, b)?;
+                    return chip.check_zero(layouter.namespace(|| "check a - b == 0"), res);
+                }
+            };
+
+            chip.check_result(layouter.namespace(|| "check res"), out, self.row)
+        }
+    }
+
+    fn run(op: Op, a: u64, b: u64, expected: u64) -> Result<(), Vec<VerifyFailure>> {
+        run_with_c(op, a, b, 0, expected)
+    }
+
+    fn run_with_c(
+        op: Op,
+        a: u64,
+        b: u64,
+        c: u64,
+        expected: u64,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let circuit = TestCircuit {
+            op,
+            // c is also used as the constant
+            k: Fp::from(c),
+            row: 0,
+            a: Value::known(Fp::from(a)),
+            b: Value::known(Fp::from(b)),
+            c: Value::known(Fp::from(c)),
+        };
+        let prover = MockProver::run(5, &circuit, vec![vec![Fp::from(expected)]]).unwrap();
+        prover.verify()
+    }
+
+    #[test]
+    fn div() {
+        // 6 / 3 = 2
+        assert_eq!(run(Op::Div, 6, 3, 2), Ok(()));
+        assert!(run(Op::Div, 6, 3, 3).is_err());
+
+        // 6 / 0 is rejected
+        assert!(run(Op::Div, 6, 0, 0).is_err());
+    }
+
+    #[test]
+    fn is_zero() {
+        assert_eq!(run(Op::IsZero, 0, 0, 1), Ok(()));
+        assert!(run(Op::IsZero, 0, 0, 0).is_err());
+
+        assert_eq!(run(Op::IsZero, 7, 0, 0), Ok(()));
+        assert!(run(Op::IsZero, 7, 0, 1).is_err());
+    }
+
+    #[test]
+    fn select() {
+        assert_eq!(run_with_c(Op::Select, 3, 5, 1, 3), Ok(()));
+        assert_eq!(run_with_c(Op::Select, 3, 5, 0, 5), Ok(()));
+        assert!(run_with_c(Op::Select, 3, 5, 0, 3).is_err());
+
+        // a non-boolean cond is rejected, even if out = cond * (a - b) + b
+        assert!(run_with_c(Op::Select, 7, 5, 2, 9).is_err());
+
+        // an already-constrained bit
+        assert_eq!(run_with_c(Op::SelectIsZero, 3, 5, 0, 3), Ok(()));
+        assert_eq!(run_with_c(Op::SelectIsZero, 3, 5, 4, 5), Ok(()));
+    }
+
+    #[test]
+    fn square() {
+        for _ in 0..5 {
+            let a = Fp::random(OsRng);
+            let circuit = TestCircuit {
+                op: Op::Square,
+                a: Value::known(a),
+                ..TestCircuit::default()
+            };
+            let prover = MockProver::run(5, &circuit, vec![vec![a * a]]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        // 3^2 = 9
+        assert_eq!(run(Op::Square, 3, 0, 9), Ok(()));
+        assert!(run(Op::Square, 3, 0, 6).is_err());
+    }
+
+    #[test]
+    fn mul_by_constant() {
+        // (2 + 5) * 5 = 35
+        assert_eq!(run_with_c(Op::MulByConstant, 2, 5, 5, 35), Ok(()));
+        assert_eq!(run_with_c(Op::MulLoadedConstant, 2, 5, 5, 35), Ok(()));
+        assert!(run_with_c(Op::MulByConstant, 2, 5, 5, 36).is_err());
+    }
+
+    #[test]
+    fn add_constant() {
+        // (2 + 5) + 5 = 12
+        assert_eq!(run_with_c(Op::AddConstant, 2, 5, 5, 12), Ok(()));
+        assert!(run_with_c(Op::AddConstant, 2, 5, 5, 13).is_err());
+    }
+
+    #[test]
+    fn curve_equation() {
+        use group::{Curve, Group};
+        use pasta_curves::arithmetic::CurveAffine;
+
+        let check = |x: Fp, y: Fp| {
+            let circuit = TestCircuit {
+                op: Op::CurveEquation,
+                k: pallas::Affine::b(),
+                a: Value::known(x),
+                b: Value::known(y),
+                ..TestCircuit::default()
+            };
+            let prover = MockProver::run(5, &circuit, vec![vec![Fp::zero()]]).unwrap();
+            prover.verify()
+        };
+
+        for _ in 0..5 {
+            let point = pallas::Point::random(OsRng).to_affine();
+            let coordinates = point.coordinates().unwrap();
+            let (x, y) = (*coordinates.x(), *coordinates.y());
+            assert_eq!(check(x, y), Ok(()));
+            assert_eq!(check(x, -y), Ok(()));
+
+            // off-curve points
+            assert!(check(x, y + Fp::one()).is_err());
+            assert!(check(x + Fp::one(), y).is_err());
+        }
+    }
+
+    #[test]
+    fn check_result_at_row() {
+        // (2 + 5) + 1 = 8, at row 0 or at row 3 of an instance whose other rows are 9
+        for row in [0, 3] {
+            let circuit = TestCircuit {
+                op: Op::AddConstant,
+                k: Fp::one(),
+                row,
+                a: Value::known(Fp::from(2)),
+                b: Value::known(Fp::from(5)),
+                c: Value::known(Fp::zero()),
+            };
+            let mut instance = vec![Fp::from(9); 4];
+            instance[row] = Fp::from(8);
+            let prover = MockProver::run(5, &circuit, vec![instance.clone()]).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "row {}", row);
+
+            // the result is checked against its row only
+            instance.swap(row, (row + 1) % 4);
+            let prover = MockProver::run(5, &circuit, vec![instance]).unwrap();
+            assert!(prover.verify().is_err(), "row {}", row);
+        }
+    }
+
+    #[test]
+    fn check_zero() {
+        assert_eq!(run(Op::CheckZero, 7, 7, 0), Ok(()));
+        assert!(run(Op::CheckZero, 7, 6, 0).is_err());
+        // against row 0 of the instance
+        assert!(run(Op::CheckZero, 7, 7, 1).is_err());
+    }
+
+    #[test]
+    fn neg() {
+        assert_eq!(run(Op::Neg, 7, 0, 0), Ok(()));
+        assert!(run(Op::Neg, 7, 0, 14).is_err());
+    }
+
+    #[test]
+    fn constrain_equal() {
+        assert_eq!(run(Op::ConstrainEqual, 3, 3, 3), Ok(()));
+        assert!(run(Op::ConstrainEqual, 3, 4, 3).is_err());
+    }
+}
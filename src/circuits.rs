@@ -1,2 +1,643 @@
+//! Circuits for verifiable encryption, and the proving / verification API shared by them.
+//! The proving API and the constructors of the circuits from witnesses are only available with
+//! the `prover` feature, which is enabled by default: verifiers can build the crate with
+//! `--no-default-features --features std`.
+use crate::circuits::verifiable_encryption::{empty_circuit, VeEncCircuit, K};
+use crate::circuits::verifiable_encryption_batch::VeEncBatchCircuit;
+use ff::{Field, PrimeField};
+use group::{Curve, Group};
+use halo2_gadgets::sinsemilla::primitives as sinsemilla;
+use halo2_proofs::dev::CircuitCost;
+use halo2_proofs::plonk::{
+    self, BatchVerifier, Circuit, ConstraintSystem, Error, SingleVerifier, VerificationStrategy,
+    VerifyingKey,
+};
+use halo2_proofs::poly::commitment::{Guard, Params, MSM};
+use halo2_proofs::transcript::{Blake2bRead, Challenge255, EncodedChallenge};
+use pasta_curves::arithmetic::{Coordinates, CurveAffine};
+use pasta_curves::{pallas, vesta};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+#[cfg(feature = "instrument")]
+use std::time::{Duration, Instant};
+use std::{fmt, fs, io};
+#[cfg(feature = "prover")]
+use {
+    halo2_proofs::plonk::ProvingKey,
+    halo2_proofs::transcript::Blake2bWrite,
+    rand::{rngs::OsRng, RngCore},
+};
+
+pub mod ciphertext_well_formedness;
+pub mod key_knowledge;
+pub mod verifiable_decryption;
 pub mod verifiable_encryption;
+pub mod verifiable_encryption_batch;
+pub mod verifiable_encryption_equal_messages;
+pub mod verifiable_encryption_with_commitment;
+pub mod verifiable_encryption_with_disclosure;
+pub mod verifiable_encryption_with_message_hash;
+pub mod verifiable_encryption_with_range;
 pub mod verifiable_encryption_with_relation;
+pub mod verifiable_encryption_with_threshold;
+
+/// Largest K tried by [`find_minimum_k`]
+const MAX_K: u32 = 20;
+
+/// Magic bytes at the start of a proof created by [`prove_encryption`]
+pub const PROOF_MAGIC: [u8; 4] = *b"QVEP";
+
+/// Version of the circuits and of the encoding, written in the proof header.
+/// It must be bumped whenever a change makes proofs of the previous version invalid.
+pub const CIRCUIT_VERSION: u8 = 4;
+
+/// Size of the proof header: the magic bytes, the circuit version and K
+pub const PROOF_HEADER_SIZE: usize = PROOF_MAGIC.len() + 2;
+
+#[cfg(feature = "prover")]
+fn proof_header(params: &Params<vesta::Affine>) -> [u8; PROOF_HEADER_SIZE] {
+    let mut header = [0u8; PROOF_HEADER_SIZE];
+    header[..PROOF_MAGIC.len()].copy_from_slice(&PROOF_MAGIC);
+    header[PROOF_MAGIC.len()] = CIRCUIT_VERSION;
+    header[PROOF_MAGIC.len() + 1] = params.k() as u8;
+    header
+}
+
+fn invalid_header(message: String) -> Error {
+    Error::Transcript(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+/// Check the header of a proof created by [`prove_encryption`] against the params,
+/// and return the halo2 proof that follows it.
+/// Fails with an `InvalidData` transcript error if the magic bytes, the circuit version or K
+/// differ.
+fn strip_proof_header<'a>(
+    params: &Params<vesta::Affine>,
+    proof: &'a [u8],
+) -> Result<&'a [u8], Error> {
+    if proof.len() < PROOF_HEADER_SIZE || proof[..PROOF_MAGIC.len()] != PROOF_MAGIC {
+        return Err(invalid_header(
+            "not a verifiable encryption proof".to_string(),
+        ));
+    }
+    let version = proof[PROOF_MAGIC.len()];
+    if version != CIRCUIT_VERSION {
+        return Err(invalid_header(format!(
+            "proof for circuit version {}, expected version {}",
+            version, CIRCUIT_VERSION
+        )));
+    }
+    let k = proof[PROOF_MAGIC.len() + 1];
+    if u32::from(k) != params.k() {
+        return Err(invalid_header(format!(
+            "proof for K = {}, expected K = {}",
+            k,
+            params.k()
+        )));
+    }
+    Ok(&proof[PROOF_HEADER_SIZE..])
+}
+
+/// Find the smallest K such that the circuit fits in 2^K rows, including its regions,
+/// its lookup tables and the blinding rows. The circuit may be without witnesses.
+/// Any K larger than the minimum can be used to create the params.
+pub fn find_minimum_k<C: Circuit<pallas::Base>>(circuit: &C) -> Result<u32, Error> {
+    find_minimum_k_from(circuit, 1)
+}
+
+// Same as find_minimum_k, for a circuit known not to fit in 2^(k - 1) rows
+fn find_minimum_k_from<C: Circuit<pallas::Base>>(circuit: &C, k: u32) -> Result<u32, Error> {
+    for k in k..=MAX_K {
+        match plonk::keygen_vk(&Params::new(k), circuit) {
+            Ok(_) => return Ok(k),
+            Err(Error::NotEnoughRowsAvailable { .. }) => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Err(Error::NotEnoughRowsAvailable { current_k: MAX_K })
+}
+
+/// Recommended K for a proof of `num_blocks` encryptions, so that callers do not choose K:
+/// the smallest K such that a VeEncCircuit, for a single block, or a VeEncBatchCircuit of
+/// the blocks fits in 2^K rows. The 10-bit lookup table of the ECC chip, the Sinsemilla table,
+/// fills 2^10 rows once for all the blocks and leaves no room for the blinding rows, so the
+/// search starts at K = 11. The rows of the ECC operations of the blocks are counted by laying
+/// out the circuit, so K is never too small, and is not larger than needed.
+/// A single block fits in 2^K rows, for K = [`K`]. The K of a batch is computed once per number
+/// of blocks, and cached.
+///
+/// Panics if `num_blocks` is 0, or if the blocks do not fit in 2^MAX_K rows.
+pub fn recommended_k(num_blocks: usize) -> u32 {
+    static BATCH_K: Mutex<BTreeMap<usize, u32>> = Mutex::new(BTreeMap::new());
+
+    assert!(num_blocks > 0, "at least one block is required");
+    if num_blocks == 1 {
+        return K;
+    }
+    let mut batch_k = BATCH_K.lock().unwrap_or_else(|error| error.into_inner());
+    *batch_k.entry(num_blocks).or_insert_with(|| {
+        let block = dummy_circuit().without_witnesses();
+        let circuit = VeEncBatchCircuit::new(vec![block; num_blocks]);
+        find_minimum_k_from(&circuit, sinsemilla::K as u32 + 1)
+            .expect("the blocks fit in 2^MAX_K rows")
+    })
+}
+
+/// Coordinates (x, y) of a point, as written in the public instance of the circuits.
+/// Fails with `Error::InvalidInstances` for the identity, which has no affine coordinates.
+pub fn point_to_xy(point: &pallas::Point) -> Result<(pallas::Base, pallas::Base), Error> {
+    let coordinates: Option<Coordinates<pallas::Affine>> = point.to_affine().coordinates().into();
+    let coordinates = coordinates.ok_or(Error::InvalidInstances)?;
+    Ok((*coordinates.x(), *coordinates.y()))
+}
+
+// Errors returned when converting between the pallas scalar and base fields
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConversionError {
+    ScalarOutOfRange, // the scalar is not smaller than the base field modulus
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::ScalarOutOfRange => {
+                write!(f, "the scalar does not fit in the pallas base field")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Convert a scalar to the base field element with the same integer value, e.g. to witness
+/// r_enc, which the circuit multiplies with as a base field element.
+/// The base modulus p is smaller than the scalar modulus q, so the conversion is lossless for
+/// the scalars in [0, p), and fails with `ConversionError::ScalarOutOfRange` for the scalars
+/// in [p, q) instead of reducing them modulo p. A uniform scalar is in [p, q) with probability
+/// below 2^-167. The range check is constant time, only its result is branched on.
+pub fn scalar_to_base(scalar: &pallas::Scalar) -> Result<pallas::Base, ConversionError> {
+    let base: Option<pallas::Base> = pallas::Base::from_repr(scalar.to_repr()).into();
+    base.ok_or(ConversionError::ScalarOutOfRange)
+}
+
+/// Random encryption randomness r_enc which the circuits can witness: the scalars which do not
+/// fit in the base field, see [`scalar_to_base`], are resampled.
+#[cfg(feature = "prover")]
+pub fn random_r_enc() -> pallas::Scalar {
+    loop {
+        let r_enc = pallas::Scalar::random(OsRng);
+        if scalar_to_base(&r_enc).is_ok() {
+            return r_enc;
+        }
+    }
+}
+
+/// Size of a VeEncCircuit, as given by the halo2 cost model
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitStats {
+    // size of a proof in bytes
+    pub proof_size: usize,
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    // maximum degree of the gates and lookups
+    pub degree: usize,
+}
+
+/// Size in bytes of a proof of `num_blocks` encryptions, including its header, without
+/// creating the proof.
+/// `num_blocks` must be at least 1, and `k` large enough for the blocks (see [`find_minimum_k`]).
+pub fn estimate_proof_size(k: u32, num_blocks: usize) -> usize {
+    assert!(num_blocks > 0, "at least one block is required");
+    let cost = if num_blocks == 1 {
+        CircuitCost::<vesta::Point, _>::measure(k, &dummy_circuit()).proof_size(1)
+    } else {
+        let circuit = VeEncBatchCircuit::new(vec![dummy_circuit(); num_blocks]);
+        CircuitCost::<vesta::Point, _>::measure(k, &circuit).proof_size(1)
+    };
+    PROOF_HEADER_SIZE + usize::from(cost)
+}
+
+/// Proof size and columns of a single block VeEncCircuit at the default K.
+pub fn estimate_constraints() -> CircuitStats {
+    let mut cs = ConstraintSystem::default();
+    VeEncCircuit::configure(&mut cs);
+    CircuitStats {
+        proof_size: estimate_proof_size(K, 1),
+        advice_columns: cs.num_advice_columns(),
+        fixed_columns: cs.num_fixed_columns(),
+        instance_columns: cs.num_instance_columns(),
+        degree: cs.degree(),
+    }
+}
+
+// The cost only depends on the circuit shape, so any public key can be used.
+fn dummy_circuit() -> VeEncCircuit {
+    empty_circuit(pallas::Point::generator())
+}
+
+/// Generate the verification key and the proving key of a circuit.
+/// The keys only depend on the params and on the circuit shape, so a circuit without witnesses
+/// can be used.
+#[cfg(feature = "prover")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(k = params.k())))]
+pub fn keygen<C: Circuit<pallas::Base>>(
+    params: &Params<vesta::Affine>,
+    circuit: &C,
+) -> Result<(VerifyingKey<vesta::Affine>, ProvingKey<vesta::Affine>), Error> {
+    let vk = plonk::keygen_vk(params, circuit)?;
+    let pk = plonk::keygen_pk(params, vk.clone(), circuit)?;
+    Ok((vk, pk))
+}
+
+/// Generate the verification key of a circuit, without the proving key, e.g. for a verifier.
+/// The key is the same as the one returned by [`keygen`] for the same params and circuit shape.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(k = params.k())))]
+pub fn keygen_vk<C: Circuit<pallas::Base>>(
+    params: &Params<vesta::Affine>,
+    circuit: &C,
+) -> Result<VerifyingKey<vesta::Affine>, Error> {
+    plonk::keygen_vk(params, circuit)
+}
+
+/// Serialize the params.
+///
+/// halo2_proofs does not expose a serialization of `VerifyingKey` and `ProvingKey`.
+/// The keys are a deterministic function of the params and the circuit shape, so they are
+/// persisted by storing the params and regenerating them with [`keygen`] after [`read_params`].
+/// The regenerated keys verify proofs made with the original keys, as long as the params
+/// (in particular `K`) and the circuit are the same.
+pub fn write_params(params: &Params<vesta::Affine>) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    params.write(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Parse params serialized by [`write_params`].
+pub fn read_params(mut bytes: &[u8]) -> io::Result<Params<vesta::Affine>> {
+    Params::read(&mut bytes)
+}
+
+/// Read the params of size 2^k from the file at `path`, or create them with `Params::new(k)`
+/// and write them to the file if it does not exist, so that repeated runs do not regenerate
+/// them. The file is written to a temporary file first and renamed, so an interrupted run does
+/// not leave truncated params behind.
+/// Fails with an `InvalidData` error if the cached params are not params for k.
+pub fn load_or_create_params(path: impl AsRef<Path>, k: u32) -> io::Result<Params<vesta::Affine>> {
+    let path = path.as_ref();
+    match fs::read(path) {
+        Ok(bytes) => {
+            let params = read_params(&bytes)?;
+            if params.k() != k {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("cached params for K = {}, expected K = {}", params.k(), k),
+                ));
+            }
+            Ok(params)
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            let params = Params::new(k);
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, write_params(&params)?)?;
+            fs::rename(&tmp, path)?;
+            Ok(params)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Create a proof for a single circuit against its public instance (one slice per instance column).
+/// The proof is created with a Blake2b transcript and returned as bytes, after a header with
+/// [`PROOF_MAGIC`], [`CIRCUIT_VERSION`] and the K of the params.
+#[cfg(feature = "prover")]
+pub fn prove_encryption<C: Circuit<pallas::Base>>(
+    params: &Params<vesta::Affine>,
+    pk: &ProvingKey<vesta::Affine>,
+    circuit: C,
+    instance: &[&[vesta::Scalar]],
+) -> Result<Vec<u8>, Error> {
+    prove_encryption_with_rng(params, pk, circuit, instance, OsRng)
+}
+
+/// Same as [`prove_encryption`], with the randomness of the proof taken from `rng`
+/// instead of `OsRng`, e.g. on targets where the operating system RNG is not available.
+#[cfg(feature = "prover")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "prove", skip_all, fields(k = params.k()))
+)]
+pub fn prove_encryption_with_rng<C: Circuit<pallas::Base>, R: RngCore>(
+    params: &Params<vesta::Affine>,
+    pk: &ProvingKey<vesta::Affine>,
+    circuit: C,
+    instance: &[&[vesta::Scalar]],
+    rng: R,
+) -> Result<Vec<u8>, Error> {
+    let mut transcript =
+        Blake2bWrite::<_, vesta::Affine, Challenge255<_>>::init(proof_header(params).to_vec());
+    plonk::create_proof(params, pk, &[circuit], &[instance], rng, &mut transcript)?;
+    Ok(transcript.finalize())
+}
+
+/// Same as [`prove_encryption`], run on the blocking thread pool of the current tokio runtime,
+/// so that proving does not block the async tasks of the runtime.
+/// The task may outlive the caller, so the params, the proving key and the instance are owned,
+/// and can be shared between proofs with an `Arc`.
+/// Fails with an `Interrupted` transcript error if the runtime shuts down before the proof is
+/// created, and resumes the panic of the proof, if any.
+///
+/// Panics if it is not called from within a tokio runtime.
+#[cfg(feature = "tokio")]
+pub async fn prove_encryption_async<C: Circuit<pallas::Base> + Send + 'static>(
+    params: std::sync::Arc<Params<vesta::Affine>>,
+    pk: std::sync::Arc<ProvingKey<vesta::Affine>>,
+    circuit: C,
+    instance: Vec<Vec<vesta::Scalar>>,
+) -> Result<Vec<u8>, Error> {
+    let proof = tokio::task::spawn_blocking(move || {
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        prove_encryption(&params, &pk, circuit, &instance)
+    });
+    match proof.await {
+        Ok(proof) => proof,
+        Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+        Err(error) => Err(Error::Transcript(io::Error::new(
+            io::ErrorKind::Interrupted,
+            error,
+        ))),
+    }
+}
+
+/// Prove the encryption of each block concurrently, with one proof per block.
+/// Each proof has its own transcript and its own `OsRng`, so the blocks do not share any state
+/// and the proofs are the same as with [`prove_encryption`] called on each block.
+///
+/// The speedup depends on the number of cores: halo2 already parallelizes some steps of a
+/// single proof, so it is lower than the number of cores. The `parallel` group of
+/// `cargo bench --bench prove --features parallel` compares the sequential and parallel proofs
+/// of an 8-block message.
+#[cfg(feature = "parallel")]
+pub fn prove_all_blocks(
+    params: &Params<vesta::Affine>,
+    pk: &ProvingKey<vesta::Affine>,
+    circuits: &[VeEncCircuit],
+) -> Result<Vec<Vec<u8>>, Error> {
+    use crate::circuits::verifiable_encryption::VeEncInstance;
+    use rayon::prelude::*;
+
+    circuits
+        .par_iter()
+        .enumerate()
+        .map(|(index, circuit)| {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!("block", index).entered();
+            #[cfg(not(feature = "tracing"))]
+            let _ = index;
+            let instance =
+                VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                    .to_halo2_instance()?;
+            let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+            prove_encryption(params, pk, circuit.clone(), &instance)
+        })
+        .collect()
+}
+
+/// Verify a proof created by [`prove_encryption`] against its public instance.
+/// The header is checked first: a proof for another circuit version or another K
+/// is rejected with an `InvalidData` transcript error, without reading the transcript.
+/// Verification does not use any randomness or threads, so it runs on wasm32.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "verify", skip_all, fields(k = params.k()))
+)]
+pub fn verify_encryption(
+    params: &Params<vesta::Affine>,
+    vk: &VerifyingKey<vesta::Affine>,
+    instance: &[&[vesta::Scalar]],
+    proof: &[u8],
+) -> Result<(), Error> {
+    let proof = strip_proof_header(params, proof)?;
+    let strategy = SingleVerifier::new(params);
+    let mut transcript = Blake2bRead::<_, vesta::Affine, Challenge255<_>>::init(proof);
+    plonk::verify_proof(params, vk, strategy, &[instance], &mut transcript)
+}
+
+/// Durations of the phases of a proof and size of the proof, filled by [`keygen_with_stats`],
+/// [`prove_encryption_with_stats`] and [`verify_encryption_with_stats`], for performance
+/// tuning. A phase which was not run is None.
+#[cfg(feature = "instrument")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProveStats {
+    pub keygen: Option<Duration>,
+    pub prove: Option<Duration>,
+    pub verify: Option<Duration>,
+    // size in bytes of the proof, including its header
+    pub proof_size: usize,
+}
+
+/// Same as [`keygen`], and record its duration in `stats`.
+#[cfg(all(feature = "instrument", feature = "prover"))]
+pub fn keygen_with_stats<C: Circuit<pallas::Base>>(
+    params: &Params<vesta::Affine>,
+    circuit: &C,
+    stats: &mut ProveStats,
+) -> Result<(VerifyingKey<vesta::Affine>, ProvingKey<vesta::Affine>), Error> {
+    let start = Instant::now();
+    let keys = keygen(params, circuit)?;
+    stats.keygen = Some(start.elapsed());
+    Ok(keys)
+}
+
+/// Same as [`prove_encryption`], and record its duration and the size of the proof in `stats`.
+#[cfg(all(feature = "instrument", feature = "prover"))]
+pub fn prove_encryption_with_stats<C: Circuit<pallas::Base>>(
+    params: &Params<vesta::Affine>,
+    pk: &ProvingKey<vesta::Affine>,
+    circuit: C,
+    instance: &[&[vesta::Scalar]],
+    stats: &mut ProveStats,
+) -> Result<Vec<u8>, Error> {
+    let start = Instant::now();
+    let proof = prove_encryption(params, pk, circuit, instance)?;
+    stats.prove = Some(start.elapsed());
+    stats.proof_size = proof.len();
+    Ok(proof)
+}
+
+/// Same as [`verify_encryption`], and record its duration and the size of the proof in
+/// `stats`. The duration is recorded whether the proof is valid or not.
+#[cfg(feature = "instrument")]
+pub fn verify_encryption_with_stats(
+    params: &Params<vesta::Affine>,
+    vk: &VerifyingKey<vesta::Affine>,
+    instance: &[&[vesta::Scalar]],
+    proof: &[u8],
+    stats: &mut ProveStats,
+) -> Result<(), Error> {
+    let start = Instant::now();
+    let result = verify_encryption(params, vk, instance, proof);
+    stats.verify = Some(start.elapsed());
+    stats.proof_size = proof.len();
+    result
+}
+
+/// Verify several proofs created by [`prove_encryption`] with the same verifying key,
+/// each against its own public instance.
+/// The proofs are checked together with halo2's batch verifier, so that the cost of the
+/// multi-scalar multiplication is shared. The batch fails if any proof is invalid.
+///
+/// Security assumptions: the transcript of each proof is still read and checked, and the final
+/// multi-scalar multiplications (MSMs) of the proofs are combined with random factors drawn
+/// by the verifier, so that an invalid proof makes the combined MSM the identity only with
+/// negligible probability. Beyond that, soundness is the one of each proof: the discrete
+/// logarithm assumption on vesta, and the Fiat-Shamir transform with BLAKE2b. The proofs
+/// are not compressed, only the MSM is shared; see also [`verify_accumulated`] for proofs
+/// received one at a time.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(k = params.k(), proofs = proofs.len()))
+)]
+pub fn verify_batch(
+    params: &Params<vesta::Affine>,
+    vk: &VerifyingKey<vesta::Affine>,
+    instances: &[&[&[vesta::Scalar]]],
+    proofs: &[Vec<u8>],
+) -> Result<(), Error> {
+    if instances.len() != proofs.len() {
+        return Err(Error::InvalidInstances);
+    }
+
+    let mut batch = BatchVerifier::new();
+    for (instance, proof) in instances.iter().zip(proofs.iter()) {
+        let instance = instance.iter().map(|column| column.to_vec()).collect();
+        let proof = strip_proof_header(params, proof)?;
+        batch.add_proof(vec![instance], proof.to_vec());
+    }
+
+    if batch.finalize(params, vk) {
+        Ok(())
+    } else {
+        Err(Error::ConstraintSystemFailure)
+    }
+}
+
+/// Accumulator of the proofs checked by [`verify_accumulated`], whose final check is deferred.
+///
+/// Verifying a proof reads its transcript and reduces it to a multi-scalar multiplication
+/// (MSM), which is the identity if the proof is valid. Evaluating the MSM is the expensive
+/// part of the verification, linear in 2^K. The accumulator keeps a random linear combination
+/// of the MSMs of the proofs, so that [`ProofAccumulator::finalize`] evaluates a single MSM for
+/// all of them: it is the identity if every proof is valid, and otherwise with negligible
+/// probability. Until it is finalized, no accumulated proof is known to be valid.
+pub struct ProofAccumulator<'params> {
+    msm: MSM<'params, vesta::Affine>,
+    proofs: usize,
+}
+
+impl<'params> ProofAccumulator<'params> {
+    pub fn new(params: &'params Params<vesta::Affine>) -> Self {
+        ProofAccumulator {
+            msm: params.empty_msm(),
+            proofs: 0,
+        }
+    }
+
+    /// Number of proofs accumulated
+    pub fn len(&self) -> usize {
+        self.proofs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs == 0
+    }
+
+    /// Run the deferred check of the accumulated proofs.
+    /// Returns true if they are all valid, which is the case for an empty accumulator.
+    pub fn finalize(self) -> bool {
+        self.msm.eval()
+    }
+}
+
+// Verification strategy returning the MSM of a proof instead of evaluating it
+struct AccumulationStrategy<'params> {
+    msm: MSM<'params, vesta::Affine>,
+}
+
+impl<'params> VerificationStrategy<'params, vesta::Affine> for AccumulationStrategy<'params> {
+    type Output = MSM<'params, vesta::Affine>;
+
+    fn process<E: EncodedChallenge<vesta::Affine>>(
+        self,
+        f: impl FnOnce(Self::Output) -> Result<Guard<'params, vesta::Affine, E>, Error>,
+    ) -> Result<Self::Output, Error> {
+        let guard = f(self.msm)?;
+        Ok(guard.use_challenges())
+    }
+}
+
+/// Verify a proof created by [`prove_encryption`] against its public instance, and add it to
+/// the accumulator, which is returned for further proofs.
+/// Only the header and the transcript of the proof are checked here: a malformed proof fails,
+/// but a well-formed invalid proof is only detected by [`ProofAccumulator::finalize`].
+/// [`verify_encryption`] is the same as finalizing an accumulator of a single proof.
+pub fn verify_accumulated<'params>(
+    params: &'params Params<vesta::Affine>,
+    vk: &VerifyingKey<vesta::Affine>,
+    instance: &[&[vesta::Scalar]],
+    proof: &[u8],
+    mut accumulator: ProofAccumulator<'params>,
+) -> Result<ProofAccumulator<'params>, Error> {
+    let proof = strip_proof_header(params, proof)?;
+    let strategy = AccumulationStrategy {
+        msm: params.empty_msm(),
+    };
+    let mut transcript = Blake2bRead::<_, vesta::Affine, Challenge255<_>>::init(proof);
+    let msm = plonk::verify_proof(params, vk, strategy, &[instance], &mut transcript)?;
+
+    // a random factor, so that the MSMs of invalid proofs cannot cancel each other
+    accumulator
+        .msm
+        .scale(vesta::Scalar::random(rand::rngs::OsRng));
+    accumulator.msm.add_msm(&msm);
+    accumulator.proofs += 1;
+    Ok(accumulator)
+}
+
+#[cfg(all(test, not(feature = "prover")))]
+mod tests {
+    use super::verifiable_encryption::{empty_circuit, VeEncInstance};
+    use super::{keygen_vk, verify_encryption, K};
+    use crate::elgamal::extended_elgamal::DataInTransmit;
+    use crate::fixtures;
+    use group::GroupEncoding;
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+
+    // Built with `cargo test --no-default-features --features std`: the verifier links without
+    // the proving API, and verifies the proof of the single-block test vector, created by the
+    // prover in test_vectors and committed as a fixture.
+    #[test]
+    fn verify_only() {
+        let fixture = fixtures::read("single_block");
+        let public_key =
+            pallas::Point::from_bytes(&fixture["public_key"][..].try_into().unwrap()).unwrap();
+        let data_in_transmit = DataInTransmit::from_bytes(&fixture["data_in_transmit"]).unwrap();
+        let proof = &fixture["proof"];
+
+        let params = Params::new(K);
+        let vk = keygen_vk(&params, &empty_circuit(public_key)).unwrap();
+        let instance = VeEncInstance::new(data_in_transmit, public_key)
+            .to_halo2_instance()
+            .unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        assert!(verify_encryption(&params, &vk, &instance, proof).is_ok());
+
+        // the last scalar of the transcript is changed, the header is still valid
+        let mut tampered = proof.clone();
+        let last_scalar = tampered.len() - 32;
+        tampered[last_scalar] ^= 1;
+        assert!(verify_encryption(&params, &vk, &instance, &tampered).is_err());
+    }
+}
@@ -0,0 +1,569 @@
+/// Format a circuit and an instance for the well-formedness of an ElGamal ciphertext
+/// A round trip test to prove that a ciphertext is an encryption of a message point p_m,
+/// without the Encode constraints (1.1) and (1.2) of task1, e.g. when p_m comes from another
+/// step of a protocol and its encoding is proven separately.
+///
+/// Prove:
+/// (2) C = ElGamal.Enc(pk, p_m)
+/// (2.1) ct_1 = [r_enc]G, G is a base point of E, the generator of E by default
+/// (2.2) ct_2 = p_m + [r_enc]pk_elgamal
+///
+/// - secret input `p_m`;
+/// - secret input `r_enc`;
+/// - public group element `ct_1 := [r_enc]G`
+/// - public group element `ct_2 := p_m + [r]elgamal_public_key`
+/// - public group element `elgamal_public_key`
+/// - public group element `G`, the ElGamal base point
+///
+/// Without Encode, the proof only shows that the prover knows r_enc and the point p_m that the
+/// ciphertext encrypts: nothing binds p_m to a message, and `r_encode` is not an input.
+/// The circuit only configures the ECC chip, with its lookup range check, and not the add,
+/// sub, mul chip of task1, so its proof is smaller and it saves the rows of the encode checks.
+/// K cannot be lowered, as the lookup table fills 2^10 rows. The constraints (2.1) and (2.2)
+/// are shared with task1, see `check_elgamal_encryption`.
+
+use crate::circuits::point_to_xy;
+use crate::circuits::verifiable_encryption::{WitnessError, ADVICE_COLUMNS, FIXED_COLUMNS};
+use crate::constants::fixed_bases::VerifiableEncryptionFixedBases;
+use crate::elgamal::elgamal::ElGamalCiphertext;
+use crate::gadgets::constrain_point_instance;
+use group::{Curve, Group};
+use halo2_gadgets::ecc::chip::{EccChip, EccConfig};
+use halo2_gadgets::ecc::{NonIdentityPoint, Point, ScalarVar};
+use halo2_gadgets::utilities::lookup_range_check::LookupRangeCheckConfig;
+use halo2_gadgets::utilities::UtilitiesInstructions;
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance as InstanceColumn},
+};
+use pasta_curves::{pallas, vesta};
+#[cfg(feature = "prover")]
+use {
+    crate::circuits::{random_r_enc, scalar_to_base, ConversionError},
+    crate::elgamal::elgamal::{elgamal_encrypt_with_randomness, Witness},
+    crate::elgamal::extended_elgamal::EncryptError,
+};
+
+const ELGAMAL_CT1_X: usize = 0;
+const ELGAMAL_CT1_Y: usize = 1;
+const ELGAMAL_CT2_X: usize = 2;
+const ELGAMAL_CT2_Y: usize = 3;
+const ELGAMAL_PK_X: usize = 4;
+const ELGAMAL_PK_Y: usize = 5;
+const GENERATOR_X: usize = 6;
+const GENERATOR_Y: usize = 7;
+
+/// Number of public inputs of the well-formedness of a ciphertext
+pub(crate) const WELL_FORMEDNESS_INSTANCE_SIZE: usize = 8;
+
+/// Configuration of the well-formedness of a ciphertext: the ECC chip, whose lookup range
+/// check loads the 10-bit table, on the columns of task1, without the add, sub, mul chip.
+#[derive(Clone, Debug)]
+pub struct WellFormednessConfig {
+    pub(crate) instance: Column<InstanceColumn>,
+    pub(crate) ecc_config: EccConfig<VerifiableEncryptionFixedBases>,
+}
+
+impl WellFormednessConfig {
+    pub(crate) fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self {
+        let advices = [(); ADVICE_COLUMNS].map(|_| meta.advice_column());
+        let table_idx = meta.lookup_table_column();
+        let table_range_check_tag = meta.lookup_table_column();
+        let lagrange_coeffs = [(); FIXED_COLUMNS].map(|_| meta.fixed_column());
+
+        // Instance column used for public inputs
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        // Permutation over all advice columns.
+        for advice in advices.iter() {
+            meta.enable_equality(*advice);
+        }
+
+        // Shared fixed column for loading constants
+        meta.enable_constant(lagrange_coeffs[0]);
+
+        let range_check =
+            LookupRangeCheckConfig::configure(meta, advices[9], table_idx, table_range_check_tag);
+        let ecc_config = EccChip::<VerifiableEncryptionFixedBases>::configure(
+            meta,
+            advices,
+            lagrange_coeffs,
+            range_check,
+        );
+
+        WellFormednessConfig {
+            instance,
+            ecc_config,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WellFormednessCircuit {
+    pub(crate) ciphertext: ElGamalCiphertext,
+    pub(crate) elgamal_public_key: pallas::Point,
+    pub(crate) generator: pallas::Point,
+    pub(crate) p_m: Value<pallas::Point>,
+    pub(crate) r_enc: Value<pallas::Base>,
+}
+
+// The base point defaults to the generator of pallas, the identity is not a valid base point.
+impl Default for WellFormednessCircuit {
+    fn default() -> Self {
+        WellFormednessCircuit {
+            ciphertext: ElGamalCiphertext::default(),
+            elgamal_public_key: pallas::Point::default(),
+            generator: pallas::Point::generator(),
+            p_m: Value::unknown(),
+            r_enc: Value::unknown(),
+        }
+    }
+}
+
+impl Circuit<pallas::Base> for WellFormednessCircuit {
+    type Config = WellFormednessConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    // The public points are witnessed as non-identity points, so they are kept.
+    fn without_witnesses(&self) -> Self {
+        Self {
+            ciphertext: self.ciphertext.clone(),
+            elgamal_public_key: self.elgamal_public_key,
+            generator: self.generator,
+            ..Self::default()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        WellFormednessConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        // Load 10-bit lookup table, used by the variable-base scalar multiplications.
+        config.ecc_config.lookup_config.load(&mut layouter)?;
+
+        self.synthesize_well_formedness(config, layouter, 0)?;
+        Ok(())
+    }
+}
+
+impl WellFormednessCircuit {
+    /// Check that the points witnessed by the circuit are not the identity.
+    /// p_m is only checked when it is known, i.e. not for a circuit without witnesses.
+    pub fn check_witnesses(&self) -> Result<(), WitnessError> {
+        if bool::from(self.elgamal_public_key.is_identity()) {
+            return Err(WitnessError::IdentityPublicKey);
+        }
+        if bool::from(self.generator.is_identity()) {
+            return Err(WitnessError::IdentityGenerator);
+        }
+        let mut identity_p_m = false;
+        self.p_m.map(|p_m| identity_p_m = bool::from(p_m.is_identity()));
+        if identity_p_m {
+            return Err(WitnessError::IdentityMessagePoint);
+        }
+        Ok(())
+    }
+
+    /// Witness p_m and r_enc and constrain the ciphertext to be an encryption of p_m, whose
+    /// public inputs start at row `instance_offset` of the instance column.
+    /// The lookup table must have been loaded by the caller.
+    /// Returns p_m, so that callers can constrain it, e.g. to the point of a separate Encode.
+    pub(crate) fn synthesize_well_formedness(
+        &self,
+        config: WellFormednessConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+        instance_offset: usize,
+    ) -> Result<NonIdentityPoint<pallas::Affine, EccChip<VerifiableEncryptionFixedBases>>, Error>
+    {
+        self.check_witnesses()?;
+        let ecc_chip = EccChip::<VerifiableEncryptionFixedBases>::construct(config.ecc_config);
+        let column = ecc_chip.config().advices[0];
+
+        // witness message point p_m
+        let p_m = NonIdentityPoint::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "load p_m"),
+            self.p_m.as_ref().map(|p_m| p_m.to_affine()),
+        )?;
+
+        // load r_enc
+        let assigned_r_enc =
+            ecc_chip.load_private(layouter.namespace(|| "load r_enc"), column, self.r_enc)?;
+
+        // elgamal_public_key
+        let elgamal_public_key = NonIdentityPoint::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "load elgamal_public_key"),
+            Value::known(self.elgamal_public_key.to_affine()),
+        )?;
+
+        // ElGamal base point
+        let generator = NonIdentityPoint::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "load generator"),
+            Value::known(self.generator.to_affine()),
+        )?;
+
+        check_elgamal_encryption(
+            layouter,
+            config.instance,
+            ecc_chip,
+            &p_m,
+            &assigned_r_enc,
+            elgamal_public_key,
+            generator,
+            instance_offset,
+        )?;
+        Ok(p_m)
+    }
+}
+
+/// (2) C = ElGamal.Enc(pk, p_m): constrain the public points of the encryption of p_m with the
+/// randomness r_enc, laid out as the instance of this circuit from row `instance_offset` of
+/// the instance column. Also used by task1, after the constraints of Encode.
+pub(crate) fn check_elgamal_encryption(
+    mut layouter: impl Layouter<pallas::Base>,
+    instance: Column<InstanceColumn>,
+    ecc_chip: EccChip<VerifiableEncryptionFixedBases>,
+    p_m: &NonIdentityPoint<pallas::Affine, EccChip<VerifiableEncryptionFixedBases>>,
+    assigned_r_enc: &AssignedCell<pallas::Base, pallas::Base>,
+    elgamal_public_key: NonIdentityPoint<pallas::Affine, EccChip<VerifiableEncryptionFixedBases>>,
+    generator: NonIdentityPoint<pallas::Affine, EccChip<VerifiableEncryptionFixedBases>>,
+    instance_offset: usize,
+) -> Result<(), Error> {
+    // r_enc, converted once for [r_enc]generator and [r_enc]pk
+    let r_enc = ScalarVar::from_base(ecc_chip, layouter.namespace(|| "r_enc"), assigned_r_enc)?;
+
+    // (2.1) ct_1 = [r_enc]generator
+
+    // Constrain generator to equal public input G
+    constrain_point_instance(
+        layouter.namespace(|| "constrain generator"),
+        &Point::from(generator.clone()),
+        instance,
+        instance_offset + GENERATOR_X,
+        instance_offset + GENERATOR_Y,
+    )?;
+
+    // compute [r_enc]generator
+    let (ct1_expected, r_enc) = generator.mul(layouter.namespace(|| "[r_enc]generator"), r_enc)?;
+
+    // Constrain ct1_expected to equal public input ct1
+    constrain_point_instance(
+        layouter.namespace(|| "constrain ct_1"),
+        &ct1_expected,
+        instance,
+        instance_offset + ELGAMAL_CT1_X,
+        instance_offset + ELGAMAL_CT1_Y,
+    )?;
+
+    // (2.2) ct_2 = p_m +[r_enc]pk
+    // Constrain elgamal_public_key to equal public input pk
+    constrain_point_instance(
+        layouter.namespace(|| "constrain elgamal_public_key"),
+        &Point::from(elgamal_public_key.clone()),
+        instance,
+        instance_offset + ELGAMAL_PK_X,
+        instance_offset + ELGAMAL_PK_Y,
+    )?;
+
+    // Compute [r_enc]elgamal_public_key
+    let (r_mul_pk, _) =
+        elgamal_public_key.mul(layouter.namespace(|| "[r_enc]elgamal_public_key"), r_enc)?;
+
+    // Compute ct_2_expected = [r_enc]elgamal_public_key + p_m
+    let ct_2_expected =
+        r_mul_pk.add(layouter.namespace(|| "[r_enc]elgamal_public_key+p_m"), p_m)?;
+
+    // Constrain ct_2_expected to equal public input ct_2
+    constrain_point_instance(
+        layouter.namespace(|| "constrain ct_2"),
+        &ct_2_expected,
+        instance,
+        instance_offset + ELGAMAL_CT2_X,
+        instance_offset + ELGAMAL_CT2_Y,
+    )
+}
+
+/// Public inputs
+#[derive(Clone, Debug)]
+pub struct WellFormednessInstance {
+    pub(crate) ciphertext: ElGamalCiphertext,
+    pub(crate) elgamal_public_key: pallas::Point,
+    pub(crate) generator: pallas::Point,
+}
+
+impl WellFormednessInstance {
+    /// Instance of a ciphertext with the generator of pallas as base point.
+    pub fn new(ciphertext: ElGamalCiphertext, elgamal_public_key: pallas::Point) -> Self {
+        Self::new_with_generator(ciphertext, elgamal_public_key, pallas::Point::generator())
+    }
+
+    /// Instance of a ciphertext with a custom base point.
+    pub fn new_with_generator(
+        ciphertext: ElGamalCiphertext,
+        elgamal_public_key: pallas::Point,
+        generator: pallas::Point,
+    ) -> Self {
+        WellFormednessInstance {
+            ciphertext,
+            elgamal_public_key,
+            generator,
+        }
+    }
+
+    /// Public inputs of the circuit.
+    /// Fails with `Error::InvalidInstances` if one of the points is the identity.
+    pub fn to_halo2_instance(
+        &self,
+    ) -> Result<[[vesta::Scalar; WELL_FORMEDNESS_INSTANCE_SIZE]; 1], Error> {
+        let (ct1_x, ct1_y) = point_to_xy(&self.ciphertext.c1)?;
+        let (ct2_x, ct2_y) = point_to_xy(&self.ciphertext.c2)?;
+        let (pk_x, pk_y) = point_to_xy(&self.elgamal_public_key)?;
+        let (generator_x, generator_y) = point_to_xy(&self.generator)?;
+
+        let mut instance = [vesta::Scalar::zero(); WELL_FORMEDNESS_INSTANCE_SIZE];
+        instance[ELGAMAL_CT1_X] = ct1_x;
+        instance[ELGAMAL_CT1_Y] = ct1_y;
+        instance[ELGAMAL_CT2_X] = ct2_x;
+        instance[ELGAMAL_CT2_Y] = ct2_y;
+        instance[ELGAMAL_PK_X] = pk_x;
+        instance[ELGAMAL_PK_Y] = pk_y;
+        instance[GENERATOR_X] = generator_x;
+        instance[GENERATOR_Y] = generator_y;
+        Ok([instance])
+    }
+}
+
+/// Encrypt the message point p_m to the public key, and create a circuit for the
+/// well-formedness of the ciphertext and its public instance.
+/// Fails with `EncryptError::InvalidPublicKey` if the public key is the identity, and with
+/// `EncryptError::InvalidMessagePoint` if p_m is the identity.
+#[cfg(feature = "prover")]
+pub fn create_circuit(
+    p_m: pallas::Point,
+    elgamal_public_key: pallas::Point,
+) -> Result<(WellFormednessCircuit, WellFormednessInstance), EncryptError> {
+    if bool::from(elgamal_public_key.is_identity()) {
+        return Err(EncryptError::InvalidPublicKey);
+    }
+    if bool::from(p_m.is_identity()) {
+        return Err(EncryptError::InvalidMessagePoint);
+    }
+
+    // r_enc is witnessed in the base field
+    let r_enc = random_r_enc();
+    let (ciphertext, witness) = elgamal_encrypt_with_randomness(&elgamal_public_key, p_m, r_enc);
+    let circuit = circuit_from_witness(ciphertext.clone(), elgamal_public_key, &witness)
+        .expect("r_enc fits in the base field");
+    Ok((circuit, WellFormednessInstance::new(ciphertext, elgamal_public_key)))
+}
+
+/// Create a circuit for a ciphertext made by the encryptor, e.g. with `elgamal_encrypt`,
+/// who knows the witness. The base point is the generator of pallas.
+/// Fails if r_enc does not fit in the base field, see `scalar_to_base`.
+#[cfg(feature = "prover")]
+pub fn circuit_from_witness(
+    ciphertext: ElGamalCiphertext,
+    elgamal_public_key: pallas::Point,
+    witness: &Witness,
+) -> Result<WellFormednessCircuit, ConversionError> {
+    Ok(WellFormednessCircuit {
+        ciphertext,
+        elgamal_public_key,
+        generator: pallas::Point::generator(),
+        p_m: Value::known(witness.p_m),
+        r_enc: Value::known(scalar_to_base(&witness.r_enc)?),
+    })
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{create_circuit, WellFormednessCircuit};
+    use crate::circuits::verifiable_encryption::{self, K};
+    use crate::circuits::{keygen, prove_encryption, verify_encryption};
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::elgamal::extended_elgamal::EncryptError;
+    use group::Group;
+    use halo2_proofs::circuit::{FloorPlanner, Value};
+    use halo2_proofs::dev::{CircuitCost, MockProver};
+    use halo2_proofs::plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+        Instance, Selector,
+    };
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn round_trip() {
+        let keypair = ElGamalKeypair::new();
+        let p_m = pallas::Point::random(OsRng);
+
+        // Step 1. encrypt p_m and create a circuit for the ciphertext
+        let (circuit, instance) = create_circuit(p_m, keypair.public_key).unwrap();
+
+        // Step 2. arrange the public instance.
+        let instance = instance.to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        // Setup phase, proving phase and verification against the public instance.
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        // the identity is neither a public key nor a message point
+        let identity = pallas::Point::identity();
+        assert_eq!(
+            create_circuit(p_m, identity).err(),
+            Some(EncryptError::InvalidPublicKey)
+        );
+        assert_eq!(
+            create_circuit(identity, keypair.public_key).err(),
+            Some(EncryptError::InvalidMessagePoint)
+        );
+    }
+
+    #[test]
+    fn wrong_message_point() {
+        let keypair = ElGamalKeypair::new();
+        let (circuit, instance) =
+            create_circuit(pallas::Point::random(OsRng), keypair.public_key).unwrap();
+        let instance = instance.to_halo2_instance().unwrap();
+
+        // another p_m does not give ct_2 with the same r_enc
+        let forged = WellFormednessCircuit {
+            p_m: Value::known(pallas::Point::random(OsRng)),
+            ..circuit
+        };
+        let prover = MockProver::run(K, &forged, instance.iter().map(|c| c.to_vec()).collect())
+            .unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // Counts the advice rows used by the regions of a circuit, as laid out by its floor
+    // planner. The fixed rows are not counted, as the lookup table fills 2^10 of them.
+    #[derive(Default)]
+    struct AdviceRows(usize);
+
+    impl Assignment<pallas::Base> for AdviceRows {
+        fn enter_region<NR, N>(&mut self, _: N)
+        where
+            NR: Into<String>,
+            N: FnOnce() -> NR,
+        {
+        }
+
+        fn exit_region(&mut self) {}
+
+        fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.0 = self.0.max(row + 1);
+            Ok(())
+        }
+
+        fn query_instance(
+            &self,
+            _: Column<Instance>,
+            _: usize,
+        ) -> Result<Value<pallas::Base>, Error> {
+            Ok(Value::unknown())
+        }
+
+        fn assign_advice<V, VR, A, AR>(
+            &mut self,
+            _: A,
+            _: Column<Advice>,
+            row: usize,
+            _: V,
+        ) -> Result<(), Error>
+        where
+            V: FnOnce() -> Value<VR>,
+            VR: Into<Assigned<pallas::Base>>,
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            self.0 = self.0.max(row + 1);
+            Ok(())
+        }
+
+        fn assign_fixed<V, VR, A, AR>(
+            &mut self,
+            _: A,
+            _: Column<Fixed>,
+            _: usize,
+            _: V,
+        ) -> Result<(), Error>
+        where
+            V: FnOnce() -> Value<VR>,
+            VR: Into<Assigned<pallas::Base>>,
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            Ok(())
+        }
+
+        fn copy(
+            &mut self,
+            _: Column<Any>,
+            _: usize,
+            _: Column<Any>,
+            _: usize,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn fill_from_row(
+            &mut self,
+            _: Column<Fixed>,
+            _: usize,
+            _: Value<Assigned<pallas::Base>>,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn push_namespace<NR, N>(&mut self, _: N)
+        where
+            NR: Into<String>,
+            N: FnOnce() -> NR,
+        {
+        }
+
+        fn pop_namespace(&mut self, _: Option<String>) {}
+    }
+
+    fn advice_rows<C: Circuit<pallas::Base>>(circuit: &C) -> usize {
+        let mut meta = ConstraintSystem::default();
+        let config = C::configure(&mut meta);
+        let mut rows = AdviceRows::default();
+        C::FloorPlanner::synthesize(&mut rows, circuit, config, meta.constants().clone())
+            .unwrap();
+        rows.0
+    }
+
+    #[test]
+    fn well_formedness_cost() {
+        let keypair = ElGamalKeypair::new();
+        let (circuit, _) =
+            create_circuit(pallas::Point::random(OsRng), keypair.public_key).unwrap();
+        let full = verifiable_encryption::create_circuit(pallas::Base::from(42), keypair);
+
+        // without the add, sub, mul chip, the proof is smaller at the same K
+        let light = CircuitCost::<vesta::Point, _>::measure(K, &circuit).proof_size(1);
+        let task1 = CircuitCost::<vesta::Point, _>::measure(K, &full).proof_size(1);
+        assert!(usize::from(light) < usize::from(task1));
+
+        // and the rows of Encode are saved
+        assert!(advice_rows(&circuit) < advice_rows(&full));
+    }
+}
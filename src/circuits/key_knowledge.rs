@@ -0,0 +1,250 @@
+/// Format a circuit and an instance for the knowledge of an ElGamal private key
+/// A round trip test to prove, as the decryptor, the knowledge of the private key sk
+/// of a public key, without revealing it, as a Schnorr proof does.
+///
+/// Prove:
+/// (1) pk = [sk]G, G is a base point of E, the generator of E by default
+///
+/// - secret input `sk`;
+/// - public group element `pk`
+/// - public group element `G`, the ElGamal base point
+///
+/// sk is witnessed as a base field element, as r_enc in task1, see `scalar_to_base`.
+
+use crate::circuits::verifiable_encryption::{VeConfig, VeEncCircuit};
+use crate::circuits::point_to_xy;
+use crate::constants::fixed_bases::VerifiableEncryptionFixedBases;
+use crate::gadgets::constrain_point_instance;
+use group::{Curve, Group};
+use halo2_gadgets::ecc::chip::EccChip;
+use halo2_gadgets::ecc::{NonIdentityPoint, Point, ScalarVar};
+use halo2_gadgets::utilities::UtilitiesInstructions;
+use halo2_proofs::{
+    circuit::{Chip, Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use pasta_curves::{pallas, vesta};
+#[cfg(feature = "prover")]
+use {
+    crate::circuits::{scalar_to_base, ConversionError},
+    crate::elgamal::elgamal::ElGamalKeypair,
+};
+
+const PK_X: usize = 0;
+const PK_Y: usize = 1;
+const GENERATOR_X: usize = 2;
+const GENERATOR_Y: usize = 3;
+
+/// Number of public inputs of the knowledge of a private key
+pub(crate) const KEY_INSTANCE_SIZE: usize = 4;
+
+#[derive(Clone)]
+pub struct KeyKnowledgeCircuit {
+    pub(crate) public_key: pallas::Point,
+    pub(crate) generator: pallas::Point,
+    pub(crate) private_key: Value<pallas::Base>,
+}
+
+// The base point defaults to the generator of pallas, the identity is not a valid base point.
+impl Default for KeyKnowledgeCircuit {
+    fn default() -> Self {
+        KeyKnowledgeCircuit {
+            public_key: pallas::Point::default(),
+            generator: pallas::Point::generator(),
+            private_key: Value::unknown(),
+        }
+    }
+}
+
+impl Circuit<pallas::Base> for KeyKnowledgeCircuit {
+    type Config = VeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    // The points are witnessed as non-identity points, so they are kept.
+    fn without_witnesses(&self) -> Self {
+        Self {
+            public_key: self.public_key,
+            generator: self.generator,
+            ..Self::default()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        VeEncCircuit::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        // Load 10-bit lookup table, used by the variable-base scalar multiplication.
+        config.ecc_config.lookup_config.load(&mut layouter)?;
+
+        let ecc_chip = EccChip::<VerifiableEncryptionFixedBases>::construct(config.ecc_config);
+        let column = ecc_chip.config().advices[0];
+
+        // load sk
+        let private_key =
+            ecc_chip.load_private(layouter.namespace(|| "load sk"), column, self.private_key)?;
+        let private_key =
+            ScalarVar::from_base(ecc_chip.clone(), layouter.namespace(|| "sk"), &private_key)?;
+
+        // ElGamal base point
+        let generator = NonIdentityPoint::new(
+            ecc_chip,
+            layouter.namespace(|| "load generator"),
+            Value::known(self.generator.to_affine()),
+        )?;
+
+        // Constrain generator to equal public input G
+        constrain_point_instance(
+            layouter.namespace(|| "constrain generator"),
+            &Point::from(generator.clone()),
+            config.instance,
+            GENERATOR_X,
+            GENERATOR_Y,
+        )?;
+
+        // (1) compute [sk]generator
+        let (public_key, _) = generator.mul(layouter.namespace(|| "[sk]generator"), private_key)?;
+
+        // Constrain [sk]generator to equal public input pk
+        constrain_point_instance(
+            layouter.namespace(|| "constrain pk"),
+            &Point::from(public_key),
+            config.instance,
+            PK_X,
+            PK_Y,
+        )
+    }
+}
+
+/// Public inputs
+#[derive(Clone, Debug)]
+pub struct KeyKnowledgeInstance {
+    pub(crate) public_key: pallas::Point,
+    pub(crate) generator: pallas::Point,
+}
+
+impl KeyKnowledgeInstance {
+    /// Instance of a public key for the generator of pallas as base point.
+    pub fn new(public_key: pallas::Point) -> Self {
+        Self::new_with_generator(public_key, pallas::Point::generator())
+    }
+
+    /// Instance of a public key for a custom base point.
+    pub fn new_with_generator(public_key: pallas::Point, generator: pallas::Point) -> Self {
+        KeyKnowledgeInstance {
+            public_key,
+            generator,
+        }
+    }
+
+    /// Public inputs of the circuit.
+    /// Fails with `Error::InvalidInstances` if one of the points is the identity.
+    pub fn to_halo2_instance(&self) -> Result<[[vesta::Scalar; KEY_INSTANCE_SIZE]; 1], Error> {
+        let (pk_x, pk_y) = point_to_xy(&self.public_key)?;
+        let (generator_x, generator_y) = point_to_xy(&self.generator)?;
+
+        let mut instance = [vesta::Scalar::zero(); KEY_INSTANCE_SIZE];
+        instance[PK_X] = pk_x;
+        instance[PK_Y] = pk_y;
+        instance[GENERATOR_X] = generator_x;
+        instance[GENERATOR_Y] = generator_y;
+        Ok([instance])
+    }
+}
+
+/// Create a circuit for the knowledge of the private key of the keypair,
+/// whose public key is [private_key]G for the generator G of pallas.
+/// Fails if the private key does not fit in the base field, see `scalar_to_base`.
+#[cfg(feature = "prover")]
+pub fn create_circuit(keypair: &ElGamalKeypair) -> Result<KeyKnowledgeCircuit, ConversionError> {
+    create_circuit_with_generator(keypair, pallas::Point::generator())
+}
+
+/// Create a circuit for the knowledge of the private key of a keypair generated for a custom
+/// base point G, e.g. with `ElGamalKeypair::new_with_generator`.
+#[cfg(feature = "prover")]
+pub fn create_circuit_with_generator(
+    keypair: &ElGamalKeypair,
+    generator: pallas::Point,
+) -> Result<KeyKnowledgeCircuit, ConversionError> {
+    Ok(KeyKnowledgeCircuit {
+        public_key: keypair.public_key,
+        generator,
+        private_key: Value::known(scalar_to_base(&keypair.private_key)?),
+    })
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{create_circuit, create_circuit_with_generator, KeyKnowledgeInstance};
+    use crate::circuits::verifiable_encryption::K;
+    use crate::circuits::{keygen, prove_encryption, scalar_to_base, verify_encryption};
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use group::Group;
+    use halo2_proofs::circuit::Value;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::Circuit;
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn round_trip() {
+        let keypair = ElGamalKeypair::new();
+
+        // Step 1. create a circuit for the private key
+        let circuit = create_circuit(&keypair).unwrap();
+
+        // Step 2. arrange the public instance.
+        let instance = KeyKnowledgeInstance::new(keypair.public_key)
+            .to_halo2_instance()
+            .unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        // Setup phase, proving phase and verification against the public instance.
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        // a keypair for a custom base point
+        let generator = pallas::Point::random(OsRng);
+        let keypair = ElGamalKeypair::new_with_generator(&generator);
+        let circuit = create_circuit_with_generator(&keypair, generator).unwrap();
+        let instance = KeyKnowledgeInstance::new_with_generator(keypair.public_key, generator)
+            .to_halo2_instance()
+            .unwrap();
+        let prover = MockProver::run(K, &circuit, instance.iter().map(|c| c.to_vec()).collect())
+            .unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn wrong_private_key() {
+        let keypair = ElGamalKeypair::new();
+        let other = ElGamalKeypair::new();
+        let instance = KeyKnowledgeInstance::new(keypair.public_key)
+            .to_halo2_instance()
+            .unwrap();
+
+        // the private key of another keypair, for the public key of the keypair
+        let mut circuit = create_circuit(&keypair).unwrap();
+        circuit.private_key = Value::known(scalar_to_base(&other.private_key).unwrap());
+        let prover = MockProver::run(K, &circuit, instance.iter().map(|c| c.to_vec()).collect())
+            .unwrap();
+        assert!(prover.verify().is_err());
+
+        // the genuine private key, against another public key
+        let circuit = create_circuit(&keypair).unwrap();
+        let instance = KeyKnowledgeInstance::new(other.public_key)
+            .to_halo2_instance()
+            .unwrap();
+        let prover = MockProver::run(K, &circuit, instance.iter().map(|c| c.to_vec()).collect())
+            .unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
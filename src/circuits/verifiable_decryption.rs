@@ -0,0 +1,431 @@
+/// Format a circuit and an instance for the decryption of an Extended ElGamal ciphertext
+/// A round trip test to prove a revealed message is the decryption of a public ciphertext,
+/// without revealing the private key. This is the verifiable decryption counterpart of task1.
+///
+/// Prove:
+/// (1) pk = [sk]G, G is a base point of E, the generator of E by default
+/// (2) p_m = ct_2 - [sk]ct_1, that is, ct_2 = p_m + [sk]ct_1
+/// (3) Decode(p_m; r_encode) = m, that is, p_m.x = r_encode + m
+///
+/// - secret input `sk`;
+/// - secret input `p_m`;
+/// - public group element `ct_1`
+/// - public group element `ct_2`
+/// - public group element `elgamal_public_key`
+/// - public group element `G`, the ElGamal base point
+/// - public random element `r_encode`
+/// - public element `m`, the decrypted message
+
+use crate::add_sub_mul::chip::{AddInstructions, AddSubMulChip, AddSubMulInstructions};
+use crate::circuits::verifiable_encryption::{VeConfig, VeEncCircuit};
+use crate::circuits::{point_to_xy, ConversionError};
+use crate::constants::fixed_bases::VerifiableEncryptionFixedBases;
+use crate::elgamal::extended_elgamal::{DataInTransmit, DecryptError};
+use crate::gadgets::constrain_point_instance;
+use group::{Curve, Group};
+use halo2_gadgets::ecc::chip::EccChip;
+use halo2_gadgets::ecc::{NonIdentityPoint, Point, ScalarVar};
+use halo2_gadgets::utilities::UtilitiesInstructions;
+use halo2_proofs::{
+    circuit::{Chip, Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use pasta_curves::{pallas, vesta};
+use std::fmt;
+#[cfg(feature = "prover")]
+use {
+    crate::circuits::scalar_to_base,
+    crate::elgamal::elgamal::{elgamal_decrypt, ElGamalKeypair},
+    crate::encode::encode::decode,
+};
+
+const ELGAMAL_CT1_X: usize = 0;
+const ELGAMAL_CT1_Y: usize = 1;
+const ELGAMAL_CT2_X: usize = 2;
+const ELGAMAL_CT2_Y: usize = 3;
+const ELGAMAL_PK_X: usize = 4;
+const ELGAMAL_PK_Y: usize = 5;
+const GENERATOR_X: usize = 6;
+const GENERATOR_Y: usize = 7;
+const R_ENCODE: usize = 8;
+const MESSAGE: usize = 9;
+
+/// Number of public inputs of a single decryption
+pub(crate) const DEC_INSTANCE_SIZE: usize = 10;
+
+// Errors returned when creating the circuit of a decryption
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecryptionCircuitError {
+    Decrypt(DecryptError),       // the ciphertext cannot be decrypted, see extended_elgamal_decrypt
+    Conversion(ConversionError), // the private key does not fit in the base field
+}
+
+impl fmt::Display for DecryptionCircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecryptionCircuitError::Decrypt(error) => write!(f, "decryption failed: {}", error),
+            DecryptionCircuitError::Conversion(error) => {
+                write!(f, "invalid private key: {}", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecryptionCircuitError {}
+
+impl From<DecryptError> for DecryptionCircuitError {
+    fn from(error: DecryptError) -> Self {
+        DecryptionCircuitError::Decrypt(error)
+    }
+}
+
+impl From<ConversionError> for DecryptionCircuitError {
+    fn from(error: ConversionError) -> Self {
+        DecryptionCircuitError::Conversion(error)
+    }
+}
+
+#[derive(Clone)]
+pub struct VeDecCircuit {
+    pub(crate) data_in_transmit: DataInTransmit,
+    pub(crate) elgamal_public_key: pallas::Point,
+    pub(crate) generator: pallas::Point,
+    pub(crate) m: pallas::Base,
+    pub(crate) private_key: Value<pallas::Base>,
+    pub(crate) p_m: Value<pallas::Point>,
+}
+
+// The base point defaults to the generator of pallas, the identity is not a valid base point.
+impl Default for VeDecCircuit {
+    fn default() -> Self {
+        VeDecCircuit {
+            data_in_transmit: DataInTransmit::default(),
+            elgamal_public_key: pallas::Point::default(),
+            generator: pallas::Point::generator(),
+            m: pallas::Base::zero(),
+            private_key: Value::unknown(),
+            p_m: Value::unknown(),
+        }
+    }
+}
+
+impl Circuit<pallas::Base> for VeDecCircuit {
+    type Config = VeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    // Keep the public data, the public points are witnessed as non-identity points.
+    fn without_witnesses(&self) -> Self {
+        Self {
+            data_in_transmit: self.data_in_transmit.clone(),
+            elgamal_public_key: self.elgamal_public_key,
+            generator: self.generator,
+            m: self.m,
+            ..Self::default()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        VeEncCircuit::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        // Load 10-bit lookup table.
+        config.ecc_config.lookup_config.load(&mut layouter)?;
+
+        let add_sub_mul_chip = AddSubMulChip::new(config.add_sub_mul_config.clone());
+        let ecc_chip = EccChip::<VerifiableEncryptionFixedBases>::construct(config.ecc_config);
+        let column = ecc_chip.config().advices[0];
+
+        // load sk
+        let assigned_private_key =
+            ecc_chip.load_private(layouter.namespace(|| "load sk"), column, self.private_key)?;
+
+        // sk, converted once for [sk]generator and [sk]ct_1
+        let private_key = ScalarVar::from_base(
+            ecc_chip.clone(),
+            layouter.namespace(|| "sk"),
+            &assigned_private_key,
+        )?;
+
+        // ElGamal base point, constrained to equal public input G
+        let generator = NonIdentityPoint::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "load generator"),
+            Value::known(self.generator.to_affine()),
+        )?;
+        constrain_point_instance(
+            layouter.namespace(|| "constrain generator"),
+            &Point::from(generator.clone()),
+            config.instance,
+            GENERATOR_X,
+            GENERATOR_Y,
+        )?;
+
+        // (1) pk = [sk]generator
+        // Constrain [sk]generator to equal public input pk
+        let (public_key, private_key) =
+            generator.mul(layouter.namespace(|| "[sk]generator"), private_key)?;
+        constrain_point_instance(
+            layouter.namespace(|| "constrain pk"),
+            &Point::from(public_key),
+            config.instance,
+            ELGAMAL_PK_X,
+            ELGAMAL_PK_Y,
+        )?;
+
+        // (2) ct_2 = p_m + [sk]ct_1
+        // ct_1, constrained to equal public input ct_1
+        let ct_1 = NonIdentityPoint::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "load ct_1"),
+            Value::known(self.data_in_transmit.ct.c1.to_affine()),
+        )?;
+        constrain_point_instance(
+            layouter.namespace(|| "constrain ct_1"),
+            &Point::from(ct_1.clone()),
+            config.instance,
+            ELGAMAL_CT1_X,
+            ELGAMAL_CT1_Y,
+        )?;
+
+        // witness message point p_m
+        let p_m = NonIdentityPoint::new(
+            ecc_chip,
+            layouter.namespace(|| "load p_m"),
+            self.p_m.as_ref().map(|p_m| p_m.to_affine()),
+        )?;
+
+        // Compute ct_2_expected = [sk]ct_1 + p_m
+        let (sk_mul_ct_1, _) = ct_1.mul(layouter.namespace(|| "[sk]ct_1"), private_key)?;
+        let ct_2_expected = sk_mul_ct_1.add(layouter.namespace(|| "[sk]ct_1 + p_m"), &p_m)?;
+
+        // Constrain ct_2_expected to equal public input ct_2
+        constrain_point_instance(
+            layouter.namespace(|| "constrain ct_2"),
+            &ct_2_expected,
+            config.instance,
+            ELGAMAL_CT2_X,
+            ELGAMAL_CT2_Y,
+        )?;
+
+        // (3) p_m.x = r_encode + m
+        // r_encode and m, constrained to equal their public inputs
+        let r_encode = add_sub_mul_chip.load_private(
+            layouter.namespace(|| "load r_encode"),
+            Value::known(self.data_in_transmit.r_encode),
+        )?;
+        add_sub_mul_chip.check_result(
+            layouter.namespace(|| "check r_encode"),
+            r_encode.clone(),
+            R_ENCODE,
+        )?;
+        let message =
+            add_sub_mul_chip.load_private(layouter.namespace(|| "load m"), Value::known(self.m))?;
+        add_sub_mul_chip.check_result(
+            layouter.namespace(|| "check m"),
+            message.clone(),
+            MESSAGE,
+        )?;
+
+        let exp_x = add_sub_mul_chip.add(layouter.namespace(|| "m + r_encode"), message, r_encode)?;
+        add_sub_mul_chip.constrain_equal(
+            layouter.namespace(|| "p_m.x = m + r_encode"),
+            exp_x,
+            p_m.inner().x(),
+        )
+    }
+}
+
+impl VeDecCircuit {
+    /// The decrypted message, a public input of the circuit.
+    pub fn message(&self) -> pallas::Base {
+        self.m
+    }
+}
+
+/// Public inputs
+#[derive(Clone, Debug)]
+pub struct VeDecInstance {
+    pub(crate) data_in_transmit: DataInTransmit,
+    pub(crate) elgamal_public_key: pallas::Point,
+    pub(crate) generator: pallas::Point,
+    pub(crate) message: pallas::Base,
+}
+
+impl VeDecInstance {
+    /// Instance of the decryption of a ciphertext to `message`,
+    /// with the generator of pallas as base point.
+    pub fn new(
+        data_in_transmit: DataInTransmit,
+        elgamal_public_key: pallas::Point,
+        message: pallas::Base,
+    ) -> Self {
+        Self::new_with_generator(
+            data_in_transmit,
+            elgamal_public_key,
+            pallas::Point::generator(),
+            message,
+        )
+    }
+
+    /// Instance of the decryption with a custom base point.
+    pub fn new_with_generator(
+        data_in_transmit: DataInTransmit,
+        elgamal_public_key: pallas::Point,
+        generator: pallas::Point,
+        message: pallas::Base,
+    ) -> Self {
+        VeDecInstance {
+            data_in_transmit,
+            elgamal_public_key,
+            generator,
+            message,
+        }
+    }
+
+    /// Public inputs of the circuit.
+    /// Fails with `Error::InvalidInstances` if one of the points is the identity.
+    pub fn to_halo2_instance(&self) -> Result<[[vesta::Scalar; DEC_INSTANCE_SIZE]; 1], Error> {
+        let mut instance = [vesta::Scalar::zero(); DEC_INSTANCE_SIZE];
+
+        let (x, y) = point_to_xy(&self.data_in_transmit.ct.c1)?;
+        instance[ELGAMAL_CT1_X] = x;
+        instance[ELGAMAL_CT1_Y] = y;
+
+        let (x, y) = point_to_xy(&self.data_in_transmit.ct.c2)?;
+        instance[ELGAMAL_CT2_X] = x;
+        instance[ELGAMAL_CT2_Y] = y;
+
+        let (x, y) = point_to_xy(&self.elgamal_public_key)?;
+        instance[ELGAMAL_PK_X] = x;
+        instance[ELGAMAL_PK_Y] = y;
+
+        let (x, y) = point_to_xy(&self.generator)?;
+        instance[GENERATOR_X] = x;
+        instance[GENERATOR_Y] = y;
+
+        instance[R_ENCODE] = self.data_in_transmit.r_encode;
+        instance[MESSAGE] = self.message;
+        Ok([instance])
+    }
+}
+
+/// Decrypt the ciphertext with the private key of the keypair,
+/// and create a circuit proving that the message is its decryption.
+/// The message is available with `VeDecCircuit::message`.
+/// Fails with `DecryptionCircuitError::Decrypt` as `extended_elgamal_decrypt` does, and with
+/// `DecryptionCircuitError::Conversion` if the private key does not fit in the base field,
+/// see `scalar_to_base`, which happens with probability 2^-167 for a random key.
+#[cfg(feature = "prover")]
+pub fn create_circuit(
+    data_in_transmit: DataInTransmit,
+    keypair: &ElGamalKeypair,
+) -> Result<VeDecCircuit, DecryptionCircuitError> {
+    if bool::from(data_in_transmit.ct.c1.is_identity()) {
+        return Err(DecryptError::InvalidCiphertext.into());
+    }
+    let p_m = elgamal_decrypt(&keypair.private_key, &data_in_transmit.ct)
+        .ok_or(DecryptError::InvalidCiphertext)?;
+    if bool::from(p_m.is_identity()) {
+        return Err(DecryptError::IdentityPoint.into());
+    }
+    let private_key = scalar_to_base(&keypair.private_key)?;
+
+    Ok(VeDecCircuit {
+        m: decode(p_m, data_in_transmit.r_encode),
+        data_in_transmit,
+        elgamal_public_key: keypair.public_key,
+        generator: pallas::Point::generator(),
+        private_key: Value::known(private_key),
+        p_m: Value::known(p_m),
+    })
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{create_circuit, DecryptionCircuitError, VeDecInstance};
+    use crate::circuits::verifiable_encryption::K;
+    use crate::circuits::{keygen, prove_encryption, verify_encryption};
+    use crate::elgamal::elgamal::{ElGamalCiphertext, ElGamalKeypair};
+    use crate::elgamal::extended_elgamal::{
+        extended_elgamal_encrypt, DataInTransmit, DecryptError,
+    };
+    use group::Group;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::Circuit;
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+
+    #[test]
+    fn round_trip() {
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let (data_in_transmit, _) = extended_elgamal_encrypt(&keypair.public_key, m).unwrap();
+
+        // Step 1. decrypt the ciphertext, and create a circuit for the decryption
+        let circuit = create_circuit(data_in_transmit.clone(), &keypair).unwrap();
+        assert_eq!(circuit.message(), m);
+
+        // Step 2. arrange the public instance, with the revealed message.
+        let instance = VeDecInstance::new(data_in_transmit, keypair.public_key, m)
+            .to_halo2_instance()
+            .unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        // Setup phase, proving phase and verification against the public instance.
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+    }
+
+    #[test]
+    fn tampered_ciphertext() {
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let (data_in_transmit, _) = extended_elgamal_encrypt(&keypair.public_key, m).unwrap();
+        let circuit = create_circuit(data_in_transmit.clone(), &keypair).unwrap();
+
+        // the genuine decryption, against a ciphertext with ct_2 + G
+        let tampered = DataInTransmit::new(
+            ElGamalCiphertext::new(
+                data_in_transmit.c1(),
+                data_in_transmit.c2() + pallas::Point::generator(),
+            ),
+            data_in_transmit.r_encode(),
+        );
+        let instance = VeDecInstance::new(tampered, keypair.public_key, m)
+            .to_halo2_instance()
+            .unwrap();
+        let prover = MockProver::run(K, &circuit, instance.iter().map(|c| c.to_vec()).collect())
+            .unwrap();
+        assert!(prover.verify().is_err());
+
+        // the genuine decryption, against another message
+        let instance = VeDecInstance::new(data_in_transmit, keypair.public_key, m + m)
+            .to_halo2_instance()
+            .unwrap();
+        let prover = MockProver::run(K, &circuit, instance.iter().map(|c| c.to_vec()).collect())
+            .unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn invalid_ciphertext() {
+        let keypair = ElGamalKeypair::new();
+        let (data_in_transmit, _) =
+            extended_elgamal_encrypt(&keypair.public_key, pallas::Base::from(42)).unwrap();
+        let invalid = DataInTransmit::new(
+            ElGamalCiphertext::new(pallas::Point::identity(), data_in_transmit.c2()),
+            data_in_transmit.r_encode(),
+        );
+        assert_eq!(
+            create_circuit(invalid, &keypair).err(),
+            Some(DecryptionCircuitError::Decrypt(DecryptError::InvalidCiphertext))
+        );
+    }
+}
@@ -8,7 +8,7 @@
 /// (1.1) p_m.x = r_encode + m
 /// (1.2) p_m.x^3 + 5 = p_m.y^2 (redundant check, if p_m is not on the curve, the point operations will fail)
 /// (2) C = ElGamal.Enc(pk, p_m)
-/// (2.1) ct_1 = [r_enc]G, G is the generator of E
+/// (2.1) ct_1 = [r_enc]G, G is a base point of E, the generator of E by default
 /// (2.2) ct_2 = p_m +[r_enc]pk_elgamal
 ///
 /// - secret input `m`;
@@ -16,36 +16,55 @@
 /// - secret input `r_enc`;
 /// - public group element `ct_1 := [r_enc]G`
 /// - public group element `ct_2 := p_m + [r]elgamal_public_key`
-/// - public random element `r_encode`
+/// - public random element `r_encode`, constrained to the r_encode of the ciphertext
 /// - public group element `elgamal_public_key`
-/// - public generator `G`;
+/// - public group element `G`, the ElGamal base point
 
 
 use crate::add_sub_mul::chip::{
-    AddInstructions, AddSubMulChip, AddSubMulConfig, AddSubMulInstructions,
-    SubInstructions,
+    AddInstructions, AddSubMulChip, AddSubMulConfig, AddSubMulInstructions, SubInstructions,
 };
+use crate::circuits::ciphertext_well_formedness::check_elgamal_encryption;
+use crate::circuits::{find_minimum_k, point_to_xy};
 use crate::constants::fixed_bases::VerifiableEncryptionFixedBases;
-use crate::elgamal::extended_elgamal::{DataInTransmit, extended_elgamal_decrypt, extended_elgamal_encrypt};
-use ff::{Field, PrimeField};
-use group::prime::PrimeCurveAffine;
-use group::Curve;
+use crate::elgamal::extended_elgamal::DataInTransmit;
+use group::{Curve, Group};
 use halo2_gadgets::ecc::chip::{EccChip, EccConfig};
-use halo2_gadgets::ecc::{NonIdentityPoint, ScalarVar};
+use halo2_gadgets::ecc::NonIdentityPoint;
 use halo2_gadgets::utilities::UtilitiesInstructions;
 use halo2_proofs::{
     circuit::{Chip, Layouter, SimpleFloorPlanner, Value},
-    plonk::{Circuit, Column, ConstraintSystem, Error, Instance as InstanceColumn},
+    dev::{MockProver, VerifyFailure},
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance as InstanceColumn,
+        TableColumn,
+    },
 };
+#[cfg(any(feature = "prover", feature = "serde", feature = "diagnostics"))]
 use pasta_curves::arithmetic::CurveAffine;
 use pasta_curves::{Fp, pallas, vesta};
-use rand::rngs::OsRng;
-use crate::elgamal::elgamal::ElGamalKeypair;
 use halo2_gadgets::utilities::lookup_range_check::LookupRangeCheckConfig;
 use halo2_proofs::circuit::AssignedCell;
+#[cfg(feature = "diagnostics")]
+use halo2_proofs::{plonk::VerifyingKey, poly::commitment::Params};
+#[cfg(feature = "serde")]
+use {crate::elgamal::elgamal::ElGamalCiphertext, ff::PrimeField};
 use pasta_curves::pallas::{Affine, Base};
+use std::{fmt, io};
+#[cfg(feature = "prover")]
+use {
+    crate::circuits::{random_r_enc, scalar_to_base, ConversionError},
+    crate::elgamal::elgamal::{elgamal_encrypt_with_generator, ElGamalKeypair},
+    crate::elgamal::extended_elgamal::{
+        default_r_encode, extended_elgamal_decrypt, extended_elgamal_encrypt_with_generator,
+        EncryptError, Witness,
+    },
+    crate::encode::encode::derive_r_encode,
+    pasta_curves::arithmetic::CurveExt,
+};
 
-const K: u32 = 11;
+/// Default K used to create the params. Any K from [`minimum_k`] up can be used.
+pub const K: u32 = 11;
 
 const ZERO: usize = 0;
 const ELGAMAL_CT1_X: usize = 1;
@@ -55,6 +74,21 @@ const ELGAMAL_CT2_X: usize = 3;
 const ELGAMAL_CT2_Y: usize = 4;
 const ELGAMAL_PK_X: usize = 5;
 const ELGAMAL_PK_Y: usize = 6;
+const GENERATOR_X: usize = 7;
+const GENERATOR_Y: usize = 8;
+const R_ENCODE: usize = 9;
+
+/// Number of advice columns of the circuit, all used by the ECC chip.
+/// The last two are shared with the add, sub, mul chip and the range check.
+pub const ADVICE_COLUMNS: usize = 10;
+
+/// Number of fixed columns of the circuit, before the selectors are compressed into fixed
+/// columns: the Lagrange coefficients of the fixed-base multiplications, the first of which
+/// is also used for the constants.
+pub const FIXED_COLUMNS: usize = 8;
+
+/// Number of public inputs of a single encryption
+pub(crate) const INSTANCE_SIZE: usize = 10;
 
 #[derive(Clone, Debug)]
 pub struct VeConfig {
@@ -63,20 +97,128 @@ pub struct VeConfig {
     pub(crate) add_sub_mul_config: AddSubMulConfig,
 }
 
-#[derive(Default,Clone)]
+impl VeConfig {
+    /// Configure the chips of the encryption on the given columns, so that circuits
+    /// extending the encryption can share the columns with their own chips.
+    pub(crate) fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        advices: [Column<Advice>; ADVICE_COLUMNS],
+        lagrange_coeffs: [Column<Fixed>; FIXED_COLUMNS],
+        table_idx: TableColumn,
+        table_range_check_tag: TableColumn,
+    ) -> Self {
+        // Instance column used for public inputs
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        // Permutation over all advice columns.
+        for advice in advices.iter() {
+            meta.enable_equality(*advice);
+        }
+
+        // Shared fixed column for loading constants
+        let constant = lagrange_coeffs[0];
+        meta.enable_constant(constant);
+
+        // Shared advice column for loading advice
+        // The add, sub, mul gates share their two columns with the ECC chip and the range
+        // check. Sharing is sound: every gate is multiplied by its own selector and only
+        // rotates within its two-row region, and the floor planner does not overlap regions
+        // in a column. Packing the gates in fewer rows does not lower K: the 10-bit lookup
+        // table alone needs 2^10 rows plus the blinding rows, that is, K = 11, and the regions
+        // of a block are shorter than the table.
+        let advice = [advices[8], advices[9]];
+
+        let add_sub_mul_config = AddSubMulChip::configure(meta, advice, instance, constant);
+
+        let range_check =
+            LookupRangeCheckConfig::configure(meta, advices[9], table_idx, table_range_check_tag);
+
+
+        // Configuration for curve point operations.
+        // This uses 10 advice columns and spans the whole circuit.
+        let ecc_config = EccChip::<VerifiableEncryptionFixedBases>::configure(
+            meta,
+            advices,
+            lagrange_coeffs,
+            range_check,
+        );
+
+        VeConfig {
+            instance,
+            ecc_config,
+            add_sub_mul_config,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct VeEncCircuit {
     pub(crate) data_in_transmit: DataInTransmit,
     pub(crate) elgamal_public_key: pallas::Point,
+    pub(crate) generator: pallas::Point,
     pub(crate) m: Value<pallas::Base>,
     pub(crate) p_m: Value<pallas::Point>,
     pub(crate) r_enc: Value<pallas::Base>,
 }
+
+// The base point defaults to the generator of pallas, the identity is not a valid base point.
+impl Default for VeEncCircuit {
+    fn default() -> Self {
+        VeEncCircuit {
+            data_in_transmit: DataInTransmit::default(),
+            elgamal_public_key: pallas::Point::default(),
+            generator: pallas::Point::generator(),
+            m: Value::unknown(),
+            p_m: Value::unknown(),
+            r_enc: Value::unknown(),
+        }
+    }
+}
+
+/// A point witnessed by the circuit is the identity, which the ECC chip cannot witness as a
+/// `NonIdentityPoint`. It is reported by synthesis as an `InvalidInput` transcript error
+/// wrapping this error, instead of a failure inside the gadget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WitnessError {
+    IdentityMessagePoint, // p_m is the identity, which Encode never returns
+    IdentityPublicKey,    // the ElGamal public key is the identity
+    IdentityGenerator,    // the ElGamal base point is the identity
+}
+
+impl fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessError::IdentityMessagePoint => {
+                write!(f, "the message point p_m is the identity")
+            }
+            WitnessError::IdentityPublicKey => write!(f, "the ElGamal public key is the identity"),
+            WitnessError::IdentityGenerator => write!(f, "the ElGamal base point is the identity"),
+        }
+    }
+}
+
+impl std::error::Error for WitnessError {}
+
+impl From<WitnessError> for Error {
+    fn from(error: WitnessError) -> Self {
+        Error::Transcript(io::Error::new(io::ErrorKind::InvalidInput, error))
+    }
+}
+
 impl Circuit<pallas::Base> for VeEncCircuit {
     type Config = VeConfig;
     type FloorPlanner = SimpleFloorPlanner;
 
     fn without_witnesses(&self) -> Self {
-        Self::default()
+        // Keep the public data: the ECC chip rejects witnessing a known identity point,
+        // so the default elgamal_public_key cannot be used for key generation.
+        Self {
+            data_in_transmit: self.data_in_transmit.clone(),
+            elgamal_public_key: self.elgamal_public_key,
+            generator: self.generator,
+            ..Self::default()
+        }
     }
 
     fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
@@ -95,16 +237,6 @@ impl Circuit<pallas::Base> for VeEncCircuit {
         ];
         let table_idx = meta.lookup_table_column();
         let table_range_check_tag = meta.lookup_table_column();
-
-        // Instance column used for public inputs
-        let instance = meta.instance_column();
-        meta.enable_equality(instance);
-
-        // Permutation over all advice columns.
-        for advice in advices.iter() {
-            meta.enable_equality(*advice);
-        }
-
         let lagrange_coeffs = [
             meta.fixed_column(),
             meta.fixed_column(),
@@ -116,66 +248,120 @@ impl Circuit<pallas::Base> for VeEncCircuit {
             meta.fixed_column(),
         ];
 
-        // Shared fixed column for loading constants
-        let constant = lagrange_coeffs[0];
-        meta.enable_constant(constant);
+        let config =
+            VeConfig::configure(meta, advices, lagrange_coeffs, table_idx, table_range_check_tag);
 
-        // Shared advice column for loading advice
-        let advice = [advices[8], advices[9]];
+        // the chips share the columns above, and do not allocate their own
+        assert_eq!(meta.num_advice_columns(), ADVICE_COLUMNS);
+        assert_eq!(meta.num_fixed_columns(), FIXED_COLUMNS);
+        config
+    }
 
-        let add_sub_mul_config = AddSubMulChip::configure(meta, advice, instance, constant);
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        // Load 10-bit lookup table.
+        config.ecc_config.lookup_config.load(&mut layouter)?;
 
-        let range_check =
-            LookupRangeCheckConfig::configure(meta, advices[9], table_idx, table_range_check_tag);
+        self.synthesize_encryption(config, layouter, 0)?;
+        Ok(())
+    }
+}
 
+impl VeEncCircuit {
+    /// Check that the points witnessed by the circuit are not the identity.
+    /// p_m is only checked when it is known, i.e. not for a circuit without witnesses.
+    pub fn check_witnesses(&self) -> Result<(), WitnessError> {
+        if bool::from(self.elgamal_public_key.is_identity()) {
+            return Err(WitnessError::IdentityPublicKey);
+        }
+        if bool::from(self.generator.is_identity()) {
+            return Err(WitnessError::IdentityGenerator);
+        }
+        let mut identity_p_m = false;
+        self.p_m.map(|p_m| identity_p_m = bool::from(p_m.is_identity()));
+        if identity_p_m {
+            return Err(WitnessError::IdentityMessagePoint);
+        }
+        Ok(())
+    }
 
-        // Configuration for curve point operations.
-        // This uses 10 advice columns and spans the whole circuit.
-        let ecc_config = EccChip::<VerifiableEncryptionFixedBases>::configure(
-            meta,
-            advices,
-            lagrange_coeffs,
-            range_check,
-        );
+    /// Witness the values of this circuit and constrain them as an encryption
+    /// whose public inputs start at row `instance_offset` of the instance column.
+    /// The lookup table must have been loaded by the caller.
+    /// Returns the message cell, so that callers can add constraints on the message.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "synthesize", skip_all, fields(instance_offset = instance_offset))
+    )]
+    pub(crate) fn synthesize_encryption(
+        &self,
+        config: VeConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+        instance_offset: usize,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        self.check_witnesses()?;
+        let (p_m, message) =
+            self.witness_message(config.clone(), layouter.namespace(|| "message"))?;
+        self.synthesize_encryption_of(config, layouter, p_m, message.clone(), instance_offset)?;
+        Ok(message)
+    }
 
-        VeConfig {
-            instance,
-            ecc_config,
-            add_sub_mul_config,
-        }
+    /// Witness the message point p_m and the message m of this circuit.
+    pub(crate) fn witness_message(
+        &self,
+        config: VeConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<
+        (
+            NonIdentityPoint<Affine, EccChip<VerifiableEncryptionFixedBases>>,
+            AssignedCell<pallas::Base, pallas::Base>,
+        ),
+        Error,
+    > {
+        let add_sub_mul_chip = AddSubMulChip::new(config.add_sub_mul_config.clone());
+        let ecc_chip = EccChip::construct(config.ecc_config);
+
+        // witness message point p_m
+        let p_m = NonIdentityPoint::new(
+            ecc_chip,
+            layouter.namespace(|| "load p_m"),
+            self.p_m.as_ref().map(|p_m| p_m.to_affine()),
+        )?;
+
+        // load dsa_private_key = message
+        let message =
+            add_sub_mul_chip.load_private(layouter.namespace(|| "load message"), self.m)?;
+        Ok((p_m, message))
     }
 
-    fn synthesize(
+    /// Constrain the ciphertext of this circuit to be an encryption of the given message point
+    /// and message, whose public inputs start at row `instance_offset` of the instance column.
+    /// The witnesses p_m and m of this circuit are not used, so that several ciphertexts
+    /// can be constrained to encrypt the same message cells.
+    pub(crate) fn synthesize_encryption_of(
         &self,
-        config: Self::Config,
+        config: VeConfig,
         mut layouter: impl Layouter<pallas::Base>,
+        p_m: NonIdentityPoint<Affine, EccChip<VerifiableEncryptionFixedBases>>,
+        message: AssignedCell<pallas::Base, pallas::Base>,
+        instance_offset: usize,
     ) -> Result<(), Error> {
         // Construct the add, sub, mul chip.
         let add_sub_mul_chip = AddSubMulChip::new(config.add_sub_mul_config.clone());
         // Construct the ECC chip.
         let ecc_chip = EccChip::construct(config.ecc_config.clone());
 
-        // Load 10-bit lookup table.
-        config.ecc_config.lookup_config.load(&mut layouter)?;
-
         let column = ecc_chip.config().advices[0];
 
-        // witness message point p_m
-        let p_m = NonIdentityPoint::new(
-            ecc_chip.clone(),
-            layouter.namespace(|| "load p_m"),
-            self.p_m.as_ref().map(|p_m| p_m.to_affine()),
-        )?;
         // load randomness r_encode
         let r_encode = add_sub_mul_chip.load_private(
             layouter.namespace(|| "load r_encode"),
             Value::known(self.data_in_transmit.r_encode),
         )?;
 
-        // load dsa_private_key = message
-        let message =
-            add_sub_mul_chip.load_private(layouter.namespace(|| "load message"), self.m)?;
-
         // load r_enc
         let assigned_r_enc =
             ecc_chip.load_private(layouter.namespace(|| "load r_enc"), column, self.r_enc)?;
@@ -187,6 +373,12 @@ impl Circuit<pallas::Base> for VeEncCircuit {
             Value::known(self.elgamal_public_key.to_affine()),
         )?;
 
+        // ElGamal base point
+        let generator = NonIdentityPoint::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "load generator"),
+            Value::known(self.generator.to_affine()),
+        )?;
 
         check_encryption(
             config,
@@ -198,6 +390,8 @@ impl Circuit<pallas::Base> for VeEncCircuit {
             message,
             assigned_r_enc,
             elgamal_public_key,
+            generator,
+            instance_offset,
         )
     }
 }
@@ -212,8 +406,18 @@ pub(crate) fn check_encryption(
     message: AssignedCell<Fp, Fp>,
     assigned_r_enc:  AssignedCell<Base, Base>,
     elgamal_public_key: NonIdentityPoint<Affine, EccChip<VerifiableEncryptionFixedBases>>,
+    generator: NonIdentityPoint<Affine, EccChip<VerifiableEncryptionFixedBases>>,
+    instance_offset: usize,
 ) -> Result<(), Error>
 {
+    // Constrain r_encode to equal public input r_encode, so that the encoding is the one
+    // of the published ciphertext
+    add_sub_mul_chip.check_result(
+        layouter.namespace(|| "check r_encode"),
+        r_encode.clone(),
+        instance_offset + R_ENCODE,
+    )?;
+
     // (1) Encode(m; r_encode) = p_m, that is,
     // (1.1) p_m.x = r_encode + m
 
@@ -230,80 +434,24 @@ pub(crate) fn check_encryption(
     )?;
 
     // check if res = 0
-    add_sub_mul_chip.check_result(layouter.namespace(|| "check res"), res, 0)?;
-
-    // (2) C = ElGamal.Enc(pk, p_m)
-    // (2.1) ct_1 = [r_enc]generator
-    // r_enc
-    let r_enc = ScalarVar::from_base(
-        ecc_chip.clone(),
-        layouter.namespace(|| "r_enc"),
-        &assigned_r_enc,
+    add_sub_mul_chip.check_result(
+        layouter.namespace(|| "check res"),
+        res,
+        instance_offset + ZERO,
     )?;
 
-    // generator
-    let generator = NonIdentityPoint::new(
-        ecc_chip.clone(),
-        layouter.namespace(|| "load generator"),
-        Value::known(pallas::Affine::generator()),
-    )?;
-
-    // compute [r_enc]generator
-    let (ct1_expected, _) =
-        { generator.mul(layouter.namespace(|| "[r_enc]generator"), r_enc)? };
-
-    // Constrain ct1_expected to equal public input ct1
-    layouter.constrain_instance(
-        ct1_expected.inner().x().cell(),
-        config.instance,
-        ELGAMAL_CT1_X,
-    )?;
-    layouter.constrain_instance(
-        ct1_expected.inner().y().cell(),
+    // (2) C = ElGamal.Enc(pk, p_m), whose public points follow ZERO in the layout of the
+    // instance of the well-formedness of a ciphertext
+    check_elgamal_encryption(
+        layouter,
         config.instance,
-        ELGAMAL_CT1_Y,
-    )?;
-
-    // (2.2) ct_2 = p_m +[r_enc]pk
-    // r_enc
-    let r_enc = ScalarVar::from_base(
-        ecc_chip.clone(),
-        layouter.namespace(|| "r_enc"),
+        ecc_chip,
+        &p_m,
         &assigned_r_enc,
-    )?;
-
-    // Constrain elgamal_public_key to equal public input pk
-    layouter.constrain_instance(
-        elgamal_public_key.inner().x().cell(),
-        config.instance,
-        ELGAMAL_PK_X,
-    )?;
-    layouter.constrain_instance(
-        elgamal_public_key.inner().y().cell(),
-        config.instance,
-        ELGAMAL_PK_Y,
-    )?;
-
-    // Compute [r_enc]elgamal_public_key
-    let (r_mul_pk, _) =
-        { elgamal_public_key.mul(layouter.namespace(|| "[r_enc]elgamal_public_key"), r_enc)? };
-
-    // Compute ct_2_expected = [r_enc]elgamal_public_key + p_m
-    let ct_2_expected =
-        r_mul_pk.add(layouter.namespace(|| "[r_enc]elgamal_public_key+p_m"), &p_m)?;
-
-    // Constrain ct_2_expected to equal public input ct_2
-    layouter.constrain_instance(
-        ct_2_expected.inner().x().cell(),
-        config.instance,
-        ELGAMAL_CT2_X,
-    )?;
-    layouter.constrain_instance(
-        ct_2_expected.inner().y().cell(),
-        config.instance,
-        ELGAMAL_CT2_Y,
-    )?;
-    Ok(())
+        elgamal_public_key,
+        generator,
+        instance_offset + ELGAMAL_CT1_X,
+    )
 }
 
 /// Public inputs
@@ -311,84 +459,693 @@ pub(crate) fn check_encryption(
 pub struct VeEncInstance {
     pub(crate) data_in_transmit: DataInTransmit,
     pub(crate) elgamal_public_key: pallas::Point,
+    pub(crate) generator: pallas::Point,
 }
 
 impl VeEncInstance {
-    pub(crate) fn to_halo2_instance(&self) -> [[vesta::Scalar; 7]; 1] {
-        let mut instance = [vesta::Scalar::random(OsRng); 7];
-        instance[ZERO] = vesta::Scalar::zero();
+    /// Instance of an encryption with the generator of pallas as base point.
+    pub fn new(data_in_transmit: DataInTransmit, elgamal_public_key: pallas::Point) -> Self {
+        Self::new_with_generator(data_in_transmit, elgamal_public_key, pallas::Point::generator())
+    }
+
+    /// Instance of an encryption with a custom base point.
+    pub fn new_with_generator(
+        data_in_transmit: DataInTransmit,
+        elgamal_public_key: pallas::Point,
+        generator: pallas::Point,
+    ) -> Self {
+        VeEncInstance {
+            data_in_transmit,
+            elgamal_public_key,
+            generator,
+        }
+    }
+
+    /// Public inputs of the circuit.
+    /// Fails with `Error::InvalidInstances` if one of the points is the identity,
+    /// which cannot be proven by the circuit.
+    pub fn to_halo2_instance(&self) -> Result<[[vesta::Scalar; INSTANCE_SIZE]; 1], Error> {
+        let mut builder = InstanceBuilder::new();
+        builder.push_instance(self)?;
+        let [instance] = builder.build();
+        Ok([instance.try_into().expect("a block has INSTANCE_SIZE rows")])
+    }
+}
+
+/// Public inputs of one or more encryptions, built block by block: each block appends its
+/// INSTANCE_SIZE rows to the instance column, so block i uses rows
+/// [i * INSTANCE_SIZE, (i + 1) * INSTANCE_SIZE), as laid out by `synthesize_encryption`.
+/// The same rows are used by a single VeEncCircuit and by the batch circuits.
+#[derive(Clone, Debug, Default)]
+pub struct InstanceBuilder {
+    instance: Vec<vesta::Scalar>,
+}
+
+impl InstanceBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        instance[ELGAMAL_CT1_X] = *self.data_in_transmit.ct.c1.to_affine().coordinates().unwrap().x();
-        instance[ELGAMAL_CT1_Y] = *self.data_in_transmit.ct.c1.to_affine().coordinates().unwrap().y();
+    /// Append the rows of an encryption with the generator of pallas as base point.
+    /// Fails with `Error::InvalidInstances` if one of the points is the identity, in which
+    /// case no row is appended.
+    pub fn push_ciphertext(
+        &mut self,
+        data_in_transmit: &DataInTransmit,
+        elgamal_public_key: &pallas::Point,
+    ) -> Result<(), Error> {
+        self.push_ciphertext_with_generator(
+            data_in_transmit,
+            elgamal_public_key,
+            &pallas::Point::generator(),
+        )
+    }
+
+    /// Append the rows of an encryption with a custom base point, see [`Self::push_ciphertext`].
+    pub fn push_ciphertext_with_generator(
+        &mut self,
+        data_in_transmit: &DataInTransmit,
+        elgamal_public_key: &pallas::Point,
+        generator: &pallas::Point,
+    ) -> Result<(), Error> {
+        // Every row must be written below, the instance does not depend on any randomness.
+        let mut instance = [None; INSTANCE_SIZE];
+        instance[ZERO] = Some(vesta::Scalar::zero());
+
+        let (x, y) = point_to_xy(&data_in_transmit.ct.c1)?;
+        instance[ELGAMAL_CT1_X] = Some(x);
+        instance[ELGAMAL_CT1_Y] = Some(y);
+
+        let (x, y) = point_to_xy(&data_in_transmit.ct.c2)?;
+        instance[ELGAMAL_CT2_X] = Some(x);
+        instance[ELGAMAL_CT2_Y] = Some(y);
 
-        instance[ELGAMAL_CT2_X] = *self.data_in_transmit.ct.c2.to_affine().coordinates().unwrap().x();
-        instance[ELGAMAL_CT2_Y] = *self.data_in_transmit.ct.c2.to_affine().coordinates().unwrap().y();
+        let (x, y) = point_to_xy(elgamal_public_key)?;
+        instance[ELGAMAL_PK_X] = Some(x);
+        instance[ELGAMAL_PK_Y] = Some(y);
 
-        instance[ELGAMAL_PK_X] = *self
-            .elgamal_public_key
-            .to_affine()
-            .coordinates()
-            .unwrap()
-            .x();
-        instance[ELGAMAL_PK_Y] = *self
-            .elgamal_public_key
-            .to_affine()
-            .coordinates()
-            .unwrap()
-            .y();
+        let (x, y) = point_to_xy(generator)?;
+        instance[GENERATOR_X] = Some(x);
+        instance[GENERATOR_Y] = Some(y);
 
-        [instance]
+        instance[R_ENCODE] = Some(data_in_transmit.r_encode);
+
+        self.instance
+            .extend(instance.map(|row| row.expect("every instance row is written")));
+        Ok(())
+    }
+
+    /// Append the rows of the instance of an encryption.
+    pub fn push_instance(&mut self, instance: &VeEncInstance) -> Result<(), Error> {
+        self.push_ciphertext_with_generator(
+            &instance.data_in_transmit,
+            &instance.elgamal_public_key,
+            &instance.generator,
+        )
+    }
+
+    /// Number of blocks appended
+    pub fn num_blocks(&self) -> usize {
+        self.instance.len() / INSTANCE_SIZE
+    }
+
+    /// The instance column of the blocks, in order.
+    pub fn build(self) -> [Vec<vesta::Scalar>; 1] {
+        [self.instance]
+    }
+}
+
+/// Smallest K for which a VeEncCircuit fits in the params
+pub fn minimum_k() -> u32 {
+    let circuit = empty_circuit(pallas::Point::generator());
+    find_minimum_k(&circuit).expect("the circuit fits in 2^MAX_K rows")
+}
+
+/// Circuit without witnesses of an encryption to `elgamal_public_key`, with the generator of
+/// pallas as base point. The keys only depend on the shape of the circuit, so a verifier
+/// generates its verifying key from it, without encrypting anything.
+pub fn empty_circuit(elgamal_public_key: pallas::Point) -> VeEncCircuit {
+    VeEncCircuit {
+        elgamal_public_key,
+        ..VeEncCircuit::default()
     }
 }
 
-pub(crate) fn create_circuit(message: pallas::Base, keypair: ElGamalKeypair) -> VeEncCircuit {
-    // Elgamal encryption
-    let (data_in_transmit, elgamal_secret) =
-        extended_elgamal_encrypt(&keypair.public_key, message);
+#[cfg(feature = "prover")]
+pub fn create_circuit(message: pallas::Base, keypair: ElGamalKeypair) -> VeEncCircuit {
+    create_circuit_with_generator(message, keypair, pallas::Point::generator())
+        .expect("the public key is valid and a point is found within MAX_ENCODE_ATTEMPTS attempts")
+}
+
+/// Create a circuit for an encryption with a custom base point G.
+/// The keypair must be generated for the same G, e.g. with `ElGamalKeypair::new_with_generator`.
+/// Fails with `EncryptError::InvalidGenerator` if G is the identity, with
+/// `EncryptError::InvalidPublicKey` if the public key is, or if the message cannot be encoded.
+#[cfg(feature = "prover")]
+pub fn create_circuit_with_generator(
+    message: pallas::Base,
+    keypair: ElGamalKeypair,
+    generator: pallas::Point,
+) -> Result<VeEncCircuit, EncryptError> {
+    if bool::from(generator.is_identity()) {
+        return Err(EncryptError::InvalidGenerator);
+    }
+
+    // Elgamal encryption, with r_enc witnessed in the base field and r_encode derived from
+    // the public key and c1
+    let r_enc = random_r_enc();
+    let r_encode = default_r_encode(&keypair.public_key, &(generator * r_enc));
+    let (data_in_transmit, elgamal_secret) = extended_elgamal_encrypt_with_generator(
+        &generator,
+        &keypair.public_key,
+        message,
+        r_enc,
+        r_encode,
+    )?;
     let decrypted_message =
         extended_elgamal_decrypt(&keypair.private_key, data_in_transmit.clone())
             .expect("Decryption failed");
     // Verify decryption
     assert_eq!(message, decrypted_message);
 
+    Ok(VeEncCircuit {
+        generator,
+        ..circuit_from_witness(data_in_transmit, keypair.public_key, &elgamal_secret)
+            .expect("r_enc fits in the base field")
+    })
+}
+
+/// Create a circuit for an encryption made by the encryptor, who knows the witness
+/// but not the private key. The base point is the generator of pallas.
+/// Fails if r_enc does not fit in the base field, see `scalar_to_base`.
+#[cfg(feature = "prover")]
+pub(crate) fn circuit_from_witness(
+    data_in_transmit: DataInTransmit,
+    elgamal_public_key: pallas::Point,
+    witness: &Witness,
+) -> Result<VeEncCircuit, ConversionError> {
     // convert r_enc to base value
-    let r_enc = pallas::Base::from_repr(elgamal_secret.r_enc.to_repr()).unwrap();
+    let r_enc = scalar_to_base(&witness.r_enc)?;
 
-    VeEncCircuit {
+    Ok(VeEncCircuit {
         data_in_transmit: data_in_transmit,
-        elgamal_public_key: keypair.public_key,
-        m: Value::known(message),
-        p_m: Value::known(elgamal_secret.p_m),
+        elgamal_public_key: elgamal_public_key,
+        generator: pallas::Point::generator(),
+        m: Value::known(witness.m),
+        p_m: Value::known(witness.p_m),
         r_enc: Value::known(r_enc),
+    })
+}
+
+/// Builder of a circuit and its public instance for the encryption of a message.
+/// The builder encrypts the message itself, so that the ciphertext in the instance and
+/// the witness of the circuit (`m`, `p_m`, `r_enc`) are always consistent.
+#[cfg(feature = "prover")]
+#[derive(Clone, Debug)]
+pub struct VeEncCircuitBuilder {
+    message: pallas::Base,
+    elgamal_public_key: pallas::Point,
+    generator: pallas::Point,
+    r_encode_context: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+#[cfg(feature = "prover")]
+impl VeEncCircuitBuilder {
+    /// Encryption of the message to the public key, with the generator of pallas as base point.
+    pub fn new(message: pallas::Base, elgamal_public_key: pallas::Point) -> Self {
+        VeEncCircuitBuilder {
+            message,
+            elgamal_public_key,
+            generator: pallas::Point::generator(),
+            r_encode_context: None,
+        }
+    }
+
+    /// Use a custom base point G. The public key must be generated for the same G.
+    pub fn generator(mut self, generator: pallas::Point) -> Self {
+        self.generator = generator;
+        self
+    }
+
+    /// Derive r_encode from a public context with `derive_r_encode`, instead of the default
+    /// context of the public key and c1 (see `default_r_encode`), so that the verifier can
+    /// check it with `is_derived_r_encode`.
+    pub fn context(mut self, domain_sep: &[u8], context: &[u8]) -> Self {
+        self.r_encode_context = Some((domain_sep.to_vec(), context.to_vec()));
+        self
+    }
+
+    /// Encrypt the message and return the circuit, its public instance, and the encryption
+    /// witness, which the encryptor may retain (e.g. to prove other statements on the message).
+    /// Fails with `EncryptError::InvalidGenerator` if the base point is the identity, with
+    /// `EncryptError::InvalidPublicKey` if the public key is, or if the message cannot be encoded.
+    pub fn build(&self) -> Result<(VeEncCircuit, VeEncInstance, Witness), EncryptError> {
+        if bool::from(self.generator.is_identity()) {
+            return Err(EncryptError::InvalidGenerator);
+        }
+
+        // r_enc is witnessed in the base field
+        let r_enc = random_r_enc();
+        let r_encode = match &self.r_encode_context {
+            Some((domain_sep, context)) => derive_r_encode(domain_sep, context),
+            None => default_r_encode(&self.elgamal_public_key, &(self.generator * r_enc)),
+        };
+        let (data_in_transmit, witness) = extended_elgamal_encrypt_with_generator(
+            &self.generator,
+            &self.elgamal_public_key,
+            self.message,
+            r_enc,
+            r_encode,
+        )?;
+
+        let circuit = VeEncCircuit {
+            generator: self.generator,
+            ..circuit_from_witness(data_in_transmit.clone(), self.elgamal_public_key, &witness)
+                .expect("r_enc fits in the base field")
+        };
+        let instance = VeEncInstance::new_with_generator(
+            data_in_transmit,
+            self.elgamal_public_key,
+            self.generator,
+        );
+        Ok((circuit, instance, witness))
     }
 }
 
-#[cfg(test)]
+/// Create a circuit and its public instance for the encryption of a message point p_m held
+/// by the caller, e.g. committed to in another step of a protocol, instead of encoding a
+/// message: the encode step is decoupled from the prove step. p_m is given by its affine
+/// coordinates and r_encode is the randomness of its encoding, so the circuit proves the
+/// encryption of m = p_m.x - r_encode. p_m is encrypted with fresh randomness, and the
+/// returned witness holds m, p_m and r_enc.
+///
+/// The point is checked out of circuit before proving: fails with
+/// `EncryptError::InvalidMessagePoint` if (x, y) is not on the curve or is the identity,
+/// and with `EncryptError::InvalidPublicKey` if the public key is the identity.
+#[cfg(feature = "prover")]
+pub fn create_circuit_from_message_point(
+    p_m_x: pallas::Base,
+    p_m_y: pallas::Base,
+    r_encode: pallas::Base,
+    elgamal_public_key: pallas::Point,
+) -> Result<(VeEncCircuit, VeEncInstance, Witness), EncryptError> {
+    if bool::from(elgamal_public_key.is_identity())
+        || !bool::from(elgamal_public_key.is_on_curve())
+    {
+        return Err(EncryptError::InvalidPublicKey);
+    }
+    // from_xy accepts (0, 0), the encoding of the identity, which is not a message point
+    let p_m: Option<Affine> = Affine::from_xy(p_m_x, p_m_y).into();
+    let p_m = p_m
+        .map(pallas::Point::from)
+        .filter(|p_m| !bool::from(p_m.is_identity()))
+        .ok_or(EncryptError::InvalidMessagePoint)?;
+
+    // r_enc is witnessed in the base field
+    let r_enc = random_r_enc();
+    let generator = pallas::Point::generator();
+    let (ct, _) = elgamal_encrypt_with_generator(&generator, &elgamal_public_key, p_m, r_enc);
+    let data_in_transmit = DataInTransmit::new(ct, r_encode);
+    let witness = Witness {
+        m: p_m_x - r_encode,
+        p_m,
+        r_enc,
+    };
+
+    let circuit = circuit_from_witness(data_in_transmit.clone(), elgamal_public_key, &witness)
+        .expect("r_enc fits in the base field");
+    let instance = VeEncInstance::new(data_in_transmit, elgamal_public_key);
+    Ok((circuit, instance, witness))
+}
+
+/// Check the circuit against the instance with the mock prover, at the default K.
+/// This is much faster than creating a proof, and on failure returns the unsatisfied
+/// constraints with the gate and the row at which they fail.
+///
+/// Panics if the circuit cannot be synthesized, e.g. if it is without witnesses.
+pub fn debug_satisfied(
+    circuit: &VeEncCircuit,
+    instance: &[&[vesta::Scalar]],
+) -> Result<(), Vec<VerifyFailure>> {
+    let instance = instance.iter().map(|column| column.to_vec()).collect();
+    let prover = MockProver::run(K, circuit, instance).expect("the circuit is synthesized");
+    prover.verify()
+}
+
+/// Values computed out of circuit from the witness of a circuit by [`dry_run`], to be
+/// compared with the instance when a proof fails.
+#[cfg(feature = "diagnostics")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessReport {
+    /// Message point p_m of the witness
+    pub p_m: pallas::Point,
+    /// p_m.x - r_encode, the message that p_m encodes, to compare with m.
+    /// None if p_m is the identity.
+    pub encoded_message: Option<pallas::Base>,
+    /// [r_enc]G, to compare with ct_1 of the instance
+    pub ct_1: pallas::Point,
+    /// p_m + [r_enc]pk, to compare with ct_2 of the instance
+    pub ct_2: pallas::Point,
+}
+
+/// Compute the points of the encryption from the witness of the circuit, with the generator
+/// and the public key of the circuit, without running the prover or the mock prover.
+/// Returns None if the circuit is without witnesses.
+#[cfg(feature = "diagnostics")]
+pub fn dry_run(circuit: &VeEncCircuit) -> Option<WitnessReport> {
+    use ff::PrimeField;
+    use pasta_curves::arithmetic::Coordinates;
+
+    let mut witness = None;
+    circuit
+        .p_m
+        .zip(circuit.r_enc)
+        .map(|(p_m, r_enc)| witness = Some((p_m, r_enc)));
+    let (p_m, r_enc) = witness?;
+    // r_enc is a base element, and the base modulus is smaller than the scalar modulus
+    let r_enc = pallas::Scalar::from_repr(r_enc.to_repr()).unwrap();
+
+    let coordinates: Option<Coordinates<pallas::Affine>> = p_m.to_affine().coordinates().into();
+    Some(WitnessReport {
+        p_m,
+        encoded_message: coordinates
+            .map(|coordinates| coordinates.x() - circuit.data_in_transmit.r_encode),
+        ct_1: circuit.generator * r_enc,
+        ct_2: p_m + circuit.elgamal_public_key * r_enc,
+    })
+}
+
+/// Statement proven by the circuit, as named by [`diagnose`]
+#[cfg(feature = "diagnostics")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Statement {
+    Encode,  // (1.1) p_m.x = r_encode + m
+    Ct1,     // (2.1) ct_1 = [r_enc]G
+    Ct2,     // (2.2) ct_2 = p_m + [r_enc]pk
+    REncode, // r_encode of the witness = r_encode of the instance
+}
+
+/// Diagnosis of a circuit which is not satisfied by its witness and the instance
+#[cfg(feature = "diagnostics")]
+#[derive(Debug)]
+pub struct Diagnosis {
+    /// Statements which do not hold for the witness and the instance
+    pub violated: Vec<Statement>,
+    /// Unsatisfied constraints, as reported by the mock prover
+    pub failures: Vec<VerifyFailure>,
+}
+
+/// Check the circuit against the instance with the mock prover, see [`debug_satisfied`], and
+/// name the statements that the witness violates. The mock prover reports the failing cells
+/// with their region offsets, so each statement is also evaluated on the witness values.
+/// Returns None if the circuit is satisfied, if it is without witnesses, e.g. from
+/// [`empty_circuit`], or if the instance has an identity point.
+#[cfg(feature = "diagnostics")]
+pub fn diagnose(circuit: &VeEncCircuit, instance: &VeEncInstance) -> Option<Diagnosis> {
+    use ff::PrimeField;
+    use pasta_curves::arithmetic::Coordinates;
+
+    let mut witness = None;
+    circuit
+        .p_m
+        .zip(circuit.m)
+        .zip(circuit.r_enc)
+        .map(|((p_m, m), r_enc)| witness = Some((p_m, m, r_enc)));
+    let (p_m, m, r_enc) = witness?;
+    // r_enc is a base element, and the base modulus is smaller than the scalar modulus
+    let r_enc = pallas::Scalar::from_repr(r_enc.to_repr()).unwrap();
+
+    let public_inputs = instance.to_halo2_instance().ok()?;
+    let public_inputs: Vec<&[vesta::Scalar]> = public_inputs.iter().map(|c| &c[..]).collect();
+    let failures = debug_satisfied(circuit, &public_inputs).err()?;
+
+    let mut violated = vec![];
+    let coordinates: Option<Coordinates<pallas::Affine>> = p_m.to_affine().coordinates().into();
+    // (1.2), p_m on the curve, is enforced by the gate of the non-identity point p_m, which
+    // any pallas::Point satisfies, so it is not a statement of its own
+    match coordinates {
+        Some(coordinates) if *coordinates.x() == m + circuit.data_in_transmit.r_encode => {}
+        _ => violated.push(Statement::Encode),
+    }
+    if instance.generator * r_enc != instance.data_in_transmit.ct.c1 {
+        violated.push(Statement::Ct1);
+    }
+    if p_m + instance.elgamal_public_key * r_enc != instance.data_in_transmit.ct.c2 {
+        violated.push(Statement::Ct2);
+    }
+    if circuit.data_in_transmit.r_encode != instance.data_in_transmit.r_encode {
+        violated.push(Statement::REncode);
+    }
+
+    Some(Diagnosis { violated, failures })
+}
+
+/// Error returned by [`verify_with_diagnosis`]
+#[cfg(feature = "diagnostics")]
+#[derive(Debug)]
+pub struct VerifyError {
+    /// Error of the verification
+    pub error: Error,
+    /// Diagnosis of the circuit, only computed in debug builds
+    pub diagnosis: Option<Diagnosis>,
+}
+
+#[cfg(feature = "diagnostics")]
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "verification failed: {}", self.error)?;
+        if let Some(diagnosis) = &self.diagnosis {
+            write!(f, ", violated statements: {:?}", diagnosis.violated)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl std::error::Error for VerifyError {}
+
+/// Verify a proof of the circuit, as [`crate::circuits::verify_encryption`] does, for the
+/// prover debugging its own proofs. If the verification fails in a debug build, the circuit is
+/// diagnosed with the mock prover, which is much slower than the verification.
+#[cfg(feature = "diagnostics")]
+pub fn verify_with_diagnosis(
+    params: &Params<vesta::Affine>,
+    vk: &VerifyingKey<vesta::Affine>,
+    circuit: &VeEncCircuit,
+    instance: &VeEncInstance,
+    proof: &[u8],
+) -> Result<(), VerifyError> {
+    use crate::circuits::verify_encryption;
+
+    let public_inputs = instance
+        .to_halo2_instance()
+        .map_err(|error| VerifyError { error, diagnosis: None })?;
+    let public_inputs: Vec<&[vesta::Scalar]> = public_inputs.iter().map(|c| &c[..]).collect();
+    verify_encryption(params, vk, &public_inputs, proof).map_err(|error| VerifyError {
+        error,
+        diagnosis: if cfg!(debug_assertions) {
+            diagnose(circuit, instance)
+        } else {
+            None
+        },
+    })
+}
+
+// Errors returned when loading the witnesses of a circuit from JSON, with the name of the field
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub enum WitnessJsonError {
+    Json(serde_json::Error),           // the input is not a JSON object of the expected fields
+    InvalidHex(&'static str),          // the field is not the hex encoding of 32 bytes
+    InvalidFieldElement(&'static str), // the field is not a canonical pallas base element
+    InvalidPoint(&'static str),        // the point is not on the curve, or is the identity
+    UnknownWitness,                    // the circuit to serialize is without witnesses
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for WitnessJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessJsonError::Json(error) => write!(f, "invalid witness JSON: {}", error),
+            WitnessJsonError::InvalidHex(name) => {
+                write!(f, "{} is not the hex encoding of 32 bytes", name)
+            }
+            WitnessJsonError::InvalidFieldElement(name) => {
+                write!(f, "{} is not a canonical pallas base field element", name)
+            }
+            WitnessJsonError::InvalidPoint(name) => {
+                write!(f, "{} is not on the curve, or is the identity", name)
+            }
+            WitnessJsonError::UnknownWitness => write!(f, "the circuit is without witnesses"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for WitnessJsonError {}
+
+/// Affine coordinates of a point, in hex
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PointJson {
+    x: String,
+    y: String,
+}
+
+/// Witnesses and public values of a [`VeEncCircuit`], as written by [`VeEncCircuit::to_json`].
+/// The base point is omitted when it is the generator of pallas.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct VeEncCircuitJson {
+    m: String,
+    p_m: PointJson,
+    r_enc: String,
+    c1: PointJson,
+    c2: PointJson,
+    r_encode: String,
+    public_key: PointJson,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    generator: Option<PointJson>,
+}
+
+#[cfg(feature = "serde")]
+fn base_to_hex(element: &pallas::Base) -> String {
+    hex::encode(element.to_repr())
+}
+
+#[cfg(feature = "serde")]
+fn base_from_hex(name: &'static str, text: &str) -> Result<pallas::Base, WitnessJsonError> {
+    let bytes: [u8; 32] = hex::decode(text)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(WitnessJsonError::InvalidHex(name))?;
+    Option::from(pallas::Base::from_repr(bytes))
+        .ok_or(WitnessJsonError::InvalidFieldElement(name))
+}
+
+#[cfg(feature = "serde")]
+fn point_to_json(
+    name: &'static str,
+    point: &pallas::Point,
+) -> Result<PointJson, WitnessJsonError> {
+    let (x, y) = point_to_xy(point).map_err(|_| WitnessJsonError::InvalidPoint(name))?;
+    Ok(PointJson {
+        x: base_to_hex(&x),
+        y: base_to_hex(&y),
+    })
+}
+
+// The coordinates are checked to be on the curve, and (0, 0), the identity, is rejected
+#[cfg(feature = "serde")]
+fn point_from_json(
+    name: &'static str,
+    json: &PointJson,
+) -> Result<pallas::Point, WitnessJsonError> {
+    let x = base_from_hex(name, &json.x)?;
+    let y = base_from_hex(name, &json.y)?;
+    let point: Option<Affine> = Affine::from_xy(x, y).into();
+    point
+        .map(pallas::Point::from)
+        .filter(|point| !bool::from(point.is_identity()))
+        .ok_or(WitnessJsonError::InvalidPoint(name))
+}
+
+#[cfg(feature = "serde")]
+impl VeEncCircuit {
+    /// Load a circuit from JSON, e.g. a fixture written by [`VeEncCircuit::to_json`].
+    ///
+    /// The JSON is an object with the witnesses `m`, `p_m` and `r_enc`, the ciphertext `c1`,
+    /// `c2` and `r_encode`, the `public_key`, and optionally the base point `generator`, the
+    /// generator of pallas by default. Field elements are the hex of their 32-byte canonical
+    /// little-endian encoding, and points are objects with the affine coordinates `x` and `y`.
+    /// Fails if a field element is not canonical, or if a point is not on the curve or is the
+    /// identity. The witnesses are not checked to be an encryption, see [`debug_satisfied`].
+    pub fn from_json(json: &str) -> Result<VeEncCircuit, WitnessJsonError> {
+        let json: VeEncCircuitJson = serde_json::from_str(json).map_err(WitnessJsonError::Json)?;
+        let generator = match &json.generator {
+            Some(generator) => point_from_json("generator", generator)?,
+            None => pallas::Point::generator(),
+        };
+        Ok(VeEncCircuit {
+            data_in_transmit: DataInTransmit::new(
+                ElGamalCiphertext::new(
+                    point_from_json("c1", &json.c1)?,
+                    point_from_json("c2", &json.c2)?,
+                ),
+                base_from_hex("r_encode", &json.r_encode)?,
+            ),
+            elgamal_public_key: point_from_json("public_key", &json.public_key)?,
+            generator,
+            m: Value::known(base_from_hex("m", &json.m)?),
+            p_m: Value::known(point_from_json("p_m", &json.p_m)?),
+            r_enc: Value::known(base_from_hex("r_enc", &json.r_enc)?),
+        })
+    }
+
+    /// Write the witnesses and the public values of the circuit to JSON, see
+    /// [`VeEncCircuit::from_json`]. Fails for a circuit without witnesses.
+    pub fn to_json(&self) -> Result<String, WitnessJsonError> {
+        let (mut m, mut p_m, mut r_enc) = (None, None, None);
+        self.m.map(|value| m = Some(value));
+        self.p_m.map(|value| p_m = Some(value));
+        self.r_enc.map(|value| r_enc = Some(value));
+        let (m, p_m, r_enc) = match (m, p_m, r_enc) {
+            (Some(m), Some(p_m), Some(r_enc)) => (m, p_m, r_enc),
+            _ => return Err(WitnessJsonError::UnknownWitness),
+        };
+
+        let json = VeEncCircuitJson {
+            m: base_to_hex(&m),
+            p_m: point_to_json("p_m", &p_m)?,
+            r_enc: base_to_hex(&r_enc),
+            c1: point_to_json("c1", &self.data_in_transmit.c1())?,
+            c2: point_to_json("c2", &self.data_in_transmit.c2())?,
+            r_encode: base_to_hex(&self.data_in_transmit.r_encode()),
+            public_key: point_to_json("public_key", &self.elgamal_public_key)?,
+            generator: if self.generator == pallas::Point::generator() {
+                None
+            } else {
+                Some(point_to_json("generator", &self.generator)?)
+            },
+        };
+        serde_json::to_string_pretty(&json).map_err(WitnessJsonError::Json)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
 mod tests {
-    use super::{create_circuit, VeEncInstance, K};
-    use crate::elgamal::elgamal::ElGamalKeypair;
-    use crate::encode::utf8::{
-        convert_string_to_u8_array, convert_u8_array_to_u64_array, split_message_into_blocks,
+    use super::{
+        create_circuit, create_circuit_with_generator, debug_satisfied, minimum_k, VeEncCircuit,
+        VeEncCircuitBuilder, VeEncInstance, ADVICE_COLUMNS, FIXED_COLUMNS, K,
+    };
+    use crate::circuits::{
+        estimate_constraints, estimate_proof_size, find_minimum_k, keygen, point_to_xy,
+        prove_encryption, read_params, recommended_k, verify_batch, verify_encryption,
+        write_params, CIRCUIT_VERSION, PROOF_HEADER_SIZE, PROOF_MAGIC,
     };
+    use crate::circuits::verifiable_encryption_batch::VeEncBatchCircuit;
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::elgamal::extended_elgamal::extended_elgamal_decrypt;
+    use crate::encode::utf8::{convert_u8_array_to_u64_array, split_message_into_blocks};
+    use group::{Curve, Group};
+    use halo2_proofs::dev::VerifyFailure;
+    use halo2_proofs::plonk::Circuit;
     use halo2_proofs::poly::commitment::Params;
-    use halo2_proofs::transcript::{Blake2bRead, Blake2bWrite, Challenge255};
     use halo2_proofs::{plonk};
-    use halo2_proofs::plonk::SingleVerifier;
+    use pasta_curves::arithmetic::{CurveAffine, CurveExt};
     use pasta_curves::{pallas, vesta};
     use rand::rngs::OsRng;
 
 
     #[test]
     fn round_trip() {
-        let mut rng = OsRng;
-
         // Split the message into blocks
         let test_message = "This is a short message.";
         // let test_message = "This is a long message for test!";
 
         // Specify the block size as 31 bytes
         let block_size = 31;
-        let blocks = split_message_into_blocks(test_message, block_size);
+        let blocks = split_message_into_blocks(test_message, block_size).unwrap();
 
         // Elgamal keygen
         let keypair = ElGamalKeypair::new();
@@ -396,60 +1153,47 @@ mod tests {
         // Setup phase: generate parameters for the circuit.
         let params = Params::new(K);
 
+        // Generate the verification key vk and proving key pk once from a circuit without witnesses.
+        // The circuit shape is identical for every block, so the keys are shared by all blocks.
+        let empty_circuit = VeEncCircuit {
+            elgamal_public_key: keypair.public_key,
+            ..Default::default()
+        };
+        let vk = plonk::keygen_vk(&params, &empty_circuit).unwrap();
+        let pk = plonk::keygen_pk(&params, vk.clone(), &empty_circuit).unwrap();
+
         // Create a circuit for each block
         for (_, block) in blocks.iter().enumerate() {
             // convert message block to a Fp element
-            let bytes = convert_string_to_u8_array(block);
-            let m = pallas::Base::from_raw(convert_u8_array_to_u64_array(bytes));
+            let limbs = convert_u8_array_to_u64_array(block).unwrap();
+            let m = pallas::Base::from_raw(limbs);
 
             // Step 1. create a circuit
-            let circuit = vec![create_circuit(m, keypair.clone())];
+            let circuit = create_circuit(m, keypair.clone());
 
             // Step 2. arrange the public instance.
-            let instance = vec![VeEncInstance {
-                data_in_transmit: circuit[0].data_in_transmit.clone(),
-                elgamal_public_key: circuit[0].elgamal_public_key.clone(),
-            }];
+            let instance = VeEncInstance::new(
+                circuit.data_in_transmit.clone(),
+                circuit.elgamal_public_key,
+            );
 
             // Instance transformation
-            let instance: Vec<_> = instance.iter().map(|i| i.to_halo2_instance()).collect();
-            let instance: Vec<Vec<_>> = instance
-                .iter()
-                .map(|i| i.iter().map(|c| &c[..]).collect())
-                .collect();
-            let instance: Vec<_> = instance.iter().map(|i| &i[..]).collect();
-
-            // Step 3. generate the verification key vk and proving key pk from the params and circuit.
-            let vk = plonk::keygen_vk(&params, &circuit[0]).unwrap();
-            let pk = plonk::keygen_pk(&params, vk.clone(), &circuit[0]).unwrap();
-
-            // Step 4. Proving phase: create a proof with public instance and witness.
-            // The proof generation will need an internal transcript for Fiat-Shamir transformation.
-            let mut transcript = Blake2bWrite::<_, vesta::Affine, _>::init(vec![]);
-            plonk::create_proof(
-                &params,
-                &pk.clone(),
-                &circuit,
-                &instance,
-                &mut rng,
-                &mut transcript,
-            )
-                .unwrap();
+            let instance = instance.to_halo2_instance().unwrap();
+            let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
 
-            let proof = transcript.finalize();
+            // Step 3. Proving phase: create a proof with public instance and witness.
+            // The proof generation will need an internal transcript for Fiat-Shamir transformation.
+            let proof = prove_encryption(&params, &pk, circuit.clone(), &instance).unwrap();
 
-            // Step 5. Verification phase: verify the proof against the public instance.
-            let strategy = SingleVerifier::new(&params);
-            let mut transcript: Blake2bRead<&[u8], vesta::Affine, Challenge255<vesta::Affine>> =
-                Blake2bRead::init(&proof[..]);
-            let verify = plonk::verify_proof(&params, &vk, strategy, &instance, &mut transcript);
+            // Step 4. Verification phase: verify the proof against the public instance.
+            let verify = verify_encryption(&params, &vk, &instance, &proof);
             // Round-trip assertion: check the proof is valid and matches expected values.
             assert!(verify.is_ok());
 
             // Calculate the circuit cost
             let circuit_cost =
-                halo2_proofs::dev::CircuitCost::<vesta::Point, _>::measure(K, &circuit[0]);
-            let expected_proof_size = usize::from(circuit_cost.proof_size(instance.len()));
+                halo2_proofs::dev::CircuitCost::<vesta::Point, _>::measure(K, &circuit);
+            let expected_proof_size = PROOF_HEADER_SIZE + usize::from(circuit_cost.proof_size(1));
             println!("Proof length: {}B", expected_proof_size);
 
             assert_eq!(proof.len(), expected_proof_size);
@@ -457,4 +1201,926 @@ mod tests {
         }
 
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "instrument")]
+    #[test]
+    fn prove_stats() {
+        use crate::circuits::{
+            keygen_with_stats, prove_encryption_with_stats, verify_encryption_with_stats,
+            ProveStats,
+        };
+
+        let circuit = create_circuit(pallas::Base::from(42), ElGamalKeypair::new());
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                .to_halo2_instance()
+                .unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        let mut stats = ProveStats::default();
+        let params = Params::new(K);
+        let (vk, pk) =
+            keygen_with_stats(&params, &circuit.without_witnesses(), &mut stats).unwrap();
+        assert!(stats.keygen.is_some());
+        assert_eq!((stats.prove, stats.verify), (None, None));
+
+        let proof =
+            prove_encryption_with_stats(&params, &pk, circuit, &instance, &mut stats).unwrap();
+        assert!(stats.prove.is_some());
+        assert_eq!(stats.proof_size, proof.len());
+        assert_eq!(stats.proof_size, estimate_proof_size(K, 1));
+
+        verify_encryption_with_stats(&params, &vk, &instance, &proof, &mut stats).unwrap();
+        assert!(stats.verify.is_some());
+        assert_eq!(stats.proof_size, proof.len());
+    }
+
+    #[test]
+    fn shared_keys_ten_blocks() {
+        // A 10-block message: 300 bytes and 10 bytes of padding
+        let test_message = "0123456789abcdefghijklmnopqrst".repeat(10);
+        let blocks = split_message_into_blocks(&test_message, 31).unwrap();
+        assert_eq!(blocks.len(), 10);
+
+        let keypair = ElGamalKeypair::new();
+        let params = Params::new(K);
+
+        // Generate the keys once, from a circuit without witnesses.
+        let empty_circuit = create_circuit(pallas::Base::zero(), keypair.clone()).without_witnesses();
+        let vk = plonk::keygen_vk(&params, &empty_circuit).unwrap();
+        let pk = plonk::keygen_pk(&params, vk.clone(), &empty_circuit).unwrap();
+
+        let mut instances = Vec::new();
+        let mut proofs = Vec::new();
+        for block in blocks.iter() {
+            let limbs = convert_u8_array_to_u64_array(block).unwrap();
+            let m = pallas::Base::from_raw(limbs);
+            let circuit = create_circuit(m, keypair.clone());
+
+            let instance = VeEncInstance::new(
+                circuit.data_in_transmit.clone(),
+                circuit.elgamal_public_key,
+            )
+            .to_halo2_instance().unwrap();
+            let proof = {
+                let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+                prove_encryption(&params, &pk, circuit, &instance).unwrap()
+            };
+            instances.push(instance);
+            proofs.push(proof);
+        }
+
+        // Each proof created with the shared pk verifies against the shared vk, and against
+        // its own block only
+        let instances: Vec<Vec<&[vesta::Scalar]>> = instances
+            .iter()
+            .map(|instance| instance.iter().map(|c| &c[..]).collect())
+            .collect();
+        for (i, proof) in proofs.iter().enumerate() {
+            assert!(verify_encryption(&params, &vk, &instances[i], proof).is_ok());
+            let other = &instances[(i + 1) % instances.len()];
+            assert!(verify_encryption(&params, &vk, other, proof).is_err());
+        }
+        let instances: Vec<&[&[vesta::Scalar]]> = instances.iter().map(|i| &i[..]).collect();
+        assert!(verify_batch(&params, &vk, &instances, &proofs).is_ok());
+    }
+
+    #[test]
+    fn keys_from_serialized_params() {
+        let keypair = ElGamalKeypair::new();
+        let params = Params::new(K);
+
+        // prove with the original keys
+        let circuit = create_circuit(pallas::Base::from(42), keypair.clone());
+        let (_, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                .to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        let proof = prove_encryption(&params, &pk, circuit.clone(), &instance).unwrap();
+
+        // the verifier regenerates the keys from the serialized params
+        let bytes = write_params(&params).unwrap();
+        let read_params = read_params(&bytes).unwrap();
+        assert_eq!(read_params.k(), K);
+        let (vk, _) = keygen(&read_params, &circuit.without_witnesses()).unwrap();
+        assert!(verify_encryption(&read_params, &vk, &instance, &proof).is_ok());
+    }
+
+    #[test]
+    fn cached_params() {
+        use crate::circuits::load_or_create_params;
+        use std::{fs, io};
+
+        let path = std::env::temp_dir().join(format!("ve_params_{}.bin", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        // the first call creates the file, the second one reads it
+        let created = load_or_create_params(&path, K).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(bytes, write_params(&created).unwrap());
+        let loaded = load_or_create_params(&path, K).unwrap();
+        assert_eq!(loaded.k(), K);
+        assert_eq!(write_params(&loaded).unwrap(), bytes);
+
+        // params cached for another K are rejected, and kept
+        let error = load_or_create_params(&path, K + 1).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn instance_builder_layout() {
+        use super::InstanceBuilder;
+        use crate::circuits::verifiable_encryption_batch::VeEncBatchInstance;
+
+        let keypair = ElGamalKeypair::new();
+        let circuit = create_circuit(pallas::Base::from(42), keypair.clone());
+        let data_in_transmit = circuit.data_in_transmit.clone();
+        let (c1_x, c1_y) = point_to_xy(&data_in_transmit.ct.c1).unwrap();
+        let (c2_x, c2_y) = point_to_xy(&data_in_transmit.ct.c2).unwrap();
+        let (pk_x, pk_y) = point_to_xy(&keypair.public_key).unwrap();
+        let (g_x, g_y) = point_to_xy(&pallas::Point::generator()).unwrap();
+        let expected = vec![
+            vesta::Scalar::zero(),
+            c1_x,
+            c1_y,
+            c2_x,
+            c2_y,
+            pk_x,
+            pk_y,
+            g_x,
+            g_y,
+            data_in_transmit.r_encode,
+        ];
+
+        // a single block is the layout of the circuit
+        let mut builder = InstanceBuilder::new();
+        builder.push_ciphertext(&data_in_transmit, &keypair.public_key).unwrap();
+        assert_eq!(builder.num_blocks(), 1);
+        assert_eq!(builder.build(), [expected.clone()]);
+        let instance = VeEncInstance::new(data_in_transmit.clone(), keypair.public_key);
+        assert_eq!(instance.to_halo2_instance().unwrap()[0].to_vec(), expected);
+
+        // blocks are appended in order, and an identity point appends nothing
+        let other = create_circuit(pallas::Base::from(7), keypair.clone()).data_in_transmit;
+        let mut builder = InstanceBuilder::new();
+        builder.push_ciphertext(&data_in_transmit, &keypair.public_key).unwrap();
+        assert!(builder
+            .push_ciphertext(&other, &pallas::Point::identity())
+            .is_err());
+        builder.push_ciphertext(&other, &keypair.public_key).unwrap();
+        assert_eq!(builder.num_blocks(), 2);
+        let batch = VeEncBatchInstance::new(vec![
+            instance,
+            VeEncInstance::new(other, keypair.public_key),
+        ]);
+        assert_eq!(builder.build(), batch.to_halo2_instance().unwrap());
+    }
+
+    #[test]
+    fn r_enc_converted_once() {
+        use halo2_proofs::dev::MockProver;
+
+        // r_enc is converted once and shared by [r_enc]G and [r_enc]pk
+        let generator = pallas::Point::random(OsRng);
+        for circuit in [
+            create_circuit(pallas::Base::from(42), ElGamalKeypair::new()),
+            create_circuit_with_generator(
+                pallas::Base::from(7),
+                ElGamalKeypair::new_with_generator(&generator),
+                generator,
+            )
+            .unwrap(),
+        ] {
+            let instance = VeEncInstance::new_with_generator(
+                circuit.data_in_transmit.clone(),
+                circuit.elgamal_public_key,
+                circuit.generator,
+            )
+            .to_halo2_instance()
+            .unwrap();
+            let prover =
+                MockProver::run(K, &circuit, instance.iter().map(|c| c.to_vec()).collect())
+                    .unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn k_set_by_lookup_table() {
+        use halo2_gadgets::sinsemilla::primitives as sinsemilla;
+
+        // the arithmetic gates share columns with the ECC chip, and proofs verify (see
+        // round_trip); the rows of a block do not set K, the 2^10 rows of the table do
+        assert_eq!(minimum_k(), sinsemilla::K as u32 + 1);
+    }
+
+    #[test]
+    fn recommended_k_of_blocks() {
+        assert_eq!(recommended_k(1), 11);
+        assert_eq!(recommended_k(1), K);
+        assert_eq!(recommended_k(1), minimum_k());
+
+        // the lookup table is shared, the ECC operations of more blocks need more rows
+        let k = recommended_k(4);
+        assert!(k >= K);
+        let block = create_circuit(pallas::Base::from(42), ElGamalKeypair::new());
+        let batch = VeEncBatchCircuit::new(vec![block.without_witnesses(); 4]);
+        assert_eq!(find_minimum_k(&batch).unwrap(), k);
+        // from the cache
+        assert_eq!(recommended_k(4), k);
+    }
+
+    #[test]
+    fn prove_with_larger_k() {
+        assert!(minimum_k() <= K);
+
+        let keypair = ElGamalKeypair::new();
+        let circuit = create_circuit(pallas::Base::from(42), keypair);
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                .to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        for k in [K, K + 1] {
+            let params = Params::new(k);
+            let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+            let proof = prove_encryption(&params, &pk, circuit.clone(), &instance).unwrap();
+            assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+        }
+    }
+
+    #[test]
+    fn proof_header() {
+        let circuit = create_circuit(pallas::Base::from(42), ElGamalKeypair::new());
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                .to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove_encryption(&params, &pk, circuit.clone(), &instance).unwrap();
+        assert_eq!(proof[..PROOF_MAGIC.len()], PROOF_MAGIC);
+        assert_eq!(proof[PROOF_MAGIC.len()], CIRCUIT_VERSION);
+        assert_eq!(u32::from(proof[PROOF_MAGIC.len() + 1]), K);
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        let is_header_error = |result: Result<(), plonk::Error>, expected: &str| match result {
+            Err(plonk::Error::Transcript(error)) => {
+                error.kind() == std::io::ErrorKind::InvalidData
+                    && error.to_string().contains(expected)
+            }
+            _ => false,
+        };
+
+        // a proof of another circuit version is rejected before reading the transcript
+        let mut bumped = proof.clone();
+        bumped[PROOF_MAGIC.len()] += 1;
+        let result = verify_encryption(&params, &vk, &instance, &bumped);
+        assert!(is_header_error(result, "circuit version"));
+        let result = verify_batch(&params, &vk, &[&instance], &[bumped]);
+        assert!(is_header_error(result, "circuit version"));
+
+        // and so are a proof for another K, and bytes which are not a proof
+        let mut other_k = proof.clone();
+        other_k[PROOF_MAGIC.len() + 1] += 1;
+        let result = verify_encryption(&params, &vk, &instance, &other_k);
+        assert!(is_header_error(result, "K ="));
+        let result = verify_encryption(&params, &vk, &instance, &proof[PROOF_HEADER_SIZE..]);
+        assert!(is_header_error(result, "not a verifiable encryption proof"));
+    }
+
+    #[test]
+    fn column_counts() {
+        use halo2_proofs::plonk::ConstraintSystem;
+
+        let mut cs = ConstraintSystem::default();
+        VeEncCircuit::configure(&mut cs);
+        assert_eq!(cs.num_advice_columns(), ADVICE_COLUMNS);
+        assert_eq!(cs.num_fixed_columns(), FIXED_COLUMNS);
+
+        let stats = estimate_constraints();
+        assert_eq!(stats.advice_columns, ADVICE_COLUMNS);
+        assert_eq!(stats.fixed_columns, FIXED_COLUMNS);
+    }
+
+    #[test]
+    fn estimated_proof_size() {
+        let stats = estimate_constraints();
+        assert_eq!(stats.instance_columns, 1);
+        assert_eq!(stats.proof_size, estimate_proof_size(K, 1));
+
+        let params = Params::new(K);
+        let circuit = create_circuit(pallas::Base::from(7), ElGamalKeypair::new());
+        let (_, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                .to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert_eq!(proof.len(), stats.proof_size);
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn dry_run_matches_instance() {
+        use super::dry_run;
+
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let (circuit, instance, _) = VeEncCircuitBuilder::new(m, keypair.public_key)
+            .build()
+            .unwrap();
+
+        let report = dry_run(&circuit).unwrap();
+        assert_eq!(report.ct_1, instance.data_in_transmit.c1());
+        assert_eq!(report.ct_2, instance.data_in_transmit.c2());
+        assert_eq!(report.encoded_message, Some(m));
+        assert!(dry_run(&circuit.without_witnesses()).is_none());
+
+        // a corrupted r_enc gives another ct_1 than the instance
+        let corrupted = VeEncCircuit {
+            r_enc: circuit.r_enc.map(|r_enc| r_enc + pallas::Base::one()),
+            ..circuit
+        };
+        let report = dry_run(&corrupted).unwrap();
+        assert_ne!(report.ct_1, instance.data_in_transmit.c1());
+        assert_eq!(report.encoded_message, Some(m));
+    }
+
+    #[test]
+    fn debug_satisfied_reports_corrupted_p_m() {
+        use halo2_proofs::circuit::Value;
+
+        let circuit = create_circuit(pallas::Base::from(42), ElGamalKeypair::new());
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                .to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        assert!(debug_satisfied(&circuit, &instance).is_ok());
+
+        // p_m = (2, 2) is not on the curve: 2^2 != 2^3 + 5. The checked constructors of pasta
+        // reject it, so the affine point is built from its coordinates directly.
+        let two = pallas::Base::from(2);
+        // SAFETY: an affine point is two base field elements; both are equal, so their order
+        // in memory does not matter.
+        let off_curve =
+            unsafe { std::mem::transmute::<[pallas::Base; 2], pallas::Affine>([two; 2]) };
+        assert!(bool::from(pallas::Affine::from_xy(two, two).is_none()));
+        let corrupted = VeEncCircuit {
+            p_m: Value::known(pallas::Point::from(off_curve)),
+            ..circuit
+        };
+        let failures = debug_satisfied(&corrupted, &instance).unwrap_err();
+        // the curve equation of p_m is checked by the gate witnessing a non-identity point
+        assert!(failures.iter().any(|failure| {
+            matches!(failure, VerifyFailure::ConstraintNotSatisfied { .. })
+                && failure.to_string().contains("witness non-identity point")
+        }));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_proofs() {
+        use crate::circuits::prove_all_blocks;
+
+        // A message of 8 blocks of 31 bytes
+        let test_message = "0123456789".repeat(24);
+        let blocks = split_message_into_blocks(&test_message, 31).unwrap();
+        assert_eq!(blocks.len(), 8);
+
+        let keypair = ElGamalKeypair::new();
+        let circuits: Vec<VeEncCircuit> = blocks
+            .iter()
+            .map(|block| {
+                let m = pallas::Base::from_raw(convert_u8_array_to_u64_array(block).unwrap());
+                create_circuit(m, keypair.clone())
+            })
+            .collect();
+
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuits[0].without_witnesses()).unwrap();
+
+        // one proof per block, in the order of the blocks, each for its own instance
+        let proofs = prove_all_blocks(&params, &pk, &circuits).unwrap();
+        assert_eq!(proofs.len(), circuits.len());
+        let instances: Vec<_> = circuits
+            .iter()
+            .map(|circuit| {
+                VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                    .to_halo2_instance()
+                    .unwrap()
+            })
+            .collect();
+        for (instance, proof) in instances.iter().zip(proofs.iter()) {
+            let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+            assert!(verify_encryption(&params, &vk, &instance, proof).is_ok());
+        }
+        let first: Vec<&[vesta::Scalar]> = instances[0].iter().map(|c| &c[..]).collect();
+        assert!(verify_encryption(&params, &vk, &first, &proofs[1]).is_err());
+    }
+
+    #[test]
+    fn batch_verification() {
+        let keypair = ElGamalKeypair::new();
+        let circuits: Vec<VeEncCircuit> = (1..=3)
+            .map(|m| create_circuit(pallas::Base::from(m), keypair.clone()))
+            .collect();
+
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuits[0].without_witnesses()).unwrap();
+
+        let instances: Vec<_> = circuits
+            .iter()
+            .map(|circuit| {
+                VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                    .to_halo2_instance().unwrap()
+            })
+            .collect();
+        let instances: Vec<Vec<&[vesta::Scalar]>> = instances
+            .iter()
+            .map(|instance| instance.iter().map(|c| &c[..]).collect())
+            .collect();
+        let instances: Vec<&[&[vesta::Scalar]]> = instances.iter().map(|i| &i[..]).collect();
+
+        let mut proofs: Vec<Vec<u8>> = circuits
+            .iter()
+            .zip(instances.iter())
+            .map(|(circuit, instance)| {
+                prove_encryption(&params, &pk, circuit.clone(), instance).unwrap()
+            })
+            .collect();
+        assert!(verify_batch(&params, &vk, &instances, &proofs).is_ok());
+
+        // a single corrupted proof makes the batch fail
+        let last = proofs[2].len() - 1;
+        proofs[2][last] ^= 1;
+        assert!(verify_batch(&params, &vk, &instances, &proofs).is_err());
+    }
+
+    #[test]
+    fn accumulated_verification() {
+        use crate::circuits::{verify_accumulated, ProofAccumulator};
+
+        let keypair = ElGamalKeypair::new();
+        let circuits: Vec<VeEncCircuit> = (1..=2)
+            .map(|m| create_circuit(pallas::Base::from(m), keypair.clone()))
+            .collect();
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuits[0].without_witnesses()).unwrap();
+
+        let instances: Vec<_> = circuits
+            .iter()
+            .map(|circuit| {
+                VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                    .to_halo2_instance().unwrap()
+            })
+            .collect();
+        let instances: Vec<Vec<&[vesta::Scalar]>> = instances
+            .iter()
+            .map(|instance| instance.iter().map(|c| &c[..]).collect())
+            .collect();
+        let proofs: Vec<Vec<u8>> = circuits
+            .iter()
+            .zip(instances.iter())
+            .map(|(circuit, instance)| {
+                prove_encryption(&params, &pk, circuit.clone(), instance).unwrap()
+            })
+            .collect();
+
+        // two valid proofs are accepted when the accumulator is finalized
+        let mut accumulator = ProofAccumulator::new(&params);
+        assert!(accumulator.is_empty());
+        for (instance, proof) in instances.iter().zip(proofs.iter()) {
+            accumulator = verify_accumulated(&params, &vk, instance, proof, accumulator).unwrap();
+        }
+        assert_eq!(accumulator.len(), 2);
+        assert!(accumulator.finalize());
+
+        // a proof checked against the instance of the other proof is only rejected at the end
+        let accumulator = verify_accumulated(
+            &params,
+            &vk,
+            &instances[0],
+            &proofs[0],
+            ProofAccumulator::new(&params),
+        )
+        .unwrap();
+        let accumulator =
+            verify_accumulated(&params, &vk, &instances[0], &proofs[1], accumulator).unwrap();
+        assert!(!accumulator.finalize());
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn diagnosis_of_bad_r_enc() {
+        use super::{diagnose, verify_with_diagnosis, Statement};
+        use ff::Field;
+
+        let circuit = create_circuit(pallas::Base::from(42), ElGamalKeypair::new());
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key);
+        assert!(diagnose(&circuit, &instance).is_none());
+        // a circuit without witnesses is not diagnosed
+        assert!(diagnose(&circuit.without_witnesses(), &instance).is_none());
+        assert!(diagnose(&super::empty_circuit(circuit.elgamal_public_key), &instance).is_none());
+
+        // r_enc is used by both ct_1 = [r_enc]G and ct_2 = p_m + [r_enc]pk
+        let bad_r_enc = VeEncCircuit {
+            r_enc: circuit.r_enc.map(|r_enc| r_enc + pallas::Base::one()),
+            ..circuit.clone()
+        };
+        let diagnosis = diagnose(&bad_r_enc, &instance).unwrap();
+        assert_eq!(diagnosis.violated, vec![Statement::Ct1, Statement::Ct2]);
+        assert!(!diagnosis.failures.is_empty());
+
+        // the proof of the bad witness fails to verify, with the diagnosis in debug builds
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let public_inputs = instance.to_halo2_instance().unwrap();
+        let public_inputs: Vec<&[vesta::Scalar]> = public_inputs.iter().map(|c| &c[..]).collect();
+        let proof = prove_encryption(&params, &pk, bad_r_enc.clone(), &public_inputs).unwrap();
+        let error = verify_with_diagnosis(&params, &vk, &bad_r_enc, &instance, &proof).unwrap_err();
+        if cfg!(debug_assertions) {
+            assert!(error.diagnosis.unwrap().violated.contains(&Statement::Ct1));
+        }
+
+        let proof = prove_encryption(&params, &pk, circuit.clone(), &public_inputs).unwrap();
+        assert!(verify_with_diagnosis(&params, &vk, &circuit, &instance, &proof).is_ok());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn async_proof() {
+        use crate::circuits::prove_encryption_async;
+        use std::sync::Arc;
+
+        let circuit = create_circuit(pallas::Base::from(42), ElGamalKeypair::new());
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                .to_halo2_instance().unwrap();
+        let params = Arc::new(Params::new(K));
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let proof = runtime
+            .block_on(prove_encryption_async(
+                params.clone(),
+                Arc::new(pk),
+                circuit,
+                instance.to_vec(),
+            ))
+            .unwrap();
+
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+    }
+
+    #[test]
+    fn deterministic_instance() {
+        let circuit = create_circuit(pallas::Base::from(3), ElGamalKeypair::new());
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key);
+        assert_eq!(instance.to_halo2_instance().unwrap(), instance.to_halo2_instance().unwrap());
+    }
+
+    #[test]
+    fn custom_generator() {
+        // A nothing-up-my-sleeve base point, independent of the generator of pallas
+        let generator = pallas::Point::hash_to_curve("QED-it:VerifiableEncryption")(b"G");
+        assert_ne!(generator, pallas::Point::generator());
+
+        let keypair = ElGamalKeypair::new_with_generator(&generator);
+        let circuit =
+            create_circuit_with_generator(pallas::Base::from(42), keypair.clone(), generator)
+                .unwrap();
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, circuit.data_in_transmit.clone()),
+            Ok(pallas::Base::from(42))
+        );
+
+        let instance = VeEncInstance::new_with_generator(
+            circuit.data_in_transmit.clone(),
+            circuit.elgamal_public_key,
+            generator,
+        )
+        .to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove_encryption(&params, &pk, circuit.clone(), &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        // The proof does not verify against the generator of pallas
+        let other = VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+            .to_halo2_instance().unwrap();
+        let other: Vec<&[vesta::Scalar]> = other.iter().map(|c| &c[..]).collect();
+        assert!(verify_encryption(&params, &vk, &other, &proof).is_err());
+        assert!(debug_satisfied(&circuit, &other).is_err());
+    }
+
+    #[test]
+    fn builder_round_trip() {
+        use crate::elgamal::extended_elgamal::EncryptError;
+        use crate::elgamal::extended_elgamal::has_default_r_encode;
+        use crate::encode::encode::is_derived_r_encode;
+
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let (circuit, instance, witness) =
+            VeEncCircuitBuilder::new(m, keypair.public_key).build().unwrap();
+        assert_eq!(witness.m, m);
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, instance.data_in_transmit.clone()),
+            Ok(m)
+        );
+        // without a context, r_encode is derived from the public key and c1
+        assert!(has_default_r_encode(&instance.data_in_transmit, &keypair.public_key));
+
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let instance = instance.to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        // a custom base point, with a keypair generated for it
+        let generator = pallas::Point::random(OsRng);
+        let keypair = ElGamalKeypair::new_with_generator(&generator);
+        let (circuit, instance, _) = VeEncCircuitBuilder::new(m, keypair.public_key)
+            .generator(generator)
+            .build()
+            .unwrap();
+        let instance = instance.to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        assert_eq!(debug_satisfied(&circuit, &instance), Ok(()));
+
+        // the identity is rejected as a public key or a base point
+        let identity = pallas::Point::identity();
+        assert_eq!(
+            VeEncCircuitBuilder::new(m, identity).build().err(),
+            Some(EncryptError::InvalidPublicKey)
+        );
+        assert_eq!(
+            VeEncCircuitBuilder::new(m, keypair.public_key).generator(identity).build().err(),
+            Some(EncryptError::InvalidGenerator)
+        );
+        assert_eq!(
+            create_circuit_with_generator(m, keypair.clone(), identity).err(),
+            Some(EncryptError::InvalidGenerator)
+        );
+
+        // r_encode derived from a context, which the verifier checks
+        let (circuit, instance, _) = VeEncCircuitBuilder::new(m, keypair.public_key)
+            .generator(generator)
+            .context(b"VE", b"block 0")
+            .build()
+            .unwrap();
+        let r_encode = instance.data_in_transmit.r_encode();
+        assert!(is_derived_r_encode(r_encode, b"VE", b"block 0"));
+        let instance = instance.to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        assert_eq!(debug_satisfied(&circuit, &instance), Ok(()));
+    }
+
+    #[test]
+    fn external_message_point() {
+        use super::create_circuit_from_message_point;
+        use crate::elgamal::extended_elgamal::EncryptError;
+        use crate::encode::encode::encode_with_retry;
+        use ff::Field;
+
+        let keypair = ElGamalKeypair::new();
+
+        // a p_m from another step of a protocol, with the r_encode it was encoded with
+        let r_encode = pallas::Base::random(OsRng);
+        let (p_m, r_encode) = encode_with_retry(pallas::Base::from(42), r_encode).unwrap();
+        let coordinates = p_m.to_affine().coordinates().unwrap();
+        let (circuit, instance, witness) = create_circuit_from_message_point(
+            *coordinates.x(),
+            *coordinates.y(),
+            r_encode,
+            keypair.public_key,
+        )
+        .unwrap();
+        assert_eq!(witness.m(), pallas::Base::from(42));
+        assert_eq!(witness.p_m(), p_m);
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, instance.data_in_transmit.clone()),
+            Ok(pallas::Base::from(42))
+        );
+
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let instance = instance.to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        // (x, y + 1) is not on the curve, and (0, 0) encodes the identity
+        let off_curve = create_circuit_from_message_point(
+            *coordinates.x(),
+            *coordinates.y() + pallas::Base::one(),
+            r_encode,
+            keypair.public_key,
+        );
+        assert_eq!(off_curve.err(), Some(EncryptError::InvalidMessagePoint));
+        let zero = pallas::Base::zero();
+        let identity = create_circuit_from_message_point(zero, zero, r_encode, keypair.public_key);
+        assert_eq!(identity.err(), Some(EncryptError::InvalidMessagePoint));
+        let invalid_key = create_circuit_from_message_point(
+            *coordinates.x(),
+            *coordinates.y(),
+            r_encode,
+            pallas::Point::identity(),
+        );
+        assert_eq!(invalid_key.err(), Some(EncryptError::InvalidPublicKey));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn witness_json_round_trip() {
+        use super::WitnessJsonError;
+        use ff::PrimeField;
+
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let (circuit, instance, _) =
+            VeEncCircuitBuilder::new(m, keypair.public_key).build().unwrap();
+        let json = circuit.to_json().unwrap();
+        let loaded = VeEncCircuit::from_json(&json).unwrap();
+        assert_eq!(loaded.to_json().unwrap(), json);
+        let instance = instance.to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        assert_eq!(debug_satisfied(&loaded, &instance), Ok(()));
+
+        // a custom base point is kept
+        let generator = pallas::Point::random(OsRng);
+        let keypair = ElGamalKeypair::new_with_generator(&generator);
+        let (circuit, _, _) = VeEncCircuitBuilder::new(m, keypair.public_key)
+            .generator(generator)
+            .build()
+            .unwrap();
+        let loaded = VeEncCircuit::from_json(&circuit.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.generator, generator);
+
+        // y + 1 is not on the curve, and (0, 0) is the identity
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let (x, y) = point_to_xy(&circuit.data_in_transmit.c1()).unwrap();
+        value["p_m"]["x"] = hex::encode(x.to_repr()).into();
+        value["p_m"]["y"] = hex::encode((y + pallas::Base::one()).to_repr()).into();
+        assert!(matches!(
+            VeEncCircuit::from_json(&value.to_string()),
+            Err(WitnessJsonError::InvalidPoint("p_m"))
+        ));
+        value["p_m"]["x"] = hex::encode([0u8; 32]).into();
+        value["p_m"]["y"] = hex::encode([0u8; 32]).into();
+        assert!(matches!(
+            VeEncCircuit::from_json(&value.to_string()),
+            Err(WitnessJsonError::InvalidPoint("p_m"))
+        ));
+
+        // invalid hex, a non-canonical field element, and missing fields
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["m"] = "not hex".into();
+        assert!(matches!(
+            VeEncCircuit::from_json(&value.to_string()),
+            Err(WitnessJsonError::InvalidHex("m"))
+        ));
+        value["m"] = hex::encode([0xff; 32]).into();
+        assert!(matches!(
+            VeEncCircuit::from_json(&value.to_string()),
+            Err(WitnessJsonError::InvalidFieldElement("m"))
+        ));
+        assert!(matches!(VeEncCircuit::from_json("{}"), Err(WitnessJsonError::Json(_))));
+
+        // a circuit without witnesses cannot be written
+        assert!(matches!(
+            circuit.without_witnesses().to_json(),
+            Err(WitnessJsonError::UnknownWitness)
+        ));
+    }
+
+    #[test]
+    fn identity_witness() {
+        use super::WitnessError;
+        use halo2_proofs::circuit::Value;
+        use halo2_proofs::dev::MockProver;
+        use std::io;
+
+        let circuit = create_circuit(pallas::Base::from(42), ElGamalKeypair::new());
+        let instance =
+            VeEncInstance::new(circuit.data_in_transmit.clone(), circuit.elgamal_public_key)
+                .to_halo2_instance().unwrap();
+        assert_eq!(circuit.check_witnesses(), Ok(()));
+        assert_eq!(circuit.without_witnesses().check_witnesses(), Ok(()));
+
+        let witness_error = |circuit: &VeEncCircuit| {
+            match MockProver::run(K, circuit, instance.iter().map(|c| c.to_vec()).collect()) {
+                Err(plonk::Error::Transcript(error)) => {
+                    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+                    *error.get_ref().unwrap().downcast_ref::<WitnessError>().unwrap()
+                }
+                _ => panic!("the circuit with an identity point is synthesized"),
+            }
+        };
+
+        // a crafted identity p_m is reported before the ECC chip witnesses it
+        let identity_p_m = VeEncCircuit {
+            p_m: Value::known(pallas::Point::identity()),
+            ..circuit.clone()
+        };
+        assert_eq!(identity_p_m.check_witnesses(), Err(WitnessError::IdentityMessagePoint));
+        assert_eq!(witness_error(&identity_p_m), WitnessError::IdentityMessagePoint);
+
+        let identity_public_key = VeEncCircuit {
+            elgamal_public_key: pallas::Point::identity(),
+            ..circuit
+        };
+        assert_eq!(witness_error(&identity_public_key), WitnessError::IdentityPublicKey);
+        assert_eq!(witness_error(&VeEncCircuit::default()), WitnessError::IdentityPublicKey);
+    }
+
+    #[test]
+    fn scalar_to_base_boundary() {
+        use super::circuit_from_witness;
+        use crate::circuits::{scalar_to_base, ConversionError};
+        use crate::elgamal::extended_elgamal::extended_elgamal_encrypt_with_randomness;
+        use ff::{Field, PrimeField};
+
+        // p - 1, the largest base element, is a scalar as p < q
+        let p_minus_one = pallas::Scalar::from_repr((-pallas::Base::one()).to_repr()).unwrap();
+        assert_eq!(scalar_to_base(&pallas::Scalar::zero()), Ok(pallas::Base::zero()));
+        assert_eq!(scalar_to_base(&p_minus_one), Ok(-pallas::Base::one()));
+
+        // p and q - 1 do not fit in the base field, they are not reduced
+        let p = p_minus_one + pallas::Scalar::one();
+        assert_eq!(scalar_to_base(&p), Err(ConversionError::ScalarOutOfRange));
+        assert_eq!(
+            scalar_to_base(&-pallas::Scalar::one()),
+            Err(ConversionError::ScalarOutOfRange)
+        );
+
+        // a circuit cannot be created for an encryption with r_enc = p
+        let keypair = ElGamalKeypair::new();
+        for (r_enc, valid) in [(p_minus_one, true), (p, false)] {
+            let (data_in_transmit, witness) = extended_elgamal_encrypt_with_randomness(
+                &keypair.public_key,
+                pallas::Base::from(42),
+                r_enc,
+                pallas::Base::random(OsRng),
+            )
+            .unwrap();
+            let circuit = circuit_from_witness(data_in_transmit, keypair.public_key, &witness);
+            assert_eq!(circuit.is_ok(), valid);
+        }
+    }
+
+    #[test]
+    fn identity_instance() {
+        let identity = pallas::Point::identity();
+        assert!(point_to_xy(&identity).is_err());
+
+        let generator = pallas::Point::generator();
+        let (x, y) = point_to_xy(&generator).unwrap();
+        assert_eq!(pallas::Affine::from_xy(x, y).unwrap(), generator.to_affine());
+
+        // an instance with an identity point is an error, not a panic
+        let circuit = create_circuit(pallas::Base::from(3), ElGamalKeypair::new());
+        let instance = VeEncInstance::new(circuit.data_in_transmit.clone(), identity);
+        assert!(instance.to_halo2_instance().is_err());
+    }
+
+    #[test]
+    fn mismatched_r_encode() {
+        let keypair = ElGamalKeypair::new();
+        let circuit = create_circuit(pallas::Base::from(42), keypair.clone());
+        let instance = VeEncInstance::new(circuit.data_in_transmit.clone(), keypair.public_key);
+        let public_inputs = instance.to_halo2_instance().unwrap();
+        let public_inputs: Vec<&[vesta::Scalar]> = public_inputs.iter().map(|c| &c[..]).collect();
+        assert_eq!(debug_satisfied(&circuit, &public_inputs), Ok(()));
+
+        // the same p_m and ciphertext encode m - 1 with r_encode + 1, which satisfies (1.1),
+        // but r_encode is not the one of the published ciphertext
+        let mut forged = circuit.clone();
+        forged.data_in_transmit.r_encode += pallas::Base::one();
+        forged.m = circuit.m.map(|m| m - pallas::Base::one());
+        assert!(debug_satisfied(&forged, &public_inputs).is_err());
+
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove_encryption(&params, &pk, forged.clone(), &public_inputs).unwrap();
+        assert!(verify_encryption(&params, &vk, &public_inputs, &proof).is_err());
+
+        #[cfg(feature = "diagnostics")]
+        {
+            use super::{diagnose, Statement};
+            let diagnosis = diagnose(&forged, &instance).unwrap();
+            assert_eq!(diagnosis.violated, vec![Statement::REncode]);
+        }
+    }
+}
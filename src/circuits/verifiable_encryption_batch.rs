@@ -0,0 +1,289 @@
+/// Defined in [Verifiable Encryption using Halo2][Section 3.2. Task 1 - Verifiable Encryption without relation R].
+/// Format a single circuit and instance for the Encode and Elgamal encryption of several message blocks
+/// A round trip test to prove, with one proof, that ciphertexts are encryptions of message blocks
+///
+/// For each block i, prove the statement of Task 1:
+/// (1) Encode(m_i; r_encode_i) = p_m_i
+/// (2) C_i = ElGamal.Enc(pk, p_m_i)
+///
+/// The instance column is the concatenation of the Task 1 instances of the blocks:
+/// block i uses rows [i * INSTANCE_SIZE, (i + 1) * INSTANCE_SIZE).
+///
+/// The 10-bit lookup table (1024 rows) is loaded once for all the blocks, so the rows of the
+/// table do not grow with the number of blocks. A table column can only be assigned once per
+/// synthesis, and the table is laid out again for each proof, as halo2 synthesizes the circuit
+/// for each proof. The Sinsemilla generator table of the commitment circuit is loaded with the
+/// same columns, by `SinsemillaChip::load`, and would be shared the same way by a batch of
+/// commitment circuits.
+
+
+use crate::circuits::verifiable_encryption::{
+    InstanceBuilder, VeConfig, VeEncCircuit, VeEncInstance, INSTANCE_SIZE,
+};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use pasta_curves::{pallas, vesta};
+
+#[derive(Default, Clone)]
+pub struct VeEncBatchCircuit {
+    pub(crate) blocks: Vec<VeEncCircuit>,
+}
+
+impl VeEncBatchCircuit {
+    pub fn new(blocks: Vec<VeEncCircuit>) -> Self {
+        VeEncBatchCircuit { blocks }
+    }
+}
+
+impl Circuit<pallas::Base> for VeEncBatchCircuit {
+    type Config = VeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    // The number of blocks is part of the circuit shape, so it is kept.
+    fn without_witnesses(&self) -> Self {
+        Self {
+            blocks: self
+                .blocks
+                .iter()
+                .map(|block| block.without_witnesses())
+                .collect(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        VeEncCircuit::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        // Load 10-bit lookup table once for all blocks,
+        // synthesize_encryption expects the table to be loaded by its caller.
+        config.ecc_config.lookup_config.load(&mut layouter)?;
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            block.synthesize_encryption(
+                config.clone(),
+                layouter.namespace(|| format!("block {}", i)),
+                i * INSTANCE_SIZE,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Public inputs
+#[derive(Clone, Debug)]
+pub struct VeEncBatchInstance {
+    pub(crate) blocks: Vec<VeEncInstance>,
+}
+
+impl VeEncBatchInstance {
+    pub fn new(blocks: Vec<VeEncInstance>) -> Self {
+        VeEncBatchInstance { blocks }
+    }
+
+    /// Public inputs of the blocks, in order.
+    /// Fails with `Error::InvalidInstances` if a point of a block is the identity.
+    pub fn to_halo2_instance(&self) -> Result<[Vec<vesta::Scalar>; 1], Error> {
+        let mut builder = InstanceBuilder::new();
+        for block in &self.blocks {
+            builder.push_instance(block)?;
+        }
+        Ok(builder.build())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{VeEncBatchCircuit, VeEncBatchInstance};
+    use crate::circuits::verifiable_encryption::{create_circuit, VeEncInstance};
+    use crate::circuits::{
+        find_minimum_k, prove_encryption, verify_encryption, PROOF_HEADER_SIZE,
+    };
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::encode::utf8::{convert_u8_array_to_u64_array, split_message_into_blocks};
+    use crate::circuits::verifiable_encryption::VeConfig;
+    use halo2_proofs::circuit::{Cell, Layouter, Region, Table};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::{self, Circuit, Column, ConstraintSystem, Error, Instance};
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+    use std::cell::Cell as Counter;
+
+    // Layouter counting the tables assigned through it, including in namespaces.
+    struct TableCounter<'a, L> {
+        inner: L,
+        tables: &'a Counter<usize>,
+    }
+
+    impl<'a, L: Layouter<pallas::Base>> Layouter<pallas::Base> for TableCounter<'a, L> {
+        type Root = Self;
+
+        fn assign_region<A, AR, N, NR>(&mut self, name: N, assignment: A) -> Result<AR, Error>
+        where
+            A: FnMut(Region<'_, pallas::Base>) -> Result<AR, Error>,
+            N: Fn() -> NR,
+            NR: Into<String>,
+        {
+            self.inner.assign_region(name, assignment)
+        }
+
+        fn assign_table<A, N, NR>(&mut self, name: N, assignment: A) -> Result<(), Error>
+        where
+            A: FnMut(Table<'_, pallas::Base>) -> Result<(), Error>,
+            N: Fn() -> NR,
+            NR: Into<String>,
+        {
+            self.tables.set(self.tables.get() + 1);
+            self.inner.assign_table(name, assignment)
+        }
+
+        fn constrain_instance(
+            &mut self,
+            cell: Cell,
+            column: Column<Instance>,
+            row: usize,
+        ) -> Result<(), Error> {
+            self.inner.constrain_instance(cell, column, row)
+        }
+
+        fn get_root(&mut self) -> &mut Self::Root {
+            self
+        }
+
+        fn push_namespace<NR, N>(&mut self, name_fn: N)
+        where
+            NR: Into<String>,
+            N: FnOnce() -> NR,
+        {
+            self.inner.get_root().push_namespace(name_fn)
+        }
+
+        fn pop_namespace(&mut self, gadget_name: Option<String>) {
+            self.inner.get_root().pop_namespace(gadget_name)
+        }
+    }
+
+    // Batch circuit recording the number of tables assigned by its synthesis.
+    struct CountingBatch {
+        batch: VeEncBatchCircuit,
+        tables: Counter<usize>,
+    }
+
+    impl Circuit<pallas::Base> for CountingBatch {
+        type Config = VeConfig;
+        type FloorPlanner = <VeEncBatchCircuit as Circuit<pallas::Base>>::FloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            CountingBatch {
+                batch: self.batch.without_witnesses(),
+                tables: Counter::new(0),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            VeEncBatchCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let layouter = TableCounter {
+                inner: layouter,
+                tables: &self.tables,
+            };
+            self.batch.synthesize(config, layouter)
+        }
+    }
+
+    fn batch_instance(circuit: &VeEncBatchCircuit) -> VeEncBatchInstance {
+        VeEncBatchInstance::new(
+            circuit
+                .blocks
+                .iter()
+                .map(|block| {
+                    VeEncInstance::new(block.data_in_transmit.clone(), block.elgamal_public_key)
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn table_loaded_once() {
+        let keypair = ElGamalKeypair::new();
+        for blocks in [1u64, 2, 4] {
+            let batch = VeEncBatchCircuit::new(
+                (0..blocks)
+                    .map(|i| create_circuit(pallas::Base::from(i), keypair.clone()))
+                    .collect(),
+            );
+            let instance = batch_instance(&batch).to_halo2_instance().unwrap();
+            let k = find_minimum_k(&batch.without_witnesses()).unwrap();
+
+            let circuit = CountingBatch {
+                batch,
+                tables: Counter::new(0),
+            };
+            let prover = MockProver::run(k, &circuit, instance.to_vec()).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+            assert_eq!(circuit.tables.get(), 1, "{} blocks", blocks);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        // A message of 3 blocks of 31 bytes (82 bytes and 11 bytes of padding)
+        let test_message =
+            "This is a long message for test! It is split into three blocks and proven at once.";
+        let blocks = split_message_into_blocks(test_message, 31).unwrap();
+        assert_eq!(blocks.len(), 3);
+
+        // Elgamal keygen
+        let keypair = ElGamalKeypair::new();
+
+        // Step 1. create a circuit encrypting all blocks
+        let circuit = VeEncBatchCircuit::new(
+            blocks
+                .iter()
+                .map(|block| {
+                    let limbs = convert_u8_array_to_u64_array(block).unwrap();
+                    let m = pallas::Base::from_raw(limbs);
+                    create_circuit(m, keypair.clone())
+                })
+                .collect(),
+        );
+
+        // Step 2. arrange the public instance.
+        let instance = batch_instance(&circuit).to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        // Setup phase: generate parameters for the circuit.
+        // Three blocks do not fit in the 2^11 rows used for a single block.
+        let empty_circuit = circuit.without_witnesses();
+        let k = find_minimum_k(&empty_circuit).unwrap();
+        assert!(k > 11);
+        let params = Params::new(k);
+
+        // Step 3. generate the verification key vk and proving key pk from a circuit without witnesses.
+        let vk = plonk::keygen_vk(&params, &empty_circuit).unwrap();
+        let pk = plonk::keygen_pk(&params, vk.clone(), &empty_circuit).unwrap();
+
+        // Step 4. Proving phase: create a single proof for all blocks.
+        let proof = prove_encryption(&params, &pk, circuit.clone(), &instance).unwrap();
+
+        // Step 5. Verification phase: verify the proof against the public instance.
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        let circuit_cost = halo2_proofs::dev::CircuitCost::<vesta::Point, _>::measure(k, &circuit);
+        let expected_proof_size = PROOF_HEADER_SIZE + usize::from(circuit_cost.proof_size(1));
+        println!("Proof length: {}B", expected_proof_size);
+        assert_eq!(proof.len(), expected_proof_size);
+    }
+}
@@ -0,0 +1,199 @@
+/// Format a circuit and an instance for two Elgamal encryptions of the same message
+/// A round trip test to prove two ciphertexts hide the same message point, without revealing it,
+/// e.g. a ciphertext and its re-randomization, or the encryptions of a message to two recipients.
+///
+/// Prove, for the same secret p_m and m, and i = 1, 2:
+/// (1) Encode(m; r_encode_i) = p_m
+/// (2) C_i = ElGamal.Enc(pk_i, p_m), with randomness r_enc_i
+///
+/// p_m and m are witnessed once and both encryptions are constrained on the same cells,
+/// hence r_encode_1 = r_encode_2, as for a re-randomized ciphertext.
+/// The instance column is the concatenation of the Task 1 instances of the two ciphertexts,
+/// as for a batch of two blocks: ciphertext i uses rows [i * INSTANCE_SIZE, (i + 1) * INSTANCE_SIZE).
+
+use crate::circuits::verifiable_encryption::{VeConfig, VeEncCircuit, INSTANCE_SIZE};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use pasta_curves::pallas;
+#[cfg(feature = "prover")]
+use {
+    crate::circuits::random_r_enc,
+    crate::circuits::verifiable_encryption::circuit_from_witness,
+    crate::elgamal::elgamal::ElGamalKeypair,
+    crate::elgamal::extended_elgamal::{
+        default_r_encode, extended_elgamal_encrypt_with_randomness, EncryptError,
+    },
+    group::Group,
+};
+
+#[derive(Default, Clone)]
+pub struct VeEqualCircuit {
+    /// The two encryptions. The message witness (p_m, m) is the one of the first encryption.
+    pub(crate) ciphertexts: [VeEncCircuit; 2],
+}
+
+impl Circuit<pallas::Base> for VeEqualCircuit {
+    type Config = VeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            ciphertexts: [
+                self.ciphertexts[0].without_witnesses(),
+                self.ciphertexts[1].without_witnesses(),
+            ],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        VeEncCircuit::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        // Load 10-bit lookup table once for both encryptions.
+        config.ecc_config.lookup_config.load(&mut layouter)?;
+
+        for ciphertext in &self.ciphertexts {
+            ciphertext.check_witnesses()?;
+        }
+
+        // the shared message cells
+        let (p_m, message) = self.ciphertexts[0]
+            .witness_message(config.clone(), layouter.namespace(|| "message"))?;
+
+        for (i, ciphertext) in self.ciphertexts.iter().enumerate() {
+            ciphertext.synthesize_encryption_of(
+                config.clone(),
+                layouter.namespace(|| format!("ciphertext {}", i)),
+                p_m.clone(),
+                message.clone(),
+                i * INSTANCE_SIZE,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Create a circuit for the encryptions of the message to the keypair and to `other_public_key`,
+/// which may be the public key of the keypair.
+/// The second encryption uses the r_encode of the first one, so that both hide the same p_m,
+/// and a fresh r_enc.
+/// Fails with `EncryptError::InvalidPublicKey` if one of the public keys is the identity, or if
+/// the message cannot be encoded.
+#[cfg(feature = "prover")]
+pub fn create_circuit(
+    message: pallas::Base,
+    keypair: ElGamalKeypair,
+    other_public_key: pallas::Point,
+) -> Result<VeEqualCircuit, EncryptError> {
+    // r_enc is witnessed in the base field
+    let r_enc = random_r_enc();
+    let r_encode = default_r_encode(&keypair.public_key, &(pallas::Point::generator() * r_enc));
+    let (data_in_transmit, witness) =
+        extended_elgamal_encrypt_with_randomness(&keypair.public_key, message, r_enc, r_encode)?;
+    let (other_data_in_transmit, other_witness) = extended_elgamal_encrypt_with_randomness(
+        &other_public_key,
+        message,
+        random_r_enc(),
+        data_in_transmit.r_encode,
+    )?;
+    assert_eq!(witness.p_m, other_witness.p_m);
+
+    Ok(VeEqualCircuit {
+        ciphertexts: [
+            circuit_from_witness(data_in_transmit, keypair.public_key, &witness)
+                .expect("r_enc fits in the base field"),
+            circuit_from_witness(other_data_in_transmit, other_public_key, &other_witness)
+                .expect("r_enc fits in the base field"),
+        ],
+    })
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{create_circuit, VeEqualCircuit};
+    use crate::circuits::verifiable_encryption::{self, VeEncInstance};
+    use crate::circuits::verifiable_encryption_batch::VeEncBatchInstance;
+    use crate::circuits::{find_minimum_k, keygen, prove_encryption, verify_encryption};
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::elgamal::extended_elgamal::EncryptError;
+    use group::Group;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::Circuit;
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+
+    // the instances of the two ciphertexts, concatenated
+    fn public_inputs(circuit: &VeEqualCircuit) -> Vec<Vec<vesta::Scalar>> {
+        VeEncBatchInstance::new(
+            circuit
+                .ciphertexts
+                .iter()
+                .map(|ct| VeEncInstance::new(ct.data_in_transmit.clone(), ct.elgamal_public_key))
+                .collect(),
+        )
+        .to_halo2_instance()
+        .unwrap()
+        .to_vec()
+    }
+
+    #[test]
+    fn round_trip() {
+        let keypair = ElGamalKeypair::new();
+        let other = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+
+        // Step 1. create a circuit for two encryptions of m, to two recipients
+        let circuit = create_circuit(m, keypair.clone(), other.public_key).unwrap();
+        assert_ne!(
+            circuit.ciphertexts[0].data_in_transmit.c1(),
+            circuit.ciphertexts[1].data_in_transmit.c1()
+        );
+
+        // Step 2. arrange the public instance.
+        let instance = public_inputs(&circuit);
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        // Setup phase: generate parameters and keys for the circuit.
+        let empty_circuit = circuit.without_witnesses();
+        let params = Params::new(find_minimum_k(&empty_circuit).unwrap());
+        let (vk, pk) = keygen(&params, &empty_circuit).unwrap();
+
+        // Step 3. Proving phase, and verification against the public instance.
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        // two encryptions of m under the same key, as for a re-randomization
+        let circuit = create_circuit(m, keypair.clone(), keypair.public_key).unwrap();
+        let prover = MockProver::run(params.k(), &circuit, public_inputs(&circuit)).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the identity is not a public key
+        assert!(matches!(
+            create_circuit(m, keypair, pallas::Point::identity()),
+            Err(EncryptError::InvalidPublicKey)
+        ));
+    }
+
+    #[test]
+    fn different_messages() {
+        let keypair = ElGamalKeypair::new();
+
+        // genuine encryptions of two different messages
+        let first = verifiable_encryption::create_circuit(pallas::Base::from(1), keypair.clone());
+        let second = verifiable_encryption::create_circuit(pallas::Base::from(2), keypair);
+        let circuit = VeEqualCircuit {
+            ciphertexts: [first, second],
+        };
+
+        let k = find_minimum_k(&circuit.without_witnesses()).unwrap();
+        let prover = MockProver::run(k, &circuit, public_inputs(&circuit)).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,381 @@
+/// Defined in [Verifiable Encryption using Halo2][Section 3.3. Task 2 - Verifiable Encryption with relation R].
+/// Format a circuit and an instance for Encode, Elgamal encryption and a Sinsemilla commitment
+/// A round trip test to prove ciphertext is an encryption of an encoded message block
+/// and the message block is the opening of a public commitment
+///
+/// This binds the ciphertext to a commitment published beforehand.
+/// Prove:
+/// (1) Encode(m; r_encode) = p_m, that is,
+/// (1.1) p_m.x = r_encode + m
+/// (1.2) p_m.x^3 + 5 = p_m.y^2 (redundant check, if p_m is not on the curve, the point operations will fail)
+/// (2) C = ElGamal.Enc(pk, p_m)
+/// (2.1) ct_1 = [r_enc]G, G is the generator of E
+/// (2.2) ct_2 = p_m +[r_enc]pk_elgamal
+/// (3) cm = SinsemillaCommit_rcm(m) (new constraint compared to task1)
+///
+/// - secret input `m`, of at most MESSAGE_BITS bits;
+/// - secret input `p_m`;
+/// - secret input `r_enc`;
+/// - secret input `rcm`, the commitment randomness;
+/// - public group element `ct_1 := [r_enc]G`
+/// - public group element `ct_2 := p_m + [r]elgamal_public_key`
+/// - public random element `r_encode`
+/// - public group element `elgamal_public_key`
+/// - public group element `cm`, in the commitment domain of the circuit (the default one unless set)
+/// - public generator `G`;
+
+use crate::circuits::point_to_xy;
+use crate::circuits::verifiable_encryption::{
+    VeConfig, VeEncCircuit, VeEncInstance, INSTANCE_SIZE,
+};
+use crate::constants::fixed_bases::VerifiableEncryptionFixedBases;
+use crate::constants::sinsemilla::{
+    VerifiableEncryptionCommitDomain, VerifiableEncryptionHashDomain,
+};
+use crate::gadgets::{commit, constrain_point_instance};
+use ff::PrimeFieldBits;
+use halo2_gadgets::ecc::chip::EccChip;
+use halo2_gadgets::sinsemilla::chip::{SinsemillaChip, SinsemillaConfig};
+use halo2_gadgets::sinsemilla::primitives as sinsemilla;
+use halo2_gadgets::sinsemilla::{Message, MessagePiece};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use pasta_curves::{pallas, vesta};
+#[cfg(feature = "prover")]
+use {crate::circuits::verifiable_encryption, crate::elgamal::elgamal::ElGamalKeypair};
+
+const COMMITMENT_X: usize = INSTANCE_SIZE;
+const COMMITMENT_Y: usize = INSTANCE_SIZE + 1;
+
+/// Maximal number of bits of a committed message block.
+/// The message is hashed as a single piece of 25 words of 10 bits,
+/// which is enough for a block of 31 bytes.
+pub const MESSAGE_BITS: usize = 250;
+pub(crate) const MESSAGE_WORDS: usize = MESSAGE_BITS / sinsemilla::K;
+
+#[derive(Clone, Debug)]
+pub struct VeCommitConfig {
+    pub(crate) ve_config: VeConfig,
+    pub(crate) sinsemilla_config: SinsemillaConfig<
+        VerifiableEncryptionHashDomain,
+        VerifiableEncryptionCommitDomain,
+        VerifiableEncryptionFixedBases,
+    >,
+}
+
+#[derive(Default, Clone)]
+pub struct VeCommitCircuit {
+    pub(crate) ve_enc_circuit: VeEncCircuit,
+    pub(crate) rcm: Value<pallas::Scalar>,
+    pub(crate) domain: VerifiableEncryptionCommitDomain,
+}
+
+impl Circuit<pallas::Base> for VeCommitCircuit {
+    type Config = VeCommitConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            ve_enc_circuit: self.ve_enc_circuit.without_witnesses(),
+            rcm: Value::unknown(),
+            domain: self.domain.clone(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        let advices = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let table_idx = meta.lookup_table_column();
+        let table_range_check_tag = meta.lookup_table_column();
+        let lookup = (
+            table_idx,
+            meta.lookup_table_column(),
+            meta.lookup_table_column(),
+            table_range_check_tag,
+        );
+        let lagrange_coeffs = [
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+        ];
+
+        let ve_config =
+            VeConfig::configure(meta, advices, lagrange_coeffs, table_idx, table_range_check_tag);
+
+        // Configuration for the Sinsemilla commitment.
+        // It shares the 10-bit lookup table with the range check of the ECC chip.
+        let sinsemilla_config = SinsemillaChip::configure(
+            meta,
+            advices[..5].try_into().unwrap(),
+            advices[6],
+            lagrange_coeffs[0],
+            lookup,
+            ve_config.ecc_config.lookup_config,
+        );
+
+        VeCommitConfig {
+            ve_config,
+            sinsemilla_config,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        // Load the Sinsemilla generator table, which also contains the 10-bit lookup table.
+        SinsemillaChip::load(config.sinsemilla_config.clone(), &mut layouter)?;
+
+        // (1) and (2) encryption
+        let message = self.ve_enc_circuit.synthesize_encryption(
+            config.ve_config.clone(),
+            layouter.namespace(|| "encryption"),
+            0,
+        )?;
+
+        let ecc_chip = EccChip::construct(config.ve_config.ecc_config.clone());
+        let sinsemilla_chip = SinsemillaChip::construct(config.sinsemilla_config.clone());
+
+        // (3) cm = SinsemillaCommit_rcm(m)
+        // Hashing the piece constrains its running sum to end at zero, that is, m < 2^MESSAGE_BITS.
+        let piece = MessagePiece::from_field_elem(
+            sinsemilla_chip.clone(),
+            layouter.namespace(|| "message piece"),
+            message.value().copied(),
+            MESSAGE_WORDS,
+        )?;
+        layouter.assign_region(
+            || "message = piece",
+            |mut region| region.constrain_equal(message.cell(), piece.inner().cell_value().cell()),
+        )?;
+        let message = Message::from_pieces(sinsemilla_chip.clone(), vec![piece]);
+
+        let cm = commit(
+            layouter.namespace(|| "commit message"),
+            sinsemilla_chip,
+            ecc_chip,
+            &self.domain,
+            message,
+            self.rcm,
+        )?;
+
+        // Constrain cm to equal public input commitment
+        constrain_point_instance(
+            layouter.namespace(|| "constrain cm"),
+            &cm,
+            config.ve_config.instance,
+            COMMITMENT_X,
+            COMMITMENT_Y,
+        )
+    }
+}
+
+/// Public inputs
+#[derive(Clone, Debug)]
+pub struct VeCommitInstance {
+    ve_enc_instance: VeEncInstance,
+    commitment: pallas::Point,
+}
+
+impl VeCommitInstance {
+    pub fn new(ve_enc_instance: VeEncInstance, commitment: pallas::Point) -> Self {
+        VeCommitInstance {
+            ve_enc_instance,
+            commitment,
+        }
+    }
+
+    pub fn to_halo2_instance(&self) -> Result<[[vesta::Scalar; INSTANCE_SIZE + 2]; 1], Error> {
+        let mut instance = [vesta::Scalar::zero(); INSTANCE_SIZE + 2];
+        instance[..INSTANCE_SIZE].copy_from_slice(&self.ve_enc_instance.to_halo2_instance()?[0]);
+
+        let (x, y) = point_to_xy(&self.commitment)?;
+        instance[COMMITMENT_X] = x;
+        instance[COMMITMENT_Y] = y;
+
+        Ok([instance])
+    }
+}
+
+/// Sinsemilla commitment to a message block of at most MESSAGE_BITS bits with randomness rcm.
+/// Returns None if the message is too long.
+pub fn commit_message(message: pallas::Base, rcm: pallas::Scalar) -> Option<pallas::Point> {
+    commit_message_in_domain(message, rcm, &VerifiableEncryptionCommitDomain::default())
+}
+
+/// Same as [`commit_message`], in the given commitment domain.
+pub fn commit_message_in_domain(
+    message: pallas::Base,
+    rcm: pallas::Scalar,
+    domain: &VerifiableEncryptionCommitDomain,
+) -> Option<pallas::Point> {
+    let bits = message.to_le_bits();
+    if bits.iter().by_vals().skip(MESSAGE_BITS).any(|bit| bit) {
+        return None;
+    }
+    let domain = sinsemilla::CommitDomain::new(domain.personalization());
+    domain
+        .commit(bits.iter().by_vals().take(MESSAGE_BITS), &rcm)
+        .into()
+}
+
+/// Create a circuit for the encryption of the message, and the commitment it opens.
+#[cfg(feature = "prover")]
+pub fn create_circuit(
+    message: pallas::Base,
+    keypair: ElGamalKeypair,
+    rcm: pallas::Scalar,
+) -> (VeCommitCircuit, pallas::Point) {
+    create_circuit_in_domain(message, keypair, rcm, VerifiableEncryptionCommitDomain::default())
+}
+
+/// Same as [`create_circuit`], with the commitment in the given domain.
+#[cfg(feature = "prover")]
+pub fn create_circuit_in_domain(
+    message: pallas::Base,
+    keypair: ElGamalKeypair,
+    rcm: pallas::Scalar,
+    domain: VerifiableEncryptionCommitDomain,
+) -> (VeCommitCircuit, pallas::Point) {
+    let commitment = commit_message_in_domain(message, rcm, &domain)
+        .expect("message has at most MESSAGE_BITS bits");
+    let circuit = VeCommitCircuit {
+        ve_enc_circuit: verifiable_encryption::create_circuit(message, keypair),
+        rcm: Value::known(rcm),
+        domain,
+    };
+    (circuit, commitment)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{
+        commit_message, commit_message_in_domain, create_circuit, create_circuit_in_domain,
+        VeCommitInstance,
+    };
+    use crate::circuits::verifiable_encryption::VeEncInstance;
+    use crate::circuits::{find_minimum_k, keygen, prove_encryption, verify_encryption};
+    use crate::constants::sinsemilla::VerifiableEncryptionCommitDomain;
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::encode::utf8::{convert_u8_array_to_u64_array, split_message_into_blocks};
+    use ff::Field;
+    use group::Group;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::Circuit;
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn round_trip() {
+        let blocks = split_message_into_blocks("committed message", 31).unwrap();
+        let m = pallas::Base::from_raw(convert_u8_array_to_u64_array(&blocks[0]).unwrap());
+
+        // Step 1. create a circuit and the commitment to the message
+        let rcm = pallas::Scalar::random(OsRng);
+        let (circuit, commitment) = create_circuit(m, ElGamalKeypair::new(), rcm);
+
+        // Step 2. arrange the public instance.
+        let ve_enc_instance = VeEncInstance::new(
+            circuit.ve_enc_circuit.data_in_transmit.clone(),
+            circuit.ve_enc_circuit.elgamal_public_key,
+        );
+        let instance = VeCommitInstance::new(ve_enc_instance.clone(), commitment);
+        let instance = instance.to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        // Setup phase: generate parameters and keys for the circuit.
+        let empty_circuit = circuit.without_witnesses();
+        let params = Params::new(find_minimum_k(&empty_circuit).unwrap());
+        let (vk, pk) = keygen(&params, &empty_circuit).unwrap();
+
+        // Step 3. Proving phase, and verification against the public instance.
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        // The proof does not verify against another commitment.
+        let other = VeCommitInstance::new(ve_enc_instance, commitment + pallas::Point::generator());
+        let other = other.to_halo2_instance().unwrap();
+        let other: Vec<&[vesta::Scalar]> = other.iter().map(|c| &c[..]).collect();
+        assert!(verify_encryption(&params, &vk, &other, &proof).is_err());
+    }
+
+    #[test]
+    fn domain_separation() {
+        let m = pallas::Base::from(42);
+        let rcm = pallas::Scalar::random(OsRng);
+        let domain = VerifiableEncryptionCommitDomain::new("some-app:MessageCommit");
+
+        // the same message and randomness commit to different points in different domains
+        let default_commitment = commit_message(m, rcm).unwrap();
+        let commitment = commit_message_in_domain(m, rcm, &domain).unwrap();
+        assert_ne!(default_commitment, commitment);
+        assert_eq!(
+            commit_message_in_domain(m, rcm, &VerifiableEncryptionCommitDomain::default()),
+            Some(default_commitment)
+        );
+
+        // the circuit proves the commitment in its own domain only
+        let (circuit, circuit_commitment) =
+            create_circuit_in_domain(m, ElGamalKeypair::new(), rcm, domain);
+        assert_eq!(circuit_commitment, commitment);
+        let ve_enc_instance = VeEncInstance::new(
+            circuit.ve_enc_circuit.data_in_transmit.clone(),
+            circuit.ve_enc_circuit.elgamal_public_key,
+        );
+        let k = find_minimum_k(&circuit.without_witnesses()).unwrap();
+
+        let instance = VeCommitInstance::new(ve_enc_instance.clone(), commitment);
+        let instance = instance.to_halo2_instance().unwrap().to_vec();
+        let prover = MockProver::run(k, &circuit, instance).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let instance = VeCommitInstance::new(ve_enc_instance, default_commitment);
+        let instance = instance.to_halo2_instance().unwrap().to_vec();
+        let prover = MockProver::run(k, &circuit, instance).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn commitment_to_another_message() {
+        let m = pallas::Base::from(42);
+        let rcm = pallas::Scalar::random(OsRng);
+        let (circuit, commitment) = create_circuit(m, ElGamalKeypair::new(), rcm);
+        let ve_enc_instance = VeEncInstance::new(
+            circuit.ve_enc_circuit.data_in_transmit.clone(),
+            circuit.ve_enc_circuit.elgamal_public_key,
+        );
+        let k = find_minimum_k(&circuit.without_witnesses()).unwrap();
+
+        let instance = VeCommitInstance::new(ve_enc_instance.clone(), commitment);
+        let instance = instance.to_halo2_instance().unwrap().to_vec();
+        let prover = MockProver::run(k, &circuit, instance).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // a commitment to another message, with the same randomness, is not opened by the
+        // encrypted message
+        let other = commit_message(m + pallas::Base::one(), rcm).unwrap();
+        let instance = VeCommitInstance::new(ve_enc_instance, other);
+        let instance = instance.to_halo2_instance().unwrap().to_vec();
+        let prover = MockProver::run(k, &circuit, instance).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
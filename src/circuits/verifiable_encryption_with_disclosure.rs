@@ -0,0 +1,303 @@
+/// Format a single circuit and instance for the Encode and Elgamal encryption of several message
+/// blocks, some of which are disclosed
+/// A round trip test to prove, with one proof, that ciphertexts are encryptions of message
+/// blocks, and that the disclosed blocks are the public values, e.g. to reveal some fields of
+/// an encrypted document.
+///
+/// For each block i, prove the statement of Task 1:
+/// (1) Encode(m_i; r_encode_i) = p_m_i
+/// (2) C_i = ElGamal.Enc(pk, p_m_i)
+/// and (new constraint compared to task1):
+/// (3) d_i * (m_i - v_i) = 0, where
+/// (3.1) d_i * (d_i - 1) = 0
+///
+/// - public boolean `d_i`, 1 if the block i is disclosed;
+/// - public field element `v_i`, the disclosed m_i, 0 if the block is hidden
+///
+/// The instance column is the concatenation of the Task 1 instances of the blocks, as in the
+/// batch circuit, followed by the disclosure of the blocks: block i uses rows
+/// [i * INSTANCE_SIZE, (i + 1) * INSTANCE_SIZE) and rows n * INSTANCE_SIZE + 2i (d_i) and
+/// n * INSTANCE_SIZE + 2i + 1 (v_i).
+/// The flags are public inputs, not part of the circuit, so the keys of n blocks are the same
+/// for every choice of disclosed blocks.
+
+use crate::add_sub_mul::chip::{
+    AddSubMulChip, AddSubMulInstructions, MulInstructions, SquareInstructions, SubInstructions,
+};
+use crate::circuits::verifiable_encryption::{
+    InstanceBuilder, VeConfig, VeEncCircuit, VeEncInstance, INSTANCE_SIZE,
+};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use pasta_curves::{pallas, vesta};
+#[cfg(feature = "prover")]
+use {crate::circuits::verifiable_encryption, crate::elgamal::elgamal::ElGamalKeypair};
+
+// Rows of the disclosure of a block, after the Task 1 instances of all blocks
+const DISCLOSED: usize = 0;
+const DISCLOSED_MESSAGE: usize = 1;
+const DISCLOSURE_SIZE: usize = 2;
+
+// Row of the disclosure of the block at index, in a circuit of num_blocks blocks
+fn disclosure_row(num_blocks: usize, index: usize) -> usize {
+    num_blocks * INSTANCE_SIZE + index * DISCLOSURE_SIZE
+}
+
+#[derive(Default, Clone)]
+pub struct VeDisclosureCircuit {
+    pub(crate) blocks: Vec<VeEncCircuit>,
+    pub(crate) disclosed: Vec<bool>,
+}
+
+impl Circuit<pallas::Base> for VeDisclosureCircuit {
+    type Config = VeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    // The number of blocks is part of the circuit shape, and the flags are public, so they
+    // are kept.
+    fn without_witnesses(&self) -> Self {
+        Self {
+            blocks: self
+                .blocks
+                .iter()
+                .map(|block| block.without_witnesses())
+                .collect(),
+            disclosed: self.disclosed.clone(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        VeEncCircuit::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        assert_eq!(self.blocks.len(), self.disclosed.len(), "one flag per block");
+
+        // Load 10-bit lookup table once for all blocks.
+        config.ecc_config.lookup_config.load(&mut layouter)?;
+
+        let add_sub_mul_chip = AddSubMulChip::new(config.add_sub_mul_config.clone());
+        let num_blocks = self.blocks.len();
+        for (i, (block, disclosed)) in self.blocks.iter().zip(&self.disclosed).enumerate() {
+            // (1) and (2) encryption
+            let instance_offset = i * INSTANCE_SIZE;
+            let message = block.synthesize_encryption(
+                config.clone(),
+                layouter.namespace(|| format!("block {}", i)),
+                instance_offset,
+            )?;
+
+            // load d_i and v_i, constrained to equal their public inputs
+            let row = disclosure_row(num_blocks, i);
+            let flag = add_sub_mul_chip.load_private(
+                layouter.namespace(|| format!("load d_{}", i)),
+                Value::known(pallas::Base::from(*disclosed as u64)),
+            )?;
+            add_sub_mul_chip.check_result(
+                layouter.namespace(|| format!("check d_{}", i)),
+                flag.clone(),
+                row + DISCLOSED,
+            )?;
+            let value = add_sub_mul_chip.load_private(
+                layouter.namespace(|| format!("load v_{}", i)),
+                if *disclosed {
+                    message.value().copied()
+                } else {
+                    Value::known(pallas::Base::zero())
+                },
+            )?;
+            add_sub_mul_chip.check_result(
+                layouter.namespace(|| format!("check v_{}", i)),
+                value.clone(),
+                row + DISCLOSED_MESSAGE,
+            )?;
+
+            // (3.1) d_i * (d_i - 1) = d_i^2 - d_i = 0, against the zero row of the block
+            let flag_square =
+                add_sub_mul_chip.square(layouter.namespace(|| "d_i^2"), flag.clone())?;
+            let res = add_sub_mul_chip.sub(
+                layouter.namespace(|| "d_i^2 - d_i"),
+                flag_square,
+                flag.clone(),
+            )?;
+            add_sub_mul_chip.check_result(
+                layouter.namespace(|| "check d_i is boolean"),
+                res,
+                instance_offset,
+            )?;
+
+            // (3) d_i * (m_i - v_i) = 0
+            let difference =
+                add_sub_mul_chip.sub(layouter.namespace(|| "m_i - v_i"), message, value)?;
+            let res = add_sub_mul_chip.mul(
+                layouter.namespace(|| "d_i * (m_i - v_i)"),
+                flag,
+                difference,
+            )?;
+            add_sub_mul_chip.check_result(
+                layouter.namespace(|| "check disclosed message"),
+                res,
+                instance_offset,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Public inputs
+#[derive(Clone, Debug)]
+pub struct VeDisclosureInstance {
+    blocks: Vec<VeEncInstance>,
+    disclosed: Vec<Option<pallas::Base>>,
+}
+
+impl VeDisclosureInstance {
+    /// Instance of the blocks, with the message of each disclosed block, None if it is hidden.
+    ///
+    /// Panics if there is not one disclosure per block.
+    pub fn new(blocks: Vec<VeEncInstance>, disclosed: Vec<Option<pallas::Base>>) -> Self {
+        assert_eq!(blocks.len(), disclosed.len(), "one disclosure per block");
+        VeDisclosureInstance { blocks, disclosed }
+    }
+
+    /// Public inputs of the blocks, in order, followed by their disclosure.
+    /// Fails with `Error::InvalidInstances` if a point of a block is the identity.
+    pub fn to_halo2_instance(&self) -> Result<[Vec<vesta::Scalar>; 1], Error> {
+        let mut builder = InstanceBuilder::new();
+        for block in &self.blocks {
+            builder.push_instance(block)?;
+        }
+        let [mut instance] = builder.build();
+        for disclosed in &self.disclosed {
+            let (flag, value) = match disclosed {
+                Some(value) => (vesta::Scalar::one(), *value),
+                None => (vesta::Scalar::zero(), vesta::Scalar::zero()),
+            };
+            instance.extend([flag, value]);
+        }
+        Ok([instance])
+    }
+}
+
+/// Create a circuit for the encryption of the messages, disclosing the messages whose flag
+/// is set, and its instance.
+///
+/// Panics if there is not one flag per message.
+#[cfg(feature = "prover")]
+pub fn create_circuit(
+    messages: &[pallas::Base],
+    disclosed: &[bool],
+    keypair: ElGamalKeypair,
+) -> (VeDisclosureCircuit, VeDisclosureInstance) {
+    assert_eq!(messages.len(), disclosed.len(), "one flag per message");
+    let blocks: Vec<VeEncCircuit> = messages
+        .iter()
+        .map(|message| verifiable_encryption::create_circuit(*message, keypair.clone()))
+        .collect();
+    let instance = VeDisclosureInstance::new(
+        blocks
+            .iter()
+            .map(|block| {
+                VeEncInstance::new(block.data_in_transmit.clone(), block.elgamal_public_key)
+            })
+            .collect(),
+        messages
+            .iter()
+            .zip(disclosed)
+            .map(|(message, disclosed)| disclosed.then_some(*message))
+            .collect(),
+    );
+    let circuit = VeDisclosureCircuit {
+        blocks,
+        disclosed: disclosed.to_vec(),
+    };
+    (circuit, instance)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{create_circuit, disclosure_row, VeDisclosureInstance, DISCLOSED};
+    use crate::circuits::{find_minimum_k, keygen, prove_encryption, verify_encryption};
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::elgamal::extended_elgamal::extended_elgamal_decrypt;
+    use crate::encode::utf8::{convert_u8_array_to_u64_array, split_message_into_blocks};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::Circuit;
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+
+    fn messages() -> Vec<pallas::Base> {
+        // A message of 2 blocks of 31 bytes: a public header of 31 bytes and a secret body
+        let message = "public header, thirty-one bytessecret body";
+        let blocks = split_message_into_blocks(message, 31).unwrap();
+        assert_eq!(blocks.len(), 2);
+        blocks
+            .iter()
+            .map(|block| pallas::Base::from_raw(convert_u8_array_to_u64_array(block).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let messages = messages();
+        let keypair = ElGamalKeypair::new();
+
+        // reveal block 0, keep block 1 hidden
+        let (circuit, instance) = create_circuit(&messages, &[true, false], keypair.clone());
+        let public_inputs = instance.to_halo2_instance().unwrap();
+        let public_inputs: Vec<&[vesta::Scalar]> = public_inputs.iter().map(|c| &c[..]).collect();
+        let row = disclosure_row(2, 0);
+        let (one, zero) = (pallas::Base::one(), pallas::Base::zero());
+        assert_eq!(public_inputs[0][row..], [one, messages[0], zero, zero]);
+
+        let empty_circuit = circuit.without_witnesses();
+        let params = Params::new(find_minimum_k(&empty_circuit).unwrap());
+        let (vk, pk) = keygen(&params, &empty_circuit).unwrap();
+        let hidden = circuit.blocks[1].data_in_transmit.clone();
+        let proof = prove_encryption(&params, &pk, circuit, &public_inputs).unwrap();
+        assert!(verify_encryption(&params, &vk, &public_inputs, &proof).is_ok());
+
+        // the hidden block is an encryption of the message, decrypted with the private key only
+        assert_eq!(extended_elgamal_decrypt(&keypair.private_key, hidden), Ok(messages[1]));
+
+        // the proof does not verify for another disclosed value
+        let other = VeDisclosureInstance::new(
+            instance.blocks.clone(),
+            vec![Some(messages[0] + pallas::Base::one()), None],
+        );
+        let other = other.to_halo2_instance().unwrap();
+        let other: Vec<&[vesta::Scalar]> = other.iter().map(|c| &c[..]).collect();
+        assert!(verify_encryption(&params, &vk, &other, &proof).is_err());
+    }
+
+    #[test]
+    fn disclosed_values() {
+        let messages = messages();
+        let keypair = ElGamalKeypair::new();
+        let (circuit, instance) = create_circuit(&messages, &[true, false], keypair);
+        let k = find_minimum_k(&circuit.without_witnesses()).unwrap();
+        let run = |disclosed: Vec<Option<pallas::Base>>| {
+            let instance = VeDisclosureInstance::new(instance.blocks.clone(), disclosed);
+            let instance = instance.to_halo2_instance().unwrap().to_vec();
+            MockProver::run(k, &circuit, instance).unwrap().verify().is_ok()
+        };
+
+        assert!(run(vec![Some(messages[0]), None]));
+        // the disclosed value is the message, and the flags are the ones of the circuit
+        assert!(!run(vec![Some(messages[1]), None]));
+        assert!(!run(vec![None, None]));
+        assert!(!run(vec![Some(messages[0]), Some(messages[1])]));
+
+        // a flag which is not boolean is rejected
+        let mut public_inputs = instance.to_halo2_instance().unwrap().to_vec();
+        public_inputs[0][disclosure_row(2, 0) + DISCLOSED] = pallas::Base::from(2);
+        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,328 @@
+/// Format a circuit and an instance for Encode, Elgamal encryption of a block of a message and
+/// the Sinsemilla hash of the whole message
+/// A round trip test to prove ciphertext is an encryption of the i-th block of a message
+/// hashed to a public digest, so that the proofs of the blocks are tied to one message.
+///
+/// Prove, for the message m_0 || ... || m_{n-1} and the block i:
+/// (1) Encode(m_i; r_encode) = p_m
+/// (1.1) p_m.x = r_encode + m_i
+/// (1.2) p_m.x^3 + 5 = p_m.y^2 (redundant check, if p_m is not on the curve, the point operations will fail)
+/// (2) C = ElGamal.Enc(pk, p_m)
+/// (2.1) ct_1 = [r_enc]G, G is the generator of E
+/// (2.2) ct_2 = p_m +[r_enc]pk_elgamal
+/// (3) digest = SinsemillaHash(Q, m_0 || ... || m_{n-1}) (new constraint compared to task1),
+///     each block as MESSAGE_BITS little-endian bits, with the Q of the commitment domain
+///
+/// - secret inputs `m_j` for j != i, of at most MESSAGE_BITS bits;
+/// - public group element `digest`, after the public inputs of task1
+///
+/// The number of blocks n and the index i are part of the circuit, so there is one
+/// verification key per block of a message of n blocks. The hash is collision resistant and
+/// each block is constrained to MESSAGE_BITS bits, so the proofs of the blocks for the same
+/// digest are for the blocks of a single message.
+///
+/// WARNING: the digest is public, unsalted and deterministic: it is not hiding. Anyone can
+/// hash candidate messages and compare them with the digest, so a message of low entropy
+/// (a yes/no vote, an amount, a date, a name from a list, ...) is recovered from the public
+/// instance by brute force, without breaking the encryption. Only use this circuit for
+/// messages which cannot be guessed, and otherwise commit to each block with the hiding
+/// commitment of `verifiable_encryption_with_commitment`, whose blinding factor is secret.
+
+use crate::circuits::point_to_xy;
+use crate::circuits::verifiable_encryption::{VeEncCircuit, VeEncInstance, INSTANCE_SIZE};
+use crate::circuits::verifiable_encryption_with_commitment::{
+    VeCommitCircuit, VeCommitConfig, MESSAGE_BITS, MESSAGE_WORDS,
+};
+use crate::constants::sinsemilla::{
+    VerifiableEncryptionHashDomain, VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION,
+};
+use crate::gadgets::constrain_point_instance;
+use ff::PrimeFieldBits;
+use halo2_gadgets::ecc::chip::EccChip;
+use halo2_gadgets::ecc::Point;
+use halo2_gadgets::sinsemilla::chip::SinsemillaChip;
+use halo2_gadgets::sinsemilla::primitives as sinsemilla;
+use halo2_gadgets::sinsemilla::{HashDomain, Message, MessagePiece};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use pasta_curves::{pallas, vesta};
+#[cfg(feature = "prover")]
+use {crate::circuits::verifiable_encryption, crate::elgamal::elgamal::ElGamalKeypair};
+
+const DIGEST_X: usize = INSTANCE_SIZE;
+const DIGEST_Y: usize = INSTANCE_SIZE + 1;
+
+/// Maximal number of blocks of a hashed message: a Sinsemilla message has at most
+/// sinsemilla::C words, and each block is MESSAGE_WORDS words.
+pub const MAX_BLOCKS: usize = sinsemilla::C / MESSAGE_WORDS;
+
+#[derive(Default, Clone)]
+pub struct VeHashCircuit {
+    pub(crate) ve_enc_circuit: VeEncCircuit,
+    pub(crate) blocks: Vec<Value<pallas::Base>>,
+    pub(crate) index: usize,
+}
+
+impl Circuit<pallas::Base> for VeHashCircuit {
+    type Config = VeCommitConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    // The number of blocks and the index are part of the circuit shape, so they are kept.
+    fn without_witnesses(&self) -> Self {
+        Self {
+            ve_enc_circuit: self.ve_enc_circuit.without_witnesses(),
+            blocks: vec![Value::unknown(); self.blocks.len()],
+            index: self.index,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        VeCommitCircuit::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        assert!(
+            self.index < self.blocks.len() && self.blocks.len() <= MAX_BLOCKS,
+            "the index is a block of a message of at most MAX_BLOCKS blocks"
+        );
+
+        // Load the Sinsemilla generator table, which also contains the 10-bit lookup table.
+        SinsemillaChip::load(config.sinsemilla_config.clone(), &mut layouter)?;
+
+        // (1) and (2) encryption of the block i
+        let message = self.ve_enc_circuit.synthesize_encryption(
+            config.ve_config.clone(),
+            layouter.namespace(|| "encryption"),
+            0,
+        )?;
+
+        let ecc_chip = EccChip::construct(config.ve_config.ecc_config.clone());
+        let sinsemilla_chip = SinsemillaChip::construct(config.sinsemilla_config.clone());
+
+        // (3) digest = SinsemillaHash(Q, m_0 || ... || m_{n-1})
+        // Hashing a piece constrains its running sum to end at zero, that is, m_j < 2^MESSAGE_BITS.
+        let pieces = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(j, block)| {
+                let value = if j == self.index {
+                    message.value().copied()
+                } else {
+                    *block
+                };
+                MessagePiece::from_field_elem(
+                    sinsemilla_chip.clone(),
+                    layouter.namespace(|| format!("block {}", j)),
+                    value,
+                    MESSAGE_WORDS,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        layouter.assign_region(
+            || "message = piece i",
+            |mut region| {
+                region.constrain_equal(
+                    message.cell(),
+                    pieces[self.index].inner().cell_value().cell(),
+                )
+            },
+        )?;
+        let message = Message::from_pieces(sinsemilla_chip.clone(), pieces);
+
+        let domain = HashDomain::new(
+            sinsemilla_chip,
+            ecc_chip,
+            &VerifiableEncryptionHashDomain::default(),
+        );
+        let (digest, _) = domain.hash_to_point(layouter.namespace(|| "hash message"), message)?;
+
+        // Constrain digest to equal public input digest
+        constrain_point_instance(
+            layouter.namespace(|| "digest"),
+            &Point::from(digest),
+            config.ve_config.instance,
+            DIGEST_X,
+            DIGEST_Y,
+        )
+    }
+}
+
+/// Public inputs
+#[derive(Clone, Debug)]
+pub struct VeHashInstance {
+    ve_enc_instance: VeEncInstance,
+    digest: pallas::Point,
+}
+
+impl VeHashInstance {
+    pub fn new(ve_enc_instance: VeEncInstance, digest: pallas::Point) -> Self {
+        VeHashInstance {
+            ve_enc_instance,
+            digest,
+        }
+    }
+
+    pub fn to_halo2_instance(&self) -> Result<[[vesta::Scalar; INSTANCE_SIZE + 2]; 1], Error> {
+        let mut instance = [vesta::Scalar::zero(); INSTANCE_SIZE + 2];
+        instance[..INSTANCE_SIZE].copy_from_slice(&self.ve_enc_instance.to_halo2_instance()?[0]);
+
+        let (x, y) = point_to_xy(&self.digest)?;
+        instance[DIGEST_X] = x;
+        instance[DIGEST_Y] = y;
+
+        Ok([instance])
+    }
+}
+
+// Hash domain with the Q of VerifiableEncryptionHashDomain: the domain of the messages of the
+// commitment domain, which Sinsemilla personalizes with the suffix "-M".
+fn hash_domain() -> sinsemilla::HashDomain {
+    sinsemilla::HashDomain::new(&format!("{}-M", VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION))
+}
+
+/// Sinsemilla hash of the blocks of a message, each block as MESSAGE_BITS little-endian bits,
+/// as proven by [`VeHashCircuit`].
+///
+/// WARNING: the digest is unsalted and deterministic, so it reveals a message of low entropy
+/// to anyone who hashes the candidate messages, see the module documentation.
+///
+/// Returns None if there are no blocks or more than MAX_BLOCKS blocks, or if a block is too long.
+pub fn hash_message_blocks(blocks: &[pallas::Base]) -> Option<pallas::Point> {
+    if blocks.is_empty() || blocks.len() > MAX_BLOCKS {
+        return None;
+    }
+    let mut bits = Vec::with_capacity(blocks.len() * MESSAGE_BITS);
+    for block in blocks {
+        let block = block.to_le_bits();
+        if block.iter().by_vals().skip(MESSAGE_BITS).any(|bit| bit) {
+            return None;
+        }
+        bits.extend(block.iter().by_vals().take(MESSAGE_BITS));
+    }
+    hash_domain().hash_to_point(bits.into_iter()).into()
+}
+
+/// Create a circuit for the encryption of the block at index of a message, and the digest of
+/// the message.
+///
+/// Panics if the index is not a block of the message, or if the message cannot be hashed,
+/// see [`hash_message_blocks`].
+#[cfg(feature = "prover")]
+pub fn create_circuit(
+    blocks: &[pallas::Base],
+    index: usize,
+    keypair: ElGamalKeypair,
+) -> (VeHashCircuit, pallas::Point) {
+    assert!(index < blocks.len(), "the index is a block of the message");
+    let digest = hash_message_blocks(blocks)
+        .expect("message of at most MAX_BLOCKS blocks of at most MESSAGE_BITS bits");
+    let circuit = VeHashCircuit {
+        ve_enc_circuit: verifiable_encryption::create_circuit(blocks[index], keypair),
+        blocks: blocks.iter().map(|block| Value::known(*block)).collect(),
+        index,
+    };
+    (circuit, digest)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{
+        create_circuit, hash_domain, hash_message_blocks, VeHashCircuit, VeHashInstance,
+        MAX_BLOCKS,
+    };
+    use crate::circuits::verifiable_encryption::VeEncInstance;
+    use crate::circuits::{find_minimum_k, keygen, prove_encryption, verify_encryption};
+    use crate::constants::sinsemilla::VerifiableEncryptionHashDomain;
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::encode::utf8::{convert_u8_array_to_u64_array, split_message_into_blocks};
+    use group::Curve;
+    use halo2_gadgets::sinsemilla::HashDomains;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::Circuit;
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+
+    fn instance(circuit: &VeHashCircuit, digest: pallas::Point) -> Vec<Vec<vesta::Scalar>> {
+        let ve_enc_instance = VeEncInstance::new(
+            circuit.ve_enc_circuit.data_in_transmit.clone(),
+            circuit.ve_enc_circuit.elgamal_public_key,
+        );
+        VeHashInstance::new(ve_enc_instance, digest)
+            .to_halo2_instance()
+            .unwrap()
+            .iter()
+            .map(|c| c.to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        // A message of 2 blocks of 31 bytes
+        let blocks = split_message_into_blocks("A message hashed to a single digest, two blocks", 31)
+            .unwrap()
+            .iter()
+            .map(|block| pallas::Base::from_raw(convert_u8_array_to_u64_array(block).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(blocks.len(), 2);
+        let digest = hash_message_blocks(&blocks).unwrap();
+        let keypair = ElGamalKeypair::new();
+
+        // the proof of each block is for the digest of the whole message
+        for index in 0..blocks.len() {
+            let (circuit, circuit_digest) = create_circuit(&blocks, index, keypair.clone());
+            assert_eq!(circuit_digest, digest);
+            let instance = instance(&circuit, digest);
+            let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+            let empty_circuit = circuit.without_witnesses();
+            let params = Params::new(find_minimum_k(&empty_circuit).unwrap());
+            let (vk, pk) = keygen(&params, &empty_circuit).unwrap();
+            let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+            assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+        }
+    }
+
+    #[test]
+    fn digest_of_another_message() {
+        let blocks = [pallas::Base::from(1), pallas::Base::from(2)];
+        let (circuit, digest) = create_circuit(&blocks, 0, ElGamalKeypair::new());
+        let k = find_minimum_k(&circuit.without_witnesses()).unwrap();
+
+        let prover = MockProver::run(k, &circuit, instance(&circuit, digest)).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the digest of another message, with the same encrypted block, or with the blocks
+        // in another order, is not proven
+        for other in [
+            [pallas::Base::from(1), pallas::Base::from(3)],
+            [pallas::Base::from(2), pallas::Base::from(1)],
+        ] {
+            let other = hash_message_blocks(&other).unwrap();
+            assert_ne!(other, digest);
+            let prover = MockProver::run(k, &circuit, instance(&circuit, other)).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn hash_of_blocks() {
+        // the hash domain is the one of the circuit
+        assert_eq!(
+            hash_domain().Q().to_affine(),
+            VerifiableEncryptionHashDomain::default().Q()
+        );
+
+        let block = pallas::Base::from(42);
+        assert!(hash_message_blocks(&[]).is_none());
+        assert!(hash_message_blocks(&[block; MAX_BLOCKS]).is_some());
+        assert!(hash_message_blocks(&[block; MAX_BLOCKS + 1]).is_none());
+        assert!(hash_message_blocks(&[block, -pallas::Base::one()]).is_none());
+    }
+}
@@ -0,0 +1,161 @@
+/// Format a circuit and an instance for Encode, Elgamal encryption and a range proof
+/// A round trip test to prove ciphertext is an encryption of a message in [0, 2^bound_bits)
+/// without revealing the message, e.g. for confidential amounts.
+///
+/// Prove:
+/// (1) Encode(m; r_encode) = p_m, that is,
+/// (1.1) p_m.x = r_encode + m
+/// (1.2) p_m.x^3 + 5 = p_m.y^2 (redundant check, if p_m is not on the curve, the point operations will fail)
+/// (2) C = ElGamal.Enc(pk, p_m)
+/// (2.1) ct_1 = [r_enc]G, G is the generator of E
+/// (2.2) ct_2 = p_m +[r_enc]pk_elgamal
+/// (3) 0 <= m < 2^bound_bits (new constraint compared to task1)
+///
+/// The public inputs are the ones of task1, bound_bits is part of the circuit.
+
+use crate::circuits::verifiable_encryption::{VeConfig, VeEncCircuit};
+use halo2_gadgets::sinsemilla::primitives as sinsemilla;
+use halo2_gadgets::utilities::lookup_range_check::LookupRangeCheckConfig;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use pasta_curves::pallas;
+#[cfg(feature = "prover")]
+use {crate::circuits::verifiable_encryption, crate::elgamal::elgamal::ElGamalKeypair};
+
+/// Constrain `value` to be in [0, 2^num_bits), with the 10-bit lookup table of `lookup_config`.
+/// `value` is decomposed into num_bits / 10 words of 10 bits, and the remaining
+/// num_bits % 10 bits are checked with a short range check.
+pub(crate) fn check_range(
+    lookup_config: &LookupRangeCheckConfig<pallas::Base, { sinsemilla::K }>,
+    mut layouter: impl Layouter<pallas::Base>,
+    value: AssignedCell<pallas::Base, pallas::Base>,
+    num_bits: usize,
+) -> Result<(), Error> {
+    let num_words = num_bits / sinsemilla::K;
+    let remaining_bits = num_bits % sinsemilla::K;
+
+    if remaining_bits == 0 {
+        // the running sum ends at zero
+        lookup_config.copy_check(layouter.namespace(|| "words"), value, num_words, true)?;
+        return Ok(());
+    }
+
+    // z = value >> (10 * num_words) must fit in the remaining bits
+    let z = if num_words == 0 {
+        value
+    } else {
+        let zs = lookup_config.copy_check(layouter.namespace(|| "words"), value, num_words, false)?;
+        zs[num_words].clone()
+    };
+    lookup_config.copy_short_check(layouter.namespace(|| "remaining bits"), z, remaining_bits)
+}
+
+#[derive(Default, Clone)]
+pub struct VeRangeCircuit {
+    pub(crate) ve_enc_circuit: VeEncCircuit,
+    pub(crate) bound_bits: usize,
+}
+
+impl Circuit<pallas::Base> for VeRangeCircuit {
+    type Config = VeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    // The bound is part of the circuit shape, so it is kept.
+    fn without_witnesses(&self) -> Self {
+        Self {
+            ve_enc_circuit: self.ve_enc_circuit.without_witnesses(),
+            bound_bits: self.bound_bits,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        VeEncCircuit::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        // Load 10-bit lookup table.
+        config.ecc_config.lookup_config.load(&mut layouter)?;
+
+        // (1) and (2) encryption
+        let message = self.ve_enc_circuit.synthesize_encryption(
+            config.clone(),
+            layouter.namespace(|| "encryption"),
+            0,
+        )?;
+
+        // (3) 0 <= m < 2^bound_bits
+        check_range(
+            &config.ecc_config.lookup_config,
+            layouter.namespace(|| "range check message"),
+            message,
+            self.bound_bits,
+        )
+    }
+}
+
+/// Create a circuit for the encryption of the message and the range proof of the message.
+/// The public instance is the one of task1.
+#[cfg(feature = "prover")]
+pub fn create_circuit(
+    message: pallas::Base,
+    keypair: ElGamalKeypair,
+    bound_bits: usize,
+) -> VeRangeCircuit {
+    VeRangeCircuit {
+        ve_enc_circuit: verifiable_encryption::create_circuit(message, keypair),
+        bound_bits,
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::create_circuit;
+    use crate::circuits::verifiable_encryption::{VeEncInstance, K};
+    use crate::circuits::{keygen, prove_encryption, verify_encryption};
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::Circuit;
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+
+    const BOUND_BITS: usize = 64;
+
+    #[test]
+    fn in_range() {
+        let circuit = create_circuit(pallas::Base::from(u64::MAX), ElGamalKeypair::new(), BOUND_BITS);
+        let instance = VeEncInstance::new(
+            circuit.ve_enc_circuit.data_in_transmit.clone(),
+            circuit.ve_enc_circuit.elgamal_public_key,
+        )
+        .to_halo2_instance().unwrap();
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        let params = Params::new(K);
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+    }
+
+    #[test]
+    fn out_of_range() {
+        let two_pow_64 = pallas::Base::from(u64::MAX) + pallas::Base::one();
+        for bound_bits in [BOUND_BITS, 60] {
+            let circuit = create_circuit(two_pow_64, ElGamalKeypair::new(), bound_bits);
+            let instance = VeEncInstance::new(
+                circuit.ve_enc_circuit.data_in_transmit.clone(),
+                circuit.ve_enc_circuit.elgamal_public_key,
+            )
+            .to_halo2_instance().unwrap();
+
+            let prover = MockProver::run(K, &circuit, instance.iter().map(|c| c.to_vec()).collect())
+                .unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+}
@@ -24,9 +24,8 @@
 /// - public generator `G`;
 
 
-use ff::{Field, PrimeField};
 use group::prime::PrimeCurveAffine;
-use group::{Curve, Group};
+use group::Curve;
 use halo2_gadgets::ecc::chip::{EccChip};
 use halo2_gadgets::ecc::{NonIdentityPoint, ScalarVar};
 use halo2_gadgets::utilities::UtilitiesInstructions;
@@ -35,20 +34,22 @@ use halo2_proofs::{
     plonk::{Circuit, ConstraintSystem, Error},
 };
 use halo2_proofs::circuit::{AssignedCell, Chip};
-use pasta_curves::arithmetic::CurveAffine;
 use pasta_curves::{Fp, pallas, vesta};
 use pasta_curves::pallas::{Affine, Base};
 use rand;
-use rand::rngs::OsRng;
 use crate::add_sub_mul::chip::{ AddSubMulChip, AddSubMulInstructions};
-use crate::circuits::verifiable_encryption::{VeEncCircuit, VeConfig, VeEncInstance};
-use crate::elgamal::elgamal::ElGamalKeypair;
-use crate::circuits::verifiable_encryption;
+use crate::circuits::verifiable_encryption::{VeEncCircuit, VeConfig, VeEncInstance, INSTANCE_SIZE};
+use crate::circuits::point_to_xy;
 use crate::constants::fixed_bases::VerifiableEncryptionFixedBases;
+#[cfg(feature = "prover")]
+use {
+    crate::circuits::verifiable_encryption, crate::elgamal::elgamal::ElGamalKeypair,
+    ff::PrimeField, group::Group,
+};
 
 const K: u32 = 11;
-const DSA_PK_X: usize = 7;
-const DSA_PK_Y: usize = 8;
+const DSA_PK_X: usize = INSTANCE_SIZE;
+const DSA_PK_Y: usize = INSTANCE_SIZE + 1;
 
 
 #[derive(Default, Clone)]
@@ -110,6 +111,13 @@ impl Circuit<pallas::Base> for VeCircuit {
             Value::known(self.ve_enc_circuit.elgamal_public_key.to_affine()),
         )?;
 
+        // ElGamal base point
+        let elgamal_generator = NonIdentityPoint::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "load elgamal generator"),
+            Value::known(self.ve_enc_circuit.generator.to_affine()),
+        )?;
+
         check_encryption_and_relation(
             config,
             layouter,
@@ -120,6 +128,7 @@ impl Circuit<pallas::Base> for VeCircuit {
             message,
             assigned_r_enc,
             elgamal_public_key,
+            elgamal_generator,
         )
     }
 }
@@ -134,6 +143,7 @@ pub(crate) fn check_encryption_and_relation(
     message: AssignedCell<Fp, Fp>,
     assigned_r_enc:  AssignedCell<Base, Base>,
     elgamal_public_key: NonIdentityPoint<Affine, EccChip<VerifiableEncryptionFixedBases>>,
+    elgamal_generator: NonIdentityPoint<Affine, EccChip<VerifiableEncryptionFixedBases>>,
 ) -> Result<(), Error>
 {
     // check relation
@@ -182,6 +192,8 @@ pub(crate) fn check_encryption_and_relation(
         message,
         assigned_r_enc,
         elgamal_public_key,
+        elgamal_generator,
+        0,
     )
 }
 
@@ -194,21 +206,24 @@ pub struct VeInstance {
 }
 
 impl VeInstance {
-    fn to_halo2_instance(&self) -> [[vesta::Scalar; 9]; 1] {
-        let mut instance = [vesta::Scalar::random(OsRng); 9];
+    fn to_halo2_instance(&self) -> Result<[[vesta::Scalar; INSTANCE_SIZE + 2]; 1], Error> {
+        // Every row must be written below, the instance does not depend on any randomness.
+        let mut instance = [None; INSTANCE_SIZE + 2];
 
         let ve_enc_instance = self.ve_enc_instance.clone();
-        let ve_enc_instance = ve_enc_instance.to_halo2_instance();
-        for i in 0..7 {
-            instance[i] = ve_enc_instance[0][i];
+        let ve_enc_instance = ve_enc_instance.to_halo2_instance()?;
+        for i in 0..INSTANCE_SIZE {
+            instance[i] = Some(ve_enc_instance[0][i]);
         }
 
-        instance[DSA_PK_X] = *self.dsa_public_key.to_affine().coordinates().unwrap().x();
-        instance[DSA_PK_Y] = *self.dsa_public_key.to_affine().coordinates().unwrap().y();
+        let (x, y) = point_to_xy(&self.dsa_public_key)?;
+        instance[DSA_PK_X] = Some(x);
+        instance[DSA_PK_Y] = Some(y);
 
-        [instance]
+        Ok([instance.map(|row| row.expect("every instance row is written"))])
     }
 }
+#[cfg(feature = "prover")]
 fn create_circuit(message: pallas::Base, elgamal_keypair: ElGamalKeypair) -> VeCircuit {
     let ve_enc_circuit = verifiable_encryption::create_circuit(message,elgamal_keypair);
 
@@ -223,7 +238,7 @@ fn create_circuit(message: pallas::Base, elgamal_keypair: ElGamalKeypair) -> VeC
         dsa_public_key: dsa_public_key,
     }
 }
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod tests {
     use super::{create_circuit, VeInstance, K};
     use crate::elgamal::elgamal::ElGamalKeypair;
@@ -255,17 +270,17 @@ mod tests {
         let circuit = vec![create_circuit(dsa_private_key, elgamal_keypair.clone())];
 
         // Step 2. arrange the public instance.
-        let ve_enc_instance = VeEncInstance{
-            data_in_transmit: circuit[0].ve_enc_circuit.data_in_transmit.clone(),
-            elgamal_public_key: circuit[0].ve_enc_circuit.elgamal_public_key.clone(),
-        };
+        let ve_enc_instance = VeEncInstance::new(
+            circuit[0].ve_enc_circuit.data_in_transmit.clone(),
+            circuit[0].ve_enc_circuit.elgamal_public_key.clone(),
+        );
         let instance = vec![VeInstance {
             ve_enc_instance: ve_enc_instance,
             dsa_public_key: circuit[0].dsa_public_key.clone(),
         }];
 
         // Instance transformation
-        let instance: Vec<_> = instance.iter().map(|i| i.to_halo2_instance()).collect();
+        let instance: Vec<_> = instance.iter().map(|i| i.to_halo2_instance().unwrap()).collect();
         let instance: Vec<Vec<_>> = instance
             .iter()
             .map(|i| i.iter().map(|c| &c[..]).collect())
@@ -326,17 +341,17 @@ mod tests {
         let circuit = vec![create_circuit(dsa_private_key, elgamal_keypair.clone())];
 
         // Step 2. arrange the public instance.
-        let ve_enc_instance = VeEncInstance{
-            data_in_transmit: circuit[0].ve_enc_circuit.data_in_transmit.clone(),
-            elgamal_public_key: circuit[0].ve_enc_circuit.elgamal_public_key.clone(),
-        };
+        let ve_enc_instance = VeEncInstance::new(
+            circuit[0].ve_enc_circuit.data_in_transmit.clone(),
+            circuit[0].ve_enc_circuit.elgamal_public_key.clone(),
+        );
         let instance = vec![VeInstance {
             ve_enc_instance: ve_enc_instance.clone(),
             dsa_public_key: circuit[0].dsa_public_key.clone(),
         }];
 
         // Instance transformation
-        let instance: Vec<_> = instance.iter().map(|i| i.to_halo2_instance()).collect();
+        let instance: Vec<_> = instance.iter().map(|i| i.to_halo2_instance().unwrap()).collect();
         let instance: Vec<Vec<_>> = instance
             .iter()
             .map(|i| i.iter().map(|c| &c[..]).collect())
@@ -369,7 +384,7 @@ mod tests {
             ve_enc_instance: ve_enc_instance,
             dsa_public_key: tampered_dsa_public_key,
         }];
-        let tampered_instance: Vec<_> = tampered_instance.iter().map(|i| i.to_halo2_instance()).collect();
+        let tampered_instance: Vec<_> = tampered_instance.iter().map(|i| i.to_halo2_instance().unwrap()).collect();
         let tampered_instance: Vec<Vec<_>> = tampered_instance.iter().map(|i| i.iter().map(|c| &c[..]).collect()).collect();
         let tampered_instance: Vec<_> = tampered_instance.iter().map(|i| &i[..]).collect();
 
@@ -400,17 +415,17 @@ mod tests {
         let circuit = vec![create_circuit(dsa_private_key, elgamal_keypair.clone())];
 
         // Step 2. arrange the public instance.
-        let ve_enc_instance = VeEncInstance{
-            data_in_transmit: circuit[0].ve_enc_circuit.data_in_transmit.clone(),
-            elgamal_public_key: circuit[0].ve_enc_circuit.elgamal_public_key.clone(),
-        };
+        let ve_enc_instance = VeEncInstance::new(
+            circuit[0].ve_enc_circuit.data_in_transmit.clone(),
+            circuit[0].ve_enc_circuit.elgamal_public_key.clone(),
+        );
         let instance = vec![VeInstance {
             ve_enc_instance: ve_enc_instance,
             dsa_public_key: circuit[0].dsa_public_key.clone(),
         }];
 
         // Instance transformation
-        let instance: Vec<_> = instance.iter().map(|i| i.to_halo2_instance()).collect();
+        let instance: Vec<_> = instance.iter().map(|i| i.to_halo2_instance().unwrap()).collect();
         let instance: Vec<Vec<_>> = instance
             .iter()
             .map(|i| i.iter().map(|c| &c[..]).collect())
@@ -0,0 +1,261 @@
+/// Format a circuit and an instance for Encode, Elgamal encryption and a comparison with a public threshold
+/// A round trip test to prove ciphertext is an encryption of a message below a public threshold T
+/// without revealing the message, e.g. for access control.
+///
+/// Prove:
+/// (1) Encode(m; r_encode) = p_m, that is,
+/// (1.1) p_m.x = r_encode + m
+/// (1.2) p_m.x^3 + 5 = p_m.y^2 (redundant check, if p_m is not on the curve, the point operations will fail)
+/// (2) C = ElGamal.Enc(pk, p_m)
+/// (2.1) ct_1 = [r_enc]G, G is the generator of E
+/// (2.2) ct_2 = p_m +[r_enc]pk_elgamal
+/// (3) m < T (new constraint compared to task1), that is,
+/// (3.1) 0 <= m < 2^bound_bits
+/// (3.2) 0 <= T - m - 1 < 2^bound_bits
+///
+/// - public field element `T`, after the public inputs of task1
+///
+/// (3.1) and (3.2) give T = m + (T - m - 1) + 1 < 2^(bound_bits + 1) as integers, which is
+/// below the base modulus for bound_bits <= MAX_THRESHOLD_BITS, so T - m - 1 does not wrap
+/// around and m < T. A proof exists for T in [1, 2^bound_bits]; bound_bits is part of the
+/// circuit.
+
+use crate::add_sub_mul::chip::{
+    AddConstantInstructions, AddSubMulChip, AddSubMulInstructions, SubInstructions,
+};
+use crate::circuits::verifiable_encryption::{
+    VeConfig, VeEncCircuit, VeEncInstance, INSTANCE_SIZE,
+};
+use crate::circuits::verifiable_encryption_with_range::check_range;
+use crate::encode::bytes::MAX_BLOCK_BYTES;
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use pasta_curves::{pallas, vesta};
+#[cfg(feature = "prover")]
+use {crate::circuits::verifiable_encryption, crate::elgamal::elgamal::ElGamalKeypair};
+
+const THRESHOLD: usize = INSTANCE_SIZE;
+
+/// Largest bound of the comparison, in bits: the bound of any message block of MAX_BLOCK_BYTES
+/// bytes. Any bound_bits with 2^(bound_bits + 1) below the base modulus would be sound.
+pub const MAX_THRESHOLD_BITS: usize = 8 * MAX_BLOCK_BYTES;
+
+#[derive(Default, Clone)]
+pub struct VeThresholdCircuit {
+    pub(crate) ve_enc_circuit: VeEncCircuit,
+    pub(crate) threshold: pallas::Base,
+    pub(crate) bound_bits: usize,
+}
+
+impl Circuit<pallas::Base> for VeThresholdCircuit {
+    type Config = VeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    // The threshold is public and the bound is part of the circuit shape, so they are kept.
+    fn without_witnesses(&self) -> Self {
+        Self {
+            ve_enc_circuit: self.ve_enc_circuit.without_witnesses(),
+            threshold: self.threshold,
+            bound_bits: self.bound_bits,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        VeEncCircuit::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        assert!(
+            self.bound_bits <= MAX_THRESHOLD_BITS,
+            "the bound has at most MAX_THRESHOLD_BITS bits"
+        );
+
+        // Load 10-bit lookup table.
+        config.ecc_config.lookup_config.load(&mut layouter)?;
+
+        // (1) and (2) encryption
+        let message = self.ve_enc_circuit.synthesize_encryption(
+            config.clone(),
+            layouter.namespace(|| "encryption"),
+            0,
+        )?;
+
+        // (3.1) 0 <= m < 2^bound_bits
+        check_range(
+            &config.ecc_config.lookup_config,
+            layouter.namespace(|| "range check message"),
+            message.clone(),
+            self.bound_bits,
+        )?;
+
+        // load T, constrained to equal public input T
+        let add_sub_mul_chip = AddSubMulChip::new(config.add_sub_mul_config.clone());
+        let threshold = add_sub_mul_chip.load_private(
+            layouter.namespace(|| "load T"),
+            Value::known(self.threshold),
+        )?;
+        add_sub_mul_chip.check_result(
+            layouter.namespace(|| "check T"),
+            threshold.clone(),
+            THRESHOLD,
+        )?;
+
+        // (3.2) 0 <= T - m - 1 < 2^bound_bits
+        // the 1 is a fixed cell of the add constant gate, instead of a loaded constant
+        let difference =
+            add_sub_mul_chip.sub(layouter.namespace(|| "T - m"), threshold, message)?;
+        let difference = add_sub_mul_chip.add_constant(
+            layouter.namespace(|| "T - m - 1"),
+            difference,
+            -pallas::Base::one(),
+        )?;
+        check_range(
+            &config.ecc_config.lookup_config,
+            layouter.namespace(|| "range check T - m - 1"),
+            difference,
+            self.bound_bits,
+        )
+    }
+}
+
+/// Public inputs
+#[derive(Clone, Debug)]
+pub struct VeThresholdInstance {
+    ve_enc_instance: VeEncInstance,
+    threshold: pallas::Base,
+}
+
+impl VeThresholdInstance {
+    pub fn new(ve_enc_instance: VeEncInstance, threshold: pallas::Base) -> Self {
+        VeThresholdInstance {
+            ve_enc_instance,
+            threshold,
+        }
+    }
+
+    pub fn to_halo2_instance(&self) -> Result<[[vesta::Scalar; INSTANCE_SIZE + 1]; 1], Error> {
+        let mut instance = [vesta::Scalar::zero(); INSTANCE_SIZE + 1];
+        instance[..INSTANCE_SIZE].copy_from_slice(&self.ve_enc_instance.to_halo2_instance()?[0]);
+        instance[THRESHOLD] = self.threshold;
+        Ok([instance])
+    }
+}
+
+/// Create a circuit for the encryption of the message and the proof that it is below the
+/// threshold, for messages and thresholds of at most bound_bits bits.
+///
+/// Panics if bound_bits is larger than MAX_THRESHOLD_BITS.
+#[cfg(feature = "prover")]
+pub fn create_circuit(
+    message: pallas::Base,
+    keypair: ElGamalKeypair,
+    threshold: pallas::Base,
+    bound_bits: usize,
+) -> VeThresholdCircuit {
+    assert!(
+        bound_bits <= MAX_THRESHOLD_BITS,
+        "the bound has at most MAX_THRESHOLD_BITS bits"
+    );
+    VeThresholdCircuit {
+        ve_enc_circuit: verifiable_encryption::create_circuit(message, keypair),
+        threshold,
+        bound_bits,
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{create_circuit, VeThresholdCircuit, VeThresholdInstance, MAX_THRESHOLD_BITS};
+    use crate::circuits::verifiable_encryption::{VeEncInstance, K};
+    use crate::circuits::{find_minimum_k, keygen, prove_encryption, verify_encryption};
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use ff::Field;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::Circuit;
+    use halo2_proofs::poly::commitment::Params;
+    use pasta_curves::{pallas, vesta};
+
+    const BOUND_BITS: usize = 64;
+
+    fn instance(circuit: &VeThresholdCircuit, threshold: pallas::Base) -> Vec<Vec<vesta::Scalar>> {
+        let ve_enc_instance = VeEncInstance::new(
+            circuit.ve_enc_circuit.data_in_transmit.clone(),
+            circuit.ve_enc_circuit.elgamal_public_key,
+        );
+        VeThresholdInstance::new(ve_enc_instance, threshold)
+            .to_halo2_instance()
+            .unwrap()
+            .iter()
+            .map(|c| c.to_vec())
+            .collect()
+    }
+
+    fn is_satisfied(message: u64, threshold: u64, bound_bits: usize) -> bool {
+        let threshold = pallas::Base::from(threshold);
+        let circuit = create_circuit(
+            pallas::Base::from(message),
+            ElGamalKeypair::new(),
+            threshold,
+            bound_bits,
+        );
+        let k = find_minimum_k(&circuit.without_witnesses()).unwrap();
+        let prover = MockProver::run(k, &circuit, instance(&circuit, threshold)).unwrap();
+        prover.verify().is_ok()
+    }
+
+    #[test]
+    fn round_trip() {
+        let threshold = pallas::Base::from(1000);
+        let circuit = create_circuit(
+            pallas::Base::from(999),
+            ElGamalKeypair::new(),
+            threshold,
+            BOUND_BITS,
+        );
+        let instance = instance(&circuit, threshold);
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+
+        let params = Params::new(K.max(find_minimum_k(&circuit.without_witnesses()).unwrap()));
+        let (vk, pk) = keygen(&params, &circuit.without_witnesses()).unwrap();
+        let proof = prove_encryption(&params, &pk, circuit, &instance).unwrap();
+        assert!(verify_encryption(&params, &vk, &instance, &proof).is_ok());
+
+        // the proof is for its threshold only
+        let mut other = instance.iter().map(|c| c.to_vec()).collect::<Vec<_>>();
+        other[0][super::THRESHOLD] = pallas::Base::from(1001);
+        let other: Vec<&[vesta::Scalar]> = other.iter().map(|c| &c[..]).collect();
+        assert!(verify_encryption(&params, &vk, &other, &proof).is_err());
+    }
+
+    #[test]
+    fn straddling_threshold() {
+        for (message, threshold) in [(0, 1), (999, 1000), (41, 42), (u64::MAX - 1, u64::MAX)] {
+            assert!(is_satisfied(message, threshold, BOUND_BITS), "{} < {}", message, threshold);
+        }
+        for (message, threshold) in [(0, 0), (1000, 1000), (1001, 1000), (u64::MAX, 1)] {
+            assert!(!is_satisfied(message, threshold, BOUND_BITS), "{} >= {}", message, threshold);
+        }
+    }
+
+    #[test]
+    fn largest_bound() {
+        // a message of MAX_THRESHOLD_BITS bits, just below a threshold 2^MAX_THRESHOLD_BITS
+        let threshold = pallas::Base::from(2).pow_vartime([MAX_THRESHOLD_BITS as u64]);
+        for (message, satisfied) in [
+            (threshold - pallas::Base::one(), true),
+            (threshold, false),
+        ] {
+            let circuit =
+                create_circuit(message, ElGamalKeypair::new(), threshold, MAX_THRESHOLD_BITS);
+            let k = find_minimum_k(&circuit.without_witnesses()).unwrap();
+            let prover = MockProver::run(k, &circuit, instance(&circuit, threshold)).unwrap();
+            assert_eq!(prover.verify().is_ok(), satisfied);
+        }
+    }
+}
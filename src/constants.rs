@@ -1,6 +1,8 @@
 //! Constants used in the Verifiable Encryption
 
+pub mod curve;
 pub(crate) mod fixed_bases;
+pub mod sinsemilla;
 
 /// $\ell^\mathsf{Verifiable Encryption}_\mathsf{scalar}$
 pub(crate) const L_VERIFIABLE_ENCRYPTION_SCALAR: usize = 255;
@@ -0,0 +1,37 @@
+//! Curve constants of the circuits: the base point of the encryption and the coefficient b
+//! of pallas, y^2 = x^3 + b, of the curve equation (1.2), which the circuits check through the
+//! non-identity point gadget of p_m.
+//! Code reproducing the instance or the relations of a proof should use these, so that the
+//! prover and the verifier agree on them.
+use group::prime::PrimeCurveAffine;
+use pasta_curves::pallas;
+
+/// Coefficient b = 5 of the curve equation y^2 = x^3 + b of pallas
+pub const CURVE_B: pallas::Base = pallas::Base::from_raw([5, 0, 0, 0]);
+
+/// Default base point G of the encryption, ct_1 = [r_enc]G: the generator of pallas.
+/// It is the public input GEN of a circuit created without a custom generator.
+pub fn generator() -> pallas::Affine {
+    pallas::Affine::generator()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generator, CURVE_B};
+    use group::prime::PrimeCurveAffine;
+    use group::{Curve, Group};
+    use pasta_curves::arithmetic::CurveAffine;
+    use pasta_curves::pallas;
+
+    #[test]
+    fn curve_constants() {
+        assert_eq!(generator(), pallas::Affine::generator());
+        assert_eq!(generator(), pallas::Point::generator().to_affine());
+        assert_eq!(CURVE_B, pallas::Affine::b());
+
+        // the generator is on the curve of CURVE_B
+        let coordinates = generator().coordinates().unwrap();
+        let (x, y) = (coordinates.x(), coordinates.y());
+        assert_eq!(x.square() * x + CURVE_B, y.square());
+    }
+}
@@ -1,14 +1,17 @@
 //! Verifiable Encryption fixed bases.
+use super::sinsemilla::VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION;
 use super::{L_VALUE, L_VERIFIABLE_ENCRYPTION_SCALAR};
 use ff::PrimeField;
 use group::{Curve, Group};
 use halo2_gadgets::ecc::chip::find_zs_and_us;
+use halo2_gadgets::sinsemilla::primitives::CommitDomain;
 use halo2_gadgets::ecc::{
     chip::{BaseFieldElem, FixedPoint, FullScalar, ShortScalar},
     FixedPoints,
 };
 use lazy_static::lazy_static;
 use pasta_curves::pallas;
+use std::sync::Arc;
 
 /// Window size for fixed-base scalar multiplication
 pub const FIXED_BASE_WINDOW_SIZE: usize = 3;
@@ -27,7 +30,7 @@ pub const NUM_WINDOWS_SHORT: usize =
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct VerifiableEncryptionFixedBases;
 #[derive(Debug, Eq, PartialEq, Clone)]
-pub struct FullWidth(pallas::Affine, &'static [(u64, [pallas::Base; H])]);
+pub struct FullWidth(pallas::Affine, Arc<Vec<(u64, [pallas::Base; H])>>);
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct BaseField;
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -39,6 +42,24 @@ lazy_static! {
         find_zs_and_us(*BASE, NUM_WINDOWS).unwrap();
     static ref ZS_AND_US_SHORT: Vec<(u64, [pallas::Base; H])> =
         find_zs_and_us(*BASE, NUM_WINDOWS_SHORT).unwrap();
+    static ref COMMIT_R: pallas::Affine =
+        CommitDomain::new(VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION)
+            .R()
+            .to_affine();
+    static ref ZS_AND_US_COMMIT_R: Arc<Vec<(u64, [pallas::Base; H])>> =
+        Arc::new(find_zs_and_us(*COMMIT_R, NUM_WINDOWS).unwrap());
+}
+
+impl FullWidth {
+    /// Fixed base R of the message commitment, multiplied by the commitment randomness
+    pub(crate) fn commit_r() -> Self {
+        FullWidth(*COMMIT_R, ZS_AND_US_COMMIT_R.clone())
+    }
+
+    /// Fixed base R of a commitment in another domain
+    pub(crate) fn from_base(base: pallas::Affine) -> Self {
+        FullWidth(base, Arc::new(find_zs_and_us(base, NUM_WINDOWS).unwrap()))
+    }
 }
 
 
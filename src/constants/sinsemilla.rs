@@ -0,0 +1,91 @@
+//! Verifiable Encryption Sinsemilla domains.
+use super::fixed_bases::{FullWidth, VerifiableEncryptionFixedBases};
+use group::Curve;
+use halo2_gadgets::sinsemilla::primitives::CommitDomain;
+use halo2_gadgets::sinsemilla::{CommitDomains, HashDomains};
+use lazy_static::lazy_static;
+use pasta_curves::pallas;
+
+/// Personalization of the Sinsemilla commitment to a message block
+pub const VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION: &str =
+    "QED-it:VerifiableEncryption-MessageCommit";
+
+lazy_static! {
+    static ref Q: pallas::Affine =
+        CommitDomain::new(VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION)
+            .Q()
+            .to_affine();
+}
+
+/// Hash domain of the message commitment, given by the Q of its personalization.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct VerifiableEncryptionHashDomain {
+    q: pallas::Affine,
+}
+
+/// Commitment domain of the message commitment.
+/// The default domain uses [`VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION`];
+/// an application can use its own personalization to separate its commitments from other protocols.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct VerifiableEncryptionCommitDomain {
+    personalization: &'static str,
+    hash_domain: VerifiableEncryptionHashDomain,
+    r: FullWidth,
+}
+
+impl Default for VerifiableEncryptionHashDomain {
+    fn default() -> Self {
+        VerifiableEncryptionHashDomain { q: *Q }
+    }
+}
+
+impl Default for VerifiableEncryptionCommitDomain {
+    fn default() -> Self {
+        VerifiableEncryptionCommitDomain {
+            personalization: VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION,
+            hash_domain: VerifiableEncryptionHashDomain::default(),
+            r: FullWidth::commit_r(),
+        }
+    }
+}
+
+impl VerifiableEncryptionCommitDomain {
+    /// Commitment domain with a custom personalization.
+    /// This computes the fixed-base tables of R, so the domain should be created once and reused.
+    pub fn new(personalization: &'static str) -> Self {
+        if personalization == VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION {
+            return Self::default();
+        }
+        let domain = CommitDomain::new(personalization);
+        VerifiableEncryptionCommitDomain {
+            personalization,
+            hash_domain: VerifiableEncryptionHashDomain {
+                q: domain.Q().to_affine(),
+            },
+            r: FullWidth::from_base(domain.R().to_affine()),
+        }
+    }
+
+    /// Personalization of the domain
+    pub fn personalization(&self) -> &'static str {
+        self.personalization
+    }
+}
+
+impl HashDomains<pallas::Affine> for VerifiableEncryptionHashDomain {
+    fn Q(&self) -> pallas::Affine {
+        self.q
+    }
+}
+
+impl CommitDomains<pallas::Affine, VerifiableEncryptionFixedBases, VerifiableEncryptionHashDomain>
+    for VerifiableEncryptionCommitDomain
+{
+    fn r(&self) -> FullWidth {
+        self.r.clone()
+    }
+
+    fn hash_domain(&self) -> VerifiableEncryptionHashDomain {
+        self.hash_domain.clone()
+    }
+}
@@ -1,2 +1,4 @@
+pub mod authenticated;
+pub mod curve;
 pub mod elgamal;
-pub(crate) mod extended_elgamal;
+pub mod extended_elgamal;
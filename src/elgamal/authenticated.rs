@@ -0,0 +1,223 @@
+/// Encrypt-then-MAC on top of extended ElGamal, for applications which need the integrity of
+/// the ciphertext: ElGamal is malleable, anyone can re-randomize a ciphertext or add a point
+/// to c2, and the decryption of the result is not detected as tampered.
+///
+/// The tag is a keyed BLAKE2b-256 of `DataInTransmit::to_bytes`, with a key derived from the
+/// shared secret S = [r_enc]public_key = [private_key]c1, which only the encryptor and the
+/// holder of the private key know. A ciphertext modified by anyone else, including a
+/// re-randomized one, whose shared secret is another point, is rejected by the decryption.
+///
+/// This changes the ciphertext format: an authenticated ciphertext is the 96-byte
+/// `DataInTransmit` followed by the 32-byte tag. The proofs of encryption are unchanged, they
+/// are for the `DataInTransmit` part, and the tag is checked by the decryptor only.
+use crate::elgamal::elgamal::{shared_secret_receiver, shared_secret_sender};
+use crate::elgamal::extended_elgamal::{
+    extended_elgamal_decrypt, extended_elgamal_encrypt_with_rng, DataInTransmit,
+    DataInTransmitError, DecryptError, EncryptError, Witness, DATA_IN_TRANSMIT_BYTES,
+};
+use alloc::vec::Vec;
+use blake2b_simd::Params;
+use group::{Group, GroupEncoding};
+use pasta_curves::pallas;
+use rand::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+
+/// Length of the tag of an authenticated ciphertext
+pub const TAG_BYTES: usize = 32;
+
+/// Length of a serialized [`AuthenticatedCiphertext`]: the `DataInTransmit` and the tag.
+pub const AUTHENTICATED_CIPHERTEXT_BYTES: usize = DATA_IN_TRANSMIT_BYTES + TAG_BYTES;
+
+// Personalizations of BLAKE2b, for the derivation of the key and for the tag
+const KEY_PERSONALIZATION: &[u8; 16] = b"QED-it:VE-MACKey";
+const TAG_PERSONALIZATION: &[u8; 16] = b"QED-it:VE-MACTag";
+
+// Ciphertext and its tag
+#[derive(Clone, Debug)]
+pub struct AuthenticatedCiphertext {
+    pub(crate) data_in_transmit: DataInTransmit, // the ciphertext and r_encode
+    pub(crate) tag: [u8; TAG_BYTES],             // MAC of the serialized data_in_transmit
+}
+
+impl AuthenticatedCiphertext {
+    /// The ciphertext, of which a proof of encryption can be verified
+    pub fn data_in_transmit(&self) -> &DataInTransmit {
+        &self.data_in_transmit
+    }
+
+    /// MAC of the ciphertext
+    pub fn tag(&self) -> [u8; TAG_BYTES] {
+        self.tag
+    }
+
+    /// Serialize the ciphertext to bytes: `DataInTransmit::to_bytes` followed by the tag
+    /// (AUTHENTICATED_CIPHERTEXT_BYTES bytes).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.data_in_transmit.to_bytes();
+        bytes.extend_from_slice(&self.tag);
+        bytes
+    }
+
+    /// Parse bytes produced by [`AuthenticatedCiphertext::to_bytes`].
+    /// The tag is not checked, see [`decrypt_authenticated`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DataInTransmitError> {
+        if bytes.len() != AUTHENTICATED_CIPHERTEXT_BYTES {
+            return Err(DataInTransmitError::InvalidLength {
+                expected: AUTHENTICATED_CIPHERTEXT_BYTES,
+                actual: bytes.len(),
+            });
+        }
+        let (data_in_transmit, tag) = bytes.split_at(DATA_IN_TRANSMIT_BYTES);
+        Ok(AuthenticatedCiphertext {
+            data_in_transmit: DataInTransmit::from_bytes(data_in_transmit)?,
+            tag: tag.try_into().unwrap(),
+        })
+    }
+}
+
+// MAC of the data with the key derived from the shared secret
+fn compute_tag(
+    shared_secret: &pallas::Point,
+    data_in_transmit: &DataInTransmit,
+) -> [u8; TAG_BYTES] {
+    let key = Params::new()
+        .hash_length(32)
+        .personal(KEY_PERSONALIZATION)
+        .hash(&shared_secret.to_bytes());
+    let tag = Params::new()
+        .hash_length(TAG_BYTES)
+        .key(key.as_bytes())
+        .personal(TAG_PERSONALIZATION)
+        .hash(&data_in_transmit.to_bytes());
+    tag.as_bytes().try_into().unwrap()
+}
+
+// Encode + ElGamal encryption, with the tag of the ciphertext
+#[cfg(feature = "std")]
+pub fn encrypt_authenticated(
+    public_key: &pallas::Point,
+    message: pallas::Base,
+) -> Result<(AuthenticatedCiphertext, Witness), EncryptError> {
+    encrypt_authenticated_with_rng(public_key, message, &mut OsRng)
+}
+
+// Encode + ElGamal encryption with the randomness drawn from the given RNG, with the tag of
+// the ciphertext
+pub fn encrypt_authenticated_with_rng<R: RngCore + CryptoRng>(
+    public_key: &pallas::Point,
+    message: pallas::Base,
+    rng: &mut R,
+) -> Result<(AuthenticatedCiphertext, Witness), EncryptError> {
+    let (data_in_transmit, witness) = extended_elgamal_encrypt_with_rng(public_key, message, rng)?;
+    let shared_secret = shared_secret_sender(&witness.r_enc, public_key);
+    let tag = compute_tag(&shared_secret, &data_in_transmit);
+    Ok((
+        AuthenticatedCiphertext {
+            data_in_transmit,
+            tag,
+        },
+        witness,
+    ))
+}
+
+// Check the tag of the ciphertext, then decrypt it
+// Fails with DecryptError::InvalidTag if the ciphertext or the tag was modified.
+pub fn decrypt_authenticated(
+    private_key: &pallas::Scalar,
+    ciphertext: &AuthenticatedCiphertext,
+) -> Result<pallas::Base, DecryptError> {
+    let data_in_transmit = &ciphertext.data_in_transmit;
+    if bool::from(data_in_transmit.c1().is_identity()) {
+        return Err(DecryptError::InvalidCiphertext);
+    }
+    let shared_secret = shared_secret_receiver(private_key, &data_in_transmit.c1());
+    let tag = compute_tag(&shared_secret, data_in_transmit);
+    if !bool::from(tag.ct_eq(&ciphertext.tag)) {
+        return Err(DecryptError::InvalidTag);
+    }
+    extended_elgamal_decrypt(private_key, data_in_transmit.clone())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::elgamal::extended_elgamal::rerandomize;
+
+    #[test]
+    fn test_authenticated_round_trip() {
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+
+        let (ciphertext, witness) = encrypt_authenticated(&keypair.public_key, m).unwrap();
+        assert_eq!(witness.m(), m);
+        assert_eq!(decrypt_authenticated(&keypair.private_key, &ciphertext), Ok(m));
+
+        let bytes = ciphertext.to_bytes();
+        assert_eq!(bytes.len(), AUTHENTICATED_CIPHERTEXT_BYTES);
+        let parsed = AuthenticatedCiphertext::from_bytes(&bytes).unwrap();
+        assert_eq!(decrypt_authenticated(&keypair.private_key, &parsed), Ok(m));
+        assert_eq!(
+            AuthenticatedCiphertext::from_bytes(&bytes[1..]).err(),
+            Some(DataInTransmitError::InvalidLength {
+                expected: AUTHENTICATED_CIPHERTEXT_BYTES,
+                actual: AUTHENTICATED_CIPHERTEXT_BYTES - 1,
+            })
+        );
+
+        // the tag is checked with the private key of the ciphertext only
+        let other = ElGamalKeypair::new();
+        assert_eq!(
+            decrypt_authenticated(&other.private_key, &ciphertext),
+            Err(DecryptError::InvalidTag)
+        );
+    }
+
+    #[test]
+    fn test_tampered_ciphertext() {
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let (ciphertext, _) = encrypt_authenticated(&keypair.public_key, m).unwrap();
+        let bytes = ciphertext.to_bytes();
+
+        // flip a bit of each byte of c2 (bytes 32..64): the result is either not a point, or
+        // another ciphertext which is rejected
+        for i in 32..64 {
+            let mut tampered = bytes.clone();
+            tampered[i] ^= 1;
+            if let Ok(tampered) = AuthenticatedCiphertext::from_bytes(&tampered) {
+                assert_eq!(
+                    decrypt_authenticated(&keypair.private_key, &tampered),
+                    Err(DecryptError::InvalidTag)
+                );
+            }
+        }
+
+        // c2 + G decrypts to another point, which the tag rejects
+        let mut tampered = ciphertext.clone();
+        tampered.data_in_transmit.ct.c2 += pallas::Point::generator();
+        assert_eq!(
+            decrypt_authenticated(&keypair.private_key, &tampered),
+            Err(DecryptError::InvalidTag)
+        );
+
+        // a re-randomized ciphertext has another shared secret
+        let mut tampered = ciphertext.clone();
+        tampered.data_in_transmit =
+            rerandomize(&ciphertext.data_in_transmit, &keypair.public_key, &mut OsRng);
+        assert_eq!(
+            decrypt_authenticated(&keypair.private_key, &tampered),
+            Err(DecryptError::InvalidTag)
+        );
+
+        // a modified tag
+        let mut tampered = ciphertext;
+        tampered.tag[0] ^= 1;
+        assert_eq!(
+            decrypt_authenticated(&keypair.private_key, &tampered),
+            Err(DecryptError::InvalidTag)
+        );
+    }
+}
@@ -0,0 +1,227 @@
+/// Extended ElGamal (Encode + ElGamal) over a pasta curve C: y^2 = x^3 + 5, e.g. for protocols
+/// on vesta, where the roles of the fields are swapped: the message and r_encode are in the
+/// base field of vesta, the scalar field of pallas, and the private key and r_enc are in the
+/// scalar field of vesta, the base field of pallas.
+///
+/// `elgamal` and `extended_elgamal` are the pallas instantiation: their encryption and
+/// decryption call the functions of this module with C = pallas, as `encode` and `decode` call
+/// `encode_with_retry_on` and `decode_on`. The circuits only support pallas, as the ECC chip of
+/// halo2_gadgets is specific to pallas, so the vesta instantiation has no proof of encryption.
+use crate::elgamal::extended_elgamal::{DecryptError, EncryptError};
+use crate::encode::encode::{decode_on, encode_with_retry_on};
+use core::fmt;
+use ff::Field;
+use group::Group;
+use pasta_curves::arithmetic::CurveExt;
+use pasta_curves::{pallas, vesta};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConstantTimeEq};
+
+// ElGamal keypair on the curve C, with the public key [private_key]G for the generator G of C
+// PartialEq compares the private keys in constant time, as for ElGamalKeypair.
+#[derive(Clone)]
+pub struct CurveKeypair<C: CurveExt> {
+    pub public_key: C,
+    pub private_key: C::ScalarExt,
+}
+
+impl<C: CurveExt> ConstantTimeEq for CurveKeypair<C> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.public_key.ct_eq(&other.public_key) & self.private_key.ct_eq(&other.private_key)
+    }
+}
+
+impl<C: CurveExt> PartialEq for CurveKeypair<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl<C: CurveExt> Eq for CurveKeypair<C> {}
+
+// Ciphertext and r_encode sent by the encryptor, as DataInTransmit for pallas
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CurveDataInTransmit<C: CurveExt> {
+    pub(crate) c1: C,             // [r_enc]G
+    pub(crate) c2: C,             // p_m + [r_enc]public_key
+    pub(crate) r_encode: C::Base, // randomness for encoding and decoding
+}
+
+/// Keypair on vesta
+pub type VestaKeypair = CurveKeypair<vesta::Point>;
+
+/// Ciphertext on vesta, of a message in the base field of vesta
+pub type VestaDataInTransmit = CurveDataInTransmit<vesta::Point>;
+
+/// Keypair on pallas, the counterpart of `ElGamalKeypair`
+pub type PallasKeypair = CurveKeypair<pallas::Point>;
+
+impl<C: CurveExt> fmt::Debug for CurveKeypair<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CurveKeypair")
+            .field("public_key", &self.public_key)
+            .field("private_key", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<C: CurveExt> CurveKeypair<C> {
+    // Generate a keypair from the given random number generator
+    pub fn from_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self::from_private_key(C::ScalarExt::random(rng))
+    }
+
+    // Build the keypair of a given private key, with the public key [private_key]G
+    pub fn from_private_key(private_key: C::ScalarExt) -> Self {
+        CurveKeypair {
+            public_key: C::generator() * private_key,
+            private_key,
+        }
+    }
+}
+
+impl<C: CurveExt> CurveDataInTransmit<C> {
+    /// c1 = [r_enc]G of the ciphertext
+    pub fn c1(&self) -> C {
+        self.c1
+    }
+
+    /// c2 = p_m + [r_enc]public_key of the ciphertext
+    pub fn c2(&self) -> C {
+        self.c2
+    }
+
+    /// Randomness of Encode, such that p_m.x = m + r_encode
+    pub fn r_encode(&self) -> C::Base {
+        self.r_encode
+    }
+}
+
+// ElGamal encryption of a point on C with a given nonce r_enc and a base point G:
+// (c1, c2) = ([r_enc]G, p_m + [r_enc]public_key)
+pub fn elgamal_encrypt_on<C: CurveExt>(
+    generator: &C,
+    public_key: &C,
+    p_m: C,
+    r_enc: C::ScalarExt,
+) -> (C, C) {
+    (*generator * r_enc, p_m + *public_key * r_enc)
+}
+
+// ElGamal decryption of a point on C: p_m = c2 - [private_key]c1
+pub fn elgamal_decrypt_on<C: CurveExt>(private_key: &C::ScalarExt, c1: C, c2: C) -> C {
+    c2 - c1 * *private_key
+}
+
+// Encode + ElGamal encryption on C with the randomness drawn from the given RNG,
+// in the order of extended_elgamal_encrypt_with_rng: r_encode, then r_enc
+pub fn encrypt_with_rng<C: CurveExt, R: RngCore + CryptoRng>(
+    public_key: &C,
+    message: C::Base,
+    rng: &mut R,
+) -> Result<CurveDataInTransmit<C>, EncryptError> {
+    let r_encode = C::Base::random(&mut *rng);
+    let r_enc = C::ScalarExt::random(rng);
+    encrypt_with_randomness(public_key, message, r_enc, r_encode)
+}
+
+// Encode + ElGamal encryption on C with given randomness r_enc and r_encode.
+// r_encode is incremented until a point is found, as in extended_elgamal_encrypt_with_randomness.
+pub fn encrypt_with_randomness<C: CurveExt>(
+    public_key: &C,
+    message: C::Base,
+    r_enc: C::ScalarExt,
+    r_encode: C::Base,
+) -> Result<CurveDataInTransmit<C>, EncryptError> {
+    encrypt_with_generator(&C::generator(), public_key, message, r_enc, r_encode)
+        .map(|(data_in_transmit, _)| data_in_transmit)
+}
+
+// Encode + ElGamal encryption on C with given randomness, for a custom base point G, as
+// extended_elgamal_encrypt_with_generator. Returns the ciphertext and the encoded point p_m.
+pub(crate) fn encrypt_with_generator<C: CurveExt>(
+    generator: &C,
+    public_key: &C,
+    message: C::Base,
+    r_enc: C::ScalarExt,
+    r_encode: C::Base,
+) -> Result<(CurveDataInTransmit<C>, C), EncryptError> {
+    if bool::from(generator.is_identity()) {
+        return Err(EncryptError::InvalidGenerator);
+    }
+    if bool::from(public_key.is_identity()) || !bool::from(public_key.is_on_curve()) {
+        return Err(EncryptError::InvalidPublicKey);
+    }
+
+    let (p_m, r_encode) = encode_with_retry_on::<C>(message, r_encode)?;
+    let (c1, c2) = elgamal_encrypt_on(generator, public_key, p_m, r_enc);
+    Ok((CurveDataInTransmit { c1, c2, r_encode }, p_m))
+}
+
+// Decryption on C: p_m = c2 - [private_key]c1, and m = p_m.x - r_encode
+pub fn decrypt<C: CurveExt>(
+    private_key: &C::ScalarExt,
+    data_in_transmit: &CurveDataInTransmit<C>,
+) -> Result<C::Base, DecryptError> {
+    if bool::from(data_in_transmit.c1.is_identity()) {
+        return Err(DecryptError::InvalidCiphertext);
+    }
+    let p_m = elgamal_decrypt_on(private_key, data_in_transmit.c1, data_in_transmit.c2);
+    if bool::from(p_m.is_identity()) {
+        return Err(DecryptError::IdentityPoint);
+    }
+    Ok(decode_on(p_m, data_in_transmit.r_encode))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::elgamal::extended_elgamal::extended_elgamal_encrypt_with_rng;
+    use rand::rngs::OsRng;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_vesta_round_trip() {
+        for _ in 0..100 {
+            let keypair = VestaKeypair::from_rng(&mut OsRng);
+            // a message in the base field of vesta, the scalar field of pallas
+            let m = vesta::Base::random(OsRng);
+
+            let data_in_transmit = encrypt_with_rng(&keypair.public_key, m, &mut OsRng).unwrap();
+            assert_eq!(decrypt(&keypair.private_key, &data_in_transmit), Ok(m));
+
+            // another private key does not decrypt the message
+            let other = VestaKeypair::from_rng(&mut OsRng);
+            assert_ne!(decrypt(&other.private_key, &data_in_transmit), Ok(m));
+        }
+
+        let identity = vesta::Point::identity();
+        assert_eq!(
+            encrypt_with_rng(&identity, vesta::Base::one(), &mut OsRng).err(),
+            Some(EncryptError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_pallas_instantiation() {
+        // the pallas instantiation is the pallas stack, for the same randomness
+        let keypair = ElGamalKeypair::from_seed([1u8; 32]);
+        let m = pallas::Base::from(42);
+
+        let mut rng = ChaCha20Rng::from_seed([2u8; 32]);
+        let (expected, _) =
+            extended_elgamal_encrypt_with_rng(&keypair.public_key, m, &mut rng).unwrap();
+        let mut rng = ChaCha20Rng::from_seed([2u8; 32]);
+        let data_in_transmit = encrypt_with_rng(&keypair.public_key, m, &mut rng).unwrap();
+
+        assert_eq!(data_in_transmit.c1(), expected.c1());
+        assert_eq!(data_in_transmit.c2(), expected.c2());
+        assert_eq!(data_in_transmit.r_encode(), expected.r_encode());
+        assert_eq!(
+            PallasKeypair::from_private_key(keypair.private_key).public_key,
+            keypair.public_key
+        );
+    }
+}
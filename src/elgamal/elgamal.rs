@@ -1,61 +1,310 @@
 /// Defined in [Verifiable Encryption using Halo2][Section 2.2. ECElgamal].
-use ff::Field;
-use group::Group;
+use crate::elgamal::curve::{elgamal_decrypt_on, elgamal_encrypt_on};
+use alloc::vec::Vec;
+use core::fmt;
+use ff::{Field, PrimeField};
+use group::{Group, GroupEncoding};
 use pasta_curves::pallas;
+#[cfg(feature = "std")]
 use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use subtle::{Choice, ConstantTimeEq};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 // Define the keypair for the ElGamal cryptosystem
-#[derive(Clone, Debug)]
+// Debug prints the public key and redacts the private key, so that keypairs can be logged.
+// PartialEq compares the private keys in constant time, with ConstantTimeEq.
+#[derive(Clone, Eq)]
 pub struct ElGamalKeypair {
     pub public_key: pallas::Point, // [private_key]G, where G is the generator point
     pub private_key: pallas::Scalar, // A secret scalar
 }
 
+/// Length of a serialized [`ElGamalKeypair`]:
+/// the 32-byte private scalar followed by the 32-byte compressed public key.
+pub const ELGAMAL_KEYPAIR_BYTES: usize = 64;
+
+// Errors returned when parsing an ElGamal keypair
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeypairError {
+    InvalidLength { expected: usize, actual: usize }, // the input is not ELGAMAL_KEYPAIR_BYTES long
+    InvalidPrivateKey, // the private key is not a canonical pallas::Scalar encoding
+    InvalidPublicKey,  // the public key is not a valid compressed point, or is not [private_key]G
+}
+
 // Define the ElGamal ciphertext tuple
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElGamalCiphertext {
-    pub c1: pallas::Point, // [r_enc] G where G is the generator and r_enc is a nonce
-    pub c2: pallas::Point, // p_m + [r_enc] public_key
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::point"))]
+    pub(crate) c1: pallas::Point, // [r_enc] G where G is the generator and r_enc is a nonce
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::point"))]
+    pub(crate) c2: pallas::Point, // p_m + [r_enc] public_key
 }
 
 // Define the ElGamal encryptor's witness values
-#[derive(Clone, Debug, Default)]
+// Debug redacts both values: p_m reveals the message, and r_enc decrypts the ciphertext.
+#[derive(Clone, Default, PartialEq, Eq)]
 pub struct Witness {
-    pub p_m: pallas::Point,    // message point p_m
-    pub r_enc: pallas::Scalar, // randomness for encryption
+    pub(crate) p_m: pallas::Point,    // message point p_m
+    pub(crate) r_enc: pallas::Scalar, // randomness for encryption
+}
+
+impl ElGamalCiphertext {
+    /// Ciphertext (c1, c2), e.g. as received from the encryptor.
+    pub fn new(c1: pallas::Point, c2: pallas::Point) -> Self {
+        ElGamalCiphertext { c1, c2 }
+    }
+
+    /// c1 = [r_enc]G
+    pub fn c1(&self) -> pallas::Point {
+        self.c1
+    }
+
+    /// c2 = p_m + [r_enc]public_key
+    pub fn c2(&self) -> pallas::Point {
+        self.c2
+    }
+}
+
+// Constant-time equality of the ciphertexts: the points are compared in projective
+// coordinates, without branching on their values.
+impl ConstantTimeEq for ElGamalCiphertext {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.c1.ct_eq(&other.c1) & self.c2.ct_eq(&other.c2)
+    }
+}
+
+// Constant-time equality of the keypairs, so that comparing them does not leak the private key.
+impl ConstantTimeEq for ElGamalKeypair {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.public_key.ct_eq(&other.public_key) & self.private_key.ct_eq(&other.private_key)
+    }
+}
+
+impl PartialEq for ElGamalKeypair {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl fmt::Debug for ElGamalKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ElGamalKeypair")
+            .field("public_key", &self.public_key)
+            .field("private_key", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Witness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Witness")
+            .field("p_m", &format_args!("<redacted>"))
+            .field("r_enc", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl Witness {
+    /// Message point p_m
+    pub fn p_m(&self) -> pallas::Point {
+        self.p_m
+    }
+
+    /// Randomness of the encryption
+    pub fn r_enc(&self) -> pallas::Scalar {
+        self.r_enc
+    }
+}
+// Overwrite a value with its default with a volatile write, which is not optimized away.
+// pallas scalars and points do not implement Zeroize.
+#[cfg(feature = "zeroize")]
+pub(crate) fn zeroize_value<T: Copy + Default>(value: &mut T) {
+    unsafe { core::ptr::write_volatile(value, T::default()) };
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
 }
+
+// Only the private key is secret
+#[cfg(feature = "zeroize")]
+impl Zeroize for ElGamalKeypair {
+    fn zeroize(&mut self) {
+        zeroize_value(&mut self.private_key);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for ElGamalKeypair {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for ElGamalKeypair {}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Witness {
+    fn zeroize(&mut self) {
+        zeroize_value(&mut self.p_m);
+        zeroize_value(&mut self.r_enc);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Witness {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Witness {}
+
 impl ElGamalKeypair {
     // Generate a new keypair for use with ElGamal encryption
+    #[cfg(feature = "std")]
     pub fn new() -> Self {
         // Secure random number generator
         let mut rng = OsRng;
+        Self::from_rng(&mut rng)
+    }
 
+    // Generate a keypair from the given random number generator
+    pub fn from_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
         // Generate the secret scalar
-        let private_key = pallas::Scalar::random(&mut rng);
+        let private_key = pallas::Scalar::random(rng);
 
-        // Calculate the public key G^private_key
-        let public_key = pallas::Point::generator() * private_key;
+        Self::from_private_key(private_key)
+    }
+
+    // Build the keypair of a given private key, with the public key [private_key]G
+    pub fn from_private_key(private_key: pallas::Scalar) -> Self {
+        Self {
+            public_key: public_key_from_private(&private_key),
+            private_key,
+        }
+    }
+
+    // Generate a keypair whose public key is [private_key]generator, for a custom base point
+    #[cfg(feature = "std")]
+    pub fn new_with_generator(generator: &pallas::Point) -> Self {
+        Self::from_rng_with_generator(generator, &mut OsRng)
+    }
+
+    // Same as new_with_generator, with the private key drawn from the given RNG
+    pub fn from_rng_with_generator<R: RngCore + CryptoRng>(
+        generator: &pallas::Point,
+        rng: &mut R,
+    ) -> Self {
+        let private_key = pallas::Scalar::random(rng);
+        let public_key = generator * private_key;
 
         Self {
             public_key,
             private_key,
         }
     }
+
+    // Derive a keypair deterministically from a 32-byte seed
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let mut rng = ChaCha20Rng::from_seed(seed);
+        Self::from_rng(&mut rng)
+    }
+
+    /// Serialize the keypair to bytes.
+    ///
+    /// The encoding is `private_key || public_key` (64 bytes), where
+    /// - `private_key` is the 32-byte little-endian canonical encoding of the pallas scalar,
+    /// - `public_key` is the 32-byte compressed pallas point: the little-endian x-coordinate
+    ///   with the sign of y stored in the most significant bit (identity is all zeros).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ELGAMAL_KEYPAIR_BYTES);
+        bytes.extend_from_slice(&self.private_key.to_repr());
+        bytes.extend_from_slice(&self.public_key.to_bytes());
+        bytes
+    }
+
+    /// Parse a keypair serialized by [`ElGamalKeypair::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<ElGamalKeypair, KeypairError> {
+        if bytes.len() != ELGAMAL_KEYPAIR_BYTES {
+            return Err(KeypairError::InvalidLength {
+                expected: ELGAMAL_KEYPAIR_BYTES,
+                actual: bytes.len(),
+            });
+        }
+
+        let mut private_key_bytes = [0u8; 32];
+        private_key_bytes.copy_from_slice(&bytes[..32]);
+        let private_key: Option<pallas::Scalar> =
+            pallas::Scalar::from_repr(private_key_bytes).into();
+        let private_key = private_key.ok_or(KeypairError::InvalidPrivateKey)?;
+
+        // decompression fails if x is not the x-coordinate of a point on the curve
+        let mut public_key_bytes = [0u8; 32];
+        public_key_bytes.copy_from_slice(&bytes[32..]);
+        let public_key: Option<pallas::Point> = pallas::Point::from_bytes(&public_key_bytes).into();
+        let public_key = public_key.ok_or(KeypairError::InvalidPublicKey)?;
+
+        // the public key must match the private key
+        if public_key != public_key_from_private(&private_key) {
+            return Err(KeypairError::InvalidPublicKey);
+        }
+
+        Ok(Self {
+            public_key,
+            private_key,
+        })
+    }
+}
+
+// ElGamal public key [private_key]G of a private key, where G is the generator of pallas,
+// e.g. for a private scalar managed outside of this crate.
+pub fn public_key_from_private(private_key: &pallas::Scalar) -> pallas::Point {
+    pallas::Point::generator() * private_key
 }
 
 // ElGamal encryption
+#[cfg(feature = "std")]
 pub fn elgamal_encrypt(
     public_key: &pallas::Point,
     p_m: pallas::Point,
 ) -> (ElGamalCiphertext, Witness) {
-    let mut rng = OsRng;
+    elgamal_encrypt_with_rng(public_key, p_m, &mut OsRng)
+}
 
+// ElGamal encryption with the nonce r_enc drawn from the given RNG
+pub fn elgamal_encrypt_with_rng<R: RngCore + CryptoRng>(
+    public_key: &pallas::Point,
+    p_m: pallas::Point,
+    rng: &mut R,
+) -> (ElGamalCiphertext, Witness) {
     // Generate a random nonce r_enc
-    let r_enc = pallas::Scalar::random(&mut rng);
-    // c1 = [r_enc]G
-    let c1 = pallas::Point::generator() * r_enc;
-    // c2 = p_m + [r_enc]public_key
-    let c2 = p_m + public_key * r_enc;
+    let r_enc = pallas::Scalar::random(rng);
+    elgamal_encrypt_with_randomness(public_key, p_m, r_enc)
+}
+
+// ElGamal encryption with a given nonce r_enc
+pub fn elgamal_encrypt_with_randomness(
+    public_key: &pallas::Point,
+    p_m: pallas::Point,
+    r_enc: pallas::Scalar,
+) -> (ElGamalCiphertext, Witness) {
+    elgamal_encrypt_with_generator(&pallas::Point::generator(), public_key, p_m, r_enc)
+}
+
+// ElGamal encryption with a given nonce r_enc and a custom base point G
+// The public key must be [private_key]G for the same G.
+pub fn elgamal_encrypt_with_generator(
+    generator: &pallas::Point,
+    public_key: &pallas::Point,
+    p_m: pallas::Point,
+    r_enc: pallas::Scalar,
+) -> (ElGamalCiphertext, Witness) {
+    // c1 = [r_enc]G, c2 = p_m + [r_enc]public_key
+    let (c1, c2) = elgamal_encrypt_on(generator, public_key, p_m, r_enc);
     (
         ElGamalCiphertext { c1, c2 },
         Witness {
@@ -74,12 +323,28 @@ pub fn elgamal_decrypt(
     // Decode p_m to the message
 
     // compute p_m = c2 - [private_key]c1
-    let p_m = ciphertext.c2 - ciphertext.c1 * private_key;
+    let p_m = elgamal_decrypt_on(private_key, ciphertext.c1, ciphertext.c2);
 
     Some(p_m)
 }
 
-#[cfg(test)]
+// Shared secret S = [r_enc]public_key of an encryption, as computed by the encryptor.
+// c2 = p_m + S, and the receiver computes the same point with shared_secret_receiver.
+// S is a group element, not uniform bytes: it must be passed through a KDF, e.g. a BLAKE2b
+// with a personalization of the protocol, to derive symmetric keys, never used raw.
+// Anyone who learns S decrypts the ciphertext, as p_m = c2 - S.
+pub fn shared_secret_sender(r_enc: &pallas::Scalar, public_key: &pallas::Point) -> pallas::Point {
+    public_key * r_enc
+}
+
+// Shared secret S = [private_key]c1 of a ciphertext, as computed by the receiver.
+// It equals shared_secret_sender(r_enc, public_key) for c1 = [r_enc]G and
+// public_key = [private_key]G, and must be passed through a KDF as well.
+pub fn shared_secret_receiver(private_key: &pallas::Scalar, c1: &pallas::Point) -> pallas::Point {
+    c1 * private_key
+}
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
     #[test]
@@ -102,4 +367,163 @@ mod tests {
             assert_eq!(p_m, decrypted_plaintext);
         }
     }
+
+    #[test]
+    fn test_keypair_from_seed() {
+        let keypair_1 = ElGamalKeypair::from_seed([7u8; 32]);
+        let keypair_2 = ElGamalKeypair::from_seed([7u8; 32]);
+        assert_eq!(keypair_1.private_key, keypair_2.private_key);
+        assert_eq!(keypair_1.public_key, keypair_2.public_key);
+        assert_eq!(
+            keypair_1.public_key,
+            pallas::Point::generator() * keypair_1.private_key
+        );
+
+        let keypair_3 = ElGamalKeypair::from_seed([8u8; 32]);
+        assert_ne!(keypair_1.private_key, keypair_3.private_key);
+
+        // constant-time equality of the keypairs
+        assert_eq!(keypair_1, keypair_2);
+        assert_ne!(keypair_1, keypair_3);
+    }
+
+    #[test]
+    fn test_keypair_bytes_round_trip() {
+        let keypair = ElGamalKeypair::new();
+        let bytes = keypair.to_bytes();
+        assert_eq!(bytes.len(), ELGAMAL_KEYPAIR_BYTES);
+
+        let parsed = ElGamalKeypair::from_bytes(&bytes).expect("Parsing failed");
+        assert_eq!(parsed.private_key, keypair.private_key);
+        assert_eq!(parsed.public_key, keypair.public_key);
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_keypair_from_invalid_bytes() {
+        let keypair = ElGamalKeypair::new();
+        let bytes = keypair.to_bytes();
+
+        // wrong length
+        assert_eq!(
+            ElGamalKeypair::from_bytes(&bytes[..63]).unwrap_err(),
+            KeypairError::InvalidLength {
+                expected: ELGAMAL_KEYPAIR_BYTES,
+                actual: 63
+            }
+        );
+
+        // public key not matching the private key
+        let mut tampered = bytes.clone();
+        tampered[32..].copy_from_slice(&ElGamalKeypair::new().public_key.to_bytes());
+        assert_eq!(
+            ElGamalKeypair::from_bytes(&tampered).unwrap_err(),
+            KeypairError::InvalidPublicKey
+        );
+
+        // x = 0 is not on the curve y^2 = x^3 + 5 (5 is not a square), with the sign bit set
+        let mut tampered = bytes;
+        tampered[32..].copy_from_slice(&[0u8; 32]);
+        tampered[63] = 0x80;
+        assert_eq!(
+            ElGamalKeypair::from_bytes(&tampered).unwrap_err(),
+            KeypairError::InvalidPublicKey
+        );
+    }
+
+    #[test]
+    fn test_public_key_from_private() {
+        let keypair = ElGamalKeypair::new();
+        assert_eq!(
+            public_key_from_private(&keypair.private_key),
+            keypair.public_key
+        );
+        assert_eq!(
+            ElGamalKeypair::from_private_key(keypair.private_key),
+            keypair
+        );
+
+        // a keypair of an imported scalar encrypts and decrypts
+        let keypair = ElGamalKeypair::from_private_key(pallas::Scalar::random(OsRng));
+        let p_m = pallas::Point::random(OsRng);
+        let (ciphertext, _) = elgamal_encrypt(&keypair.public_key, p_m);
+        assert_eq!(
+            elgamal_decrypt(&keypair.private_key, &ciphertext),
+            Some(p_m)
+        );
+    }
+
+    #[test]
+    fn test_shared_secret() {
+        let keypair = ElGamalKeypair::new();
+        let p_m = pallas::Point::random(OsRng);
+        let (ciphertext, witness) = elgamal_encrypt(&keypair.public_key, p_m);
+
+        let sender = shared_secret_sender(&witness.r_enc, &keypair.public_key);
+        let receiver = shared_secret_receiver(&keypair.private_key, &ciphertext.c1);
+        assert_eq!(sender, receiver);
+        assert_eq!(ciphertext.c2 - sender, p_m);
+
+        // another private key or another ciphertext gives another point
+        let other = ElGamalKeypair::new();
+        assert_ne!(
+            shared_secret_receiver(&other.private_key, &ciphertext.c1),
+            sender
+        );
+        let (other_ciphertext, _) = elgamal_encrypt(&keypair.public_key, p_m);
+        assert_ne!(
+            shared_secret_receiver(&keypair.private_key, &other_ciphertext.c1),
+            sender
+        );
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let keypair = ElGamalKeypair::new();
+        let debug = format!("{:?}", keypair);
+        assert!(debug.contains(&format!("{:?}", keypair.public_key)));
+        assert!(debug.contains("<redacted>"));
+
+        // neither the printed scalar nor its little-endian encoding appear
+        let private_key = format!("{:?}", keypair.private_key);
+        let private_key_bytes: String = keypair
+            .private_key
+            .to_repr()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        assert!(!debug.contains(private_key.trim_start_matches("0x")));
+        assert!(!debug.contains(&private_key_bytes));
+
+        let (_, witness) = elgamal_encrypt(&keypair.public_key, pallas::Point::random(OsRng));
+        let debug = format!("{:?}", witness);
+        assert!(!debug.contains(format!("{:?}", witness.r_enc).trim_start_matches("0x")));
+
+        // keypairs are compared on both keys
+        assert_eq!(
+            keypair,
+            ElGamalKeypair::from_bytes(&keypair.to_bytes()).unwrap()
+        );
+        assert_ne!(keypair, ElGamalKeypair::new());
+        assert_eq!(witness.clone(), witness);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize() {
+        use zeroize::Zeroizing;
+
+        let mut keypair = Zeroizing::new(ElGamalKeypair::new());
+        let public_key = keypair.public_key;
+        keypair.zeroize();
+        // only the private key is cleared
+        assert_eq!(keypair.private_key, pallas::Scalar::zero());
+        assert_eq!(keypair.public_key, public_key);
+
+        let (_, witness) = elgamal_encrypt(&public_key, pallas::Point::random(OsRng));
+        let mut witness = Zeroizing::new(witness);
+        witness.zeroize();
+        assert_eq!(witness.p_m, pallas::Point::identity());
+        assert_eq!(witness.r_enc, pallas::Scalar::zero());
+    }
 }
@@ -1,63 +1,578 @@
 /// Defined in [Verifiable Encryption using Halo2][Section 2.4. Real Application Process].
-use crate::elgamal::elgamal::{
-    elgamal_decrypt, elgamal_encrypt, ElGamalCiphertext, ElGamalKeypair,
-};
-use crate::encode::encode::{decode, encode};
-use ff::Field;
+use crate::elgamal::curve::{self, CurveDataInTransmit};
+use crate::elgamal::elgamal::{elgamal_encrypt_with_generator, ElGamalCiphertext, ElGamalKeypair};
+use crate::encode::bytes::EncodeError;
+use crate::encode::encode::{derive_r_encode, encode_with_retry, is_derived_r_encode};
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::fmt;
+use ff::{Field, PrimeField};
+use group::{Group, GroupEncoding};
+use pasta_curves::arithmetic::CurveExt;
 use pasta_curves::pallas;
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConstantTimeEq};
+#[cfg(feature = "zeroize")]
+use {
+    crate::elgamal::elgamal::zeroize_value,
+    zeroize::{Zeroize, ZeroizeOnDrop},
+};
+#[cfg(feature = "std")]
+use {
+    halo2_proofs::transcript::{Challenge255, EncodedChallenge, Transcript},
+    pasta_curves::vesta,
+    rand::rngs::OsRng,
+};
 
 // Define the DataInTransmit tuple
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DataInTransmit {
-    pub ct: ElGamalCiphertext,  // ElGamal Ciphertext
-    pub r_encode: pallas::Base, // randomness for encoding and decoding
+    pub(crate) ct: ElGamalCiphertext, // ElGamal Ciphertext
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::base"))]
+    pub(crate) r_encode: pallas::Base, // randomness for encoding and decoding
+}
+
+impl DataInTransmit {
+    /// Data sent by the encryptor: the ciphertext and the r_encode used by Encode.
+    pub fn new(ct: ElGamalCiphertext, r_encode: pallas::Base) -> Self {
+        DataInTransmit { ct, r_encode }
+    }
+
+    /// ElGamal ciphertext of the message point
+    pub fn ct(&self) -> &ElGamalCiphertext {
+        &self.ct
+    }
+
+    /// c1 = [r_enc]G of the ciphertext
+    pub fn c1(&self) -> pallas::Point {
+        self.ct.c1
+    }
+
+    /// c2 = p_m + [r_enc]public_key of the ciphertext
+    pub fn c2(&self) -> pallas::Point {
+        self.ct.c2
+    }
+
+    /// Randomness of Encode, such that p_m.x = m + r_encode
+    pub fn r_encode(&self) -> pallas::Base {
+        self.r_encode
+    }
+
+    /// Serialize the data to bytes.
+    ///
+    /// The encoding is `c1 || c2 || r_encode` (96 bytes), where
+    /// - `c1` and `c2` are 32-byte compressed pallas points,
+    /// - `r_encode` is the 32-byte little-endian canonical encoding of the pallas base element.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(DATA_IN_TRANSMIT_BYTES);
+        bytes.extend_from_slice(&self.ct.c1.to_bytes());
+        bytes.extend_from_slice(&self.ct.c2.to_bytes());
+        bytes.extend_from_slice(&self.r_encode.to_repr());
+        bytes
+    }
+
+    /// Parse data serialized by [`DataInTransmit::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<DataInTransmit, DataInTransmitError> {
+        if bytes.len() != DATA_IN_TRANSMIT_BYTES {
+            return Err(DataInTransmitError::InvalidLength {
+                expected: DATA_IN_TRANSMIT_BYTES,
+                actual: bytes.len(),
+            });
+        }
+
+        // decompression fails if x is not the x-coordinate of a point on the curve
+        let mut point_bytes = [0u8; 32];
+        point_bytes.copy_from_slice(&bytes[..32]);
+        let c1: Option<pallas::Point> = pallas::Point::from_bytes(&point_bytes).into();
+        let c1 = c1.ok_or(DataInTransmitError::InvalidPoint)?;
+        point_bytes.copy_from_slice(&bytes[32..64]);
+        let c2: Option<pallas::Point> = pallas::Point::from_bytes(&point_bytes).into();
+        let c2 = c2.ok_or(DataInTransmitError::InvalidPoint)?;
+
+        let mut r_encode_bytes = [0u8; 32];
+        r_encode_bytes.copy_from_slice(&bytes[64..]);
+        let r_encode: Option<pallas::Base> = pallas::Base::from_repr(r_encode_bytes).into();
+        let r_encode = r_encode.ok_or(DataInTransmitError::InvalidREncode)?;
+
+        Ok(DataInTransmit::new(
+            ElGamalCiphertext::new(c1, c2),
+            r_encode,
+        ))
+    }
+}
+
+// The pallas instantiation of the ciphertext of the generic extended ElGamal
+impl From<CurveDataInTransmit<pallas::Point>> for DataInTransmit {
+    fn from(data_in_transmit: CurveDataInTransmit<pallas::Point>) -> Self {
+        DataInTransmit {
+            ct: ElGamalCiphertext::new(data_in_transmit.c1, data_in_transmit.c2),
+            r_encode: data_in_transmit.r_encode,
+        }
+    }
+}
+
+impl From<DataInTransmit> for CurveDataInTransmit<pallas::Point> {
+    fn from(data_in_transmit: DataInTransmit) -> Self {
+        CurveDataInTransmit {
+            c1: data_in_transmit.ct.c1,
+            c2: data_in_transmit.ct.c2,
+            r_encode: data_in_transmit.r_encode,
+        }
+    }
+}
+
+// Constant-time equality of the ciphertexts and of r_encode, for comparisons of received data
+// which gate a security decision.
+impl ConstantTimeEq for DataInTransmit {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.ct.ct_eq(&other.ct) & self.r_encode.ct_eq(&other.r_encode)
+    }
+}
+
+/// Length of a serialized [`DataInTransmit`]: the two compressed points of the ciphertext
+/// followed by r_encode.
+pub const DATA_IN_TRANSMIT_BYTES: usize = 96;
+
+// Errors returned when parsing a DataInTransmit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DataInTransmitError {
+    InvalidLength { expected: usize, actual: usize }, // input is not DATA_IN_TRANSMIT_BYTES long
+    InvalidPoint,                                     // c1 or c2 is not a valid compressed point
+    InvalidREncode, // r_encode is not a canonical pallas::Base encoding
+}
+
+impl fmt::Display for DataInTransmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataInTransmitError::InvalidLength { expected, actual } => {
+                write!(
+                    f,
+                    "invalid length: expected {} bytes, got {}",
+                    expected, actual
+                )
+            }
+            DataInTransmitError::InvalidPoint => write!(f, "invalid compressed ciphertext point"),
+            DataInTransmitError::InvalidREncode => write!(f, "non-canonical r_encode"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DataInTransmitError {}
+
+// Errors returned by the decryption
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecryptError {
+    InvalidCiphertext, // c1 is the identity, the ciphertext does not hide the message point
+    IdentityPoint,     // the decrypted point is the identity, which has no x-coordinate to decode
+    InvalidTag,        // the tag of an authenticated ciphertext does not match the ciphertext
+}
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecryptError::InvalidCiphertext => write!(f, "invalid ciphertext: c1 is the identity"),
+            DecryptError::IdentityPoint => {
+                write!(f, "decrypted point is the identity and cannot be decoded")
+            }
+            DecryptError::InvalidTag => write!(f, "invalid tag: the ciphertext was modified"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecryptError {}
+
+// Errors returned by the encryption
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncryptError {
+    InvalidPublicKey,    // the public key is the identity, or is not on the curve
+    Encode(EncodeError), // the message cannot be encoded to a point
+    RandomnessReused,    // r_enc was already used for a ciphertext of the RandomnessTracker
+    InvalidMessagePoint, // a message point given by the caller is the identity or not on the curve
+    InvalidGenerator,    // the ElGamal base point G is the identity
+}
+
+impl fmt::Display for EncryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptError::InvalidPublicKey => {
+                write!(
+                    f,
+                    "invalid public key: the identity or a point not on the curve"
+                )
+            }
+            EncryptError::Encode(error) => write!(f, "encoding failed: {}", error),
+            EncryptError::RandomnessReused => {
+                write!(f, "the encryption randomness r_enc was already used")
+            }
+            EncryptError::InvalidMessagePoint => {
+                write!(
+                    f,
+                    "invalid message point: the identity or a point not on the curve"
+                )
+            }
+            EncryptError::InvalidGenerator => write!(f, "invalid base point: the identity"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncryptError {}
+
+impl From<EncodeError> for EncryptError {
+    fn from(error: EncodeError) -> Self {
+        EncryptError::Encode(error)
+    }
 }
 
 // Define the encryptor's witness values
-#[derive(Clone, Debug, Default)]
+// Debug redacts every value, they are all secret.
+#[derive(Clone, Default, PartialEq, Eq)]
 pub struct Witness {
-    pub m: pallas::Base,       // message m
-    pub p_m: pallas::Point,    // message point p_m
-    pub r_enc: pallas::Scalar, // randomness for encryption
+    pub(crate) m: pallas::Base,       // message m
+    pub(crate) p_m: pallas::Point,    // message point p_m
+    pub(crate) r_enc: pallas::Scalar, // randomness for encryption
+}
+
+impl Witness {
+    /// Message m
+    pub fn m(&self) -> pallas::Base {
+        self.m
+    }
+
+    /// Message point p_m = Encode(m; r_encode)
+    pub fn p_m(&self) -> pallas::Point {
+        self.p_m
+    }
+
+    /// Randomness of the encryption
+    pub fn r_enc(&self) -> pallas::Scalar {
+        self.r_enc
+    }
+}
+
+impl fmt::Debug for Witness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Witness")
+            .field("m", &format_args!("<redacted>"))
+            .field("p_m", &format_args!("<redacted>"))
+            .field("r_enc", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Witness {
+    fn zeroize(&mut self) {
+        zeroize_value(&mut self.m);
+        zeroize_value(&mut self.p_m);
+        zeroize_value(&mut self.r_enc);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Witness {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
 }
 
-// Encode + ElGamal encryption
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Witness {}
+
+// Encode + ElGamal encryption, with r_enc drawn from OsRng and r_encode derived from the
+// public key and c1, see default_r_encode.
+#[cfg(feature = "std")]
 pub fn extended_elgamal_encrypt(
     public_key: &pallas::Point,
     message: pallas::Base,
-) -> (DataInTransmit, Witness) {
-    // encode m to point p_m
-    let (p_m, r_encode) = encode(message);
-
-    // encrypting p_m
-    let (ct, witness) = elgamal_encrypt(public_key, p_m);
-    assert_eq!(witness.p_m, p_m);
-    (
-        DataInTransmit { ct, r_encode },
+) -> Result<(DataInTransmit, Witness), EncryptError> {
+    let r_enc = pallas::Scalar::random(OsRng);
+    let r_encode = default_r_encode(public_key, &(pallas::Point::generator() * r_enc));
+    extended_elgamal_encrypt_with_randomness(public_key, message, r_enc, r_encode)
+}
+
+// Encode + ElGamal encryption with the randomness drawn from the given RNG
+pub fn extended_elgamal_encrypt_with_rng<R: RngCore + CryptoRng>(
+    public_key: &pallas::Point,
+    message: pallas::Base,
+    rng: &mut R,
+) -> Result<(DataInTransmit, Witness), EncryptError> {
+    // sample a random r_encode to start the encoding from
+    let r_encode = pallas::Base::random(&mut *rng);
+
+    // Generate a random nonce r_enc
+    let r_enc = pallas::Scalar::random(rng);
+
+    // the encoding fails with probability 2^-MAX_ENCODE_ATTEMPTS
+    extended_elgamal_encrypt_with_randomness(public_key, message, r_enc, r_encode)
+}
+
+// Encode + ElGamal encryption with r_encode derived from a public context, see
+// derive_r_encode, and r_enc drawn from the given RNG.
+// The verifier of the ciphertext recomputes r_encode from the context and checks the one of
+// the ciphertext with is_derived_r_encode. This restricts r_encode to the MAX_ENCODE_ATTEMPTS
+// offsets accepted by is_derived_r_encode, among which the encryptor can still pick one
+// (in addition to grinding over r_enc), so r_encode is constrained but not fixed.
+pub fn extended_elgamal_encrypt_with_context<R: RngCore + CryptoRng>(
+    public_key: &pallas::Point,
+    message: pallas::Base,
+    domain_sep: &[u8],
+    context: &[u8],
+    rng: &mut R,
+) -> Result<(DataInTransmit, Witness), EncryptError> {
+    let r_encode = derive_r_encode(domain_sep, context);
+    let r_enc = pallas::Scalar::random(rng);
+    extended_elgamal_encrypt_with_randomness(public_key, message, r_enc, r_encode)
+}
+
+/// Domain separator of the r_encode derived by default, see `default_r_encode`
+pub const DEFAULT_R_ENCODE_DOMAIN: &[u8] = b"QED-it:VE:default-r_encode";
+
+/// r_encode derived by default by the high-level encryptions (`extended_elgamal_encrypt`,
+/// `VeEncCircuitBuilder` without a context, `encrypt_and_prove`, the facade) with
+/// `derive_r_encode`, from the compressed public key and c1 = [r_enc]G.
+/// c1 does not depend on r_encode, so the verifier recomputes it from the ciphertext, see
+/// `has_default_r_encode`.
+pub fn default_r_encode(public_key: &pallas::Point, c1: &pallas::Point) -> pallas::Base {
+    derive_r_encode(
+        DEFAULT_R_ENCODE_DOMAIN,
+        &default_r_encode_context(public_key, c1),
+    )
+}
+
+/// Check that the r_encode of a ciphertext to the public key is derived from the default
+/// context, see `default_r_encode` and `is_derived_r_encode`. The encryptor can still pick
+/// one of the MAX_ENCODE_ATTEMPTS accepted offsets, and grind over r_enc.
+pub fn has_default_r_encode(data_in_transmit: &DataInTransmit, public_key: &pallas::Point) -> bool {
+    is_derived_r_encode(
+        data_in_transmit.r_encode,
+        DEFAULT_R_ENCODE_DOMAIN,
+        &default_r_encode_context(public_key, &data_in_transmit.ct.c1),
+    )
+}
+
+fn default_r_encode_context(public_key: &pallas::Point, c1: &pallas::Point) -> [u8; 64] {
+    let mut context = [0u8; 64];
+    context[..32].copy_from_slice(&public_key.to_bytes());
+    context[32..].copy_from_slice(&c1.to_bytes());
+    context
+}
+
+// Encode + ElGamal encryption with given randomness r_enc and r_encode
+// If there is no point with x-coordinate m + r_encode on the curve, r_encode is incremented
+// until a point is found, and the r_encode used is returned in DataInTransmit.
+// Fails after MAX_ENCODE_ATTEMPTS attempts, or if the public key is not a valid non-identity point.
+pub fn extended_elgamal_encrypt_with_randomness(
+    public_key: &pallas::Point,
+    message: pallas::Base,
+    r_enc: pallas::Scalar,
+    r_encode: pallas::Base,
+) -> Result<(DataInTransmit, Witness), EncryptError> {
+    extended_elgamal_encrypt_with_generator(
+        &pallas::Point::generator(),
+        public_key,
+        message,
+        r_enc,
+        r_encode,
+    )
+}
+
+// Encode + ElGamal encryption with given randomness, for a custom base point G:
+// ct_1 = [r_enc]G, and the public key must be [private_key]G.
+// Decryption does not depend on G.
+pub fn extended_elgamal_encrypt_with_generator(
+    generator: &pallas::Point,
+    public_key: &pallas::Point,
+    message: pallas::Base,
+    r_enc: pallas::Scalar,
+    r_encode: pallas::Base,
+) -> Result<(DataInTransmit, Witness), EncryptError> {
+    // The circuit witnesses the public key as a NonIdentityPoint: the generic encryption
+    // rejects the keys it cannot prove. A pallas::Point built through the curve API is always
+    // on the curve, but its coordinates are public.
+    let (data_in_transmit, p_m) =
+        curve::encrypt_with_generator(generator, public_key, message, r_enc, r_encode)?;
+    Ok((
+        data_in_transmit.into(),
         Witness {
             m: message,
-            p_m: p_m,
-            r_enc: witness.r_enc,
+            p_m,
+            r_enc,
         },
-    )
+    ))
+}
+
+// Encode + ElGamal encryption of the same message to several recipients
+// The message is encoded once, so every ciphertext hides the same p_m with the same r_encode,
+// and the i-th ciphertext decrypts to the message with the private key of public_keys[i].
+//
+// Each ciphertext uses an independent r_enc. Sharing r_enc between recipients would save
+// the scalar multiplications of c1 = [r_enc]G, and c1 could be sent once, but:
+// - it is only secure if the public keys are honestly generated and independent
+//   (multi-recipient ElGamal); a recipient who registers a key related to another key,
+//   e.g. [a]pk_1, can decrypt the ciphertext of the other recipient,
+// - the ciphertexts are linkable, as they share c1, and p_m leaks if r_enc leaks for any
+//   recipient, e.g. from the witness kept to prove one of the encryptions.
+// With independent randomness, each ciphertext is a regular encryption which can be proven
+// and decrypted on its own.
+// Fails if any public key is not a valid non-identity point, or if the message cannot be encoded.
+#[cfg(feature = "std")]
+pub fn encrypt_to_many(
+    public_keys: &[pallas::Point],
+    message: pallas::Base,
+) -> Result<Vec<DataInTransmit>, EncryptError> {
+    encrypt_to_many_with_rng(public_keys, message, &mut OsRng)
+}
+
+// Same as encrypt_to_many, with the randomness drawn from the given RNG
+pub fn encrypt_to_many_with_rng<R: RngCore + CryptoRng>(
+    public_keys: &[pallas::Point],
+    message: pallas::Base,
+    rng: &mut R,
+) -> Result<Vec<DataInTransmit>, EncryptError> {
+    if public_keys
+        .iter()
+        .any(|pk| bool::from(pk.is_identity()) || !bool::from(pk.is_on_curve()))
+    {
+        return Err(EncryptError::InvalidPublicKey);
+    }
+
+    // encode m to point p_m once for all the recipients
+    let (p_m, r_encode) = encode_with_retry(message, pallas::Base::random(&mut *rng))?;
+
+    Ok(public_keys
+        .iter()
+        .map(|public_key| {
+            let r_enc = pallas::Scalar::random(&mut *rng);
+            let (ct, _) =
+                elgamal_encrypt_with_generator(&pallas::Point::generator(), public_key, p_m, r_enc);
+            DataInTransmit { ct, r_encode }
+        })
+        .collect())
+}
+
+// Record of the randomness used by the encryptions of a session, to detect a reused r_enc.
+// Two ciphertexts with the same r_enc under the same key leak the difference of their
+// message points: c2 - c2' = p_m - p_m'.
+// The tracker only stores the c1 = [r_enc]G of the ciphertexts, which are public, so it does
+// not hold any secret. Encryptions which are not given a tracker are unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct RandomnessTracker {
+    used: BTreeSet<[u8; 32]>,
+}
+
+impl RandomnessTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Number of distinct r_enc recorded
+    pub fn len(&self) -> usize {
+        self.used.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.used.is_empty()
+    }
+}
+
+// Same as extended_elgamal_encrypt_with_randomness, and record r_enc in the tracker.
+// Fails with RandomnessReused if the tracker already recorded r_enc, in which case no
+// ciphertext is returned.
+pub fn extended_elgamal_encrypt_tracked(
+    tracker: &mut RandomnessTracker,
+    public_key: &pallas::Point,
+    message: pallas::Base,
+    r_enc: pallas::Scalar,
+    r_encode: pallas::Base,
+) -> Result<(DataInTransmit, Witness), EncryptError> {
+    let (data_in_transmit, witness) =
+        extended_elgamal_encrypt_with_randomness(public_key, message, r_enc, r_encode)?;
+
+    // c1 = [r_enc]G identifies r_enc, as G is the same for every ciphertext
+    if !tracker.used.insert(data_in_transmit.ct.c1.to_bytes()) {
+        return Err(EncryptError::RandomnessReused);
+    }
+    Ok((data_in_transmit, witness))
+}
+
+// Encode + ElGamal encryption with the randomness squeezed from a transcript, to bind the
+// encryption to the protocol messages absorbed before it.
+// The same transcript state yields the same ciphertext.
+//
+// Mapping of the challenges to the fields: a challenge is a 512-bit Blake2b output reduced
+// modulo the vesta scalar field, which is pallas::Base, so its statistical distance to uniform
+// is below 2^-250.
+// - r_encode is the first challenge,
+// - r_enc is the second challenge, read as a pallas::Scalar from its canonical encoding.
+//   The pallas base modulus p is smaller than the scalar modulus q, so every base element is a
+//   canonical scalar, and r_enc is uniform in [0, p) instead of [0, q): as q - p < 2^87, the
+//   distance to uniform is below 2^-167.
+// A transcript of public messages only gives a public r_enc, from which anyone computes
+// p_m = c2 - [r_enc]public_key: the transcript must have absorbed a secret of the encryptor.
+#[cfg(feature = "std")]
+pub fn encrypt_from_transcript(
+    public_key: &pallas::Point,
+    message: pallas::Base,
+    transcript: &mut impl Transcript<vesta::Affine, Challenge255<vesta::Affine>>,
+) -> Result<(DataInTransmit, Witness), EncryptError> {
+    let r_encode = transcript.squeeze_challenge().get_scalar();
+    let r_enc = pallas::Scalar::from_repr(transcript.squeeze_challenge().get_scalar().to_repr())
+        .expect("p < q, a base element is a canonical scalar");
+
+    extended_elgamal_encrypt_with_randomness(public_key, message, r_enc, r_encode)
 }
 
 // ElGamal decryption + Decode
+// The message is recovered directly as m = p_m.x - r_encode, since Encode sets
+// p_m.x = m + r_encode: there is no discrete log or search, decryption takes constant time
+// in the message.
 pub fn extended_elgamal_decrypt(
     private_key: &pallas::Scalar,
     data_in_transmit: DataInTransmit,
-) -> Option<pallas::Base> {
-    // Decrypt ct to obtain the message point p_m
-    let p_m = elgamal_decrypt(private_key, &data_in_transmit.ct);
+) -> Result<pallas::Base, DecryptError> {
+    curve::decrypt::<pallas::Point>(private_key, &data_in_transmit.into())
+}
 
-    // decode p_m to m
-    let m = decode(p_m.unwrap(), data_in_transmit.r_encode);
-    Some(m)
+// Re-randomize a ciphertext: (c1 + [r']G, c2 + [r']public_key) for a fresh r'
+// The encoded point p_m and r_encode are unchanged, so it decrypts to the same message
+pub fn rerandomize<R: RngCore + CryptoRng>(
+    data_in_transmit: &DataInTransmit,
+    public_key: &pallas::Point,
+    rng: &mut R,
+) -> DataInTransmit {
+    let r = pallas::Scalar::random(rng);
+    DataInTransmit {
+        ct: ElGamalCiphertext {
+            c1: data_in_transmit.ct.c1 + pallas::Point::generator() * r,
+            c2: data_in_transmit.ct.c2 + public_key * r,
+        },
+        r_encode: data_in_transmit.r_encode,
+    }
 }
 
-#[cfg(test)]
+// Combine two ciphertexts: (a.c1 + b.c1, a.c2 + b.c2) is an encryption of p_m1 + p_m2
+// Decryption of the result (elgamal_decrypt on its ct) yields the sum of the encoded points,
+// which is not the encoding of the numeric sum of the messages. There is no r_encode that
+// decodes it, so r_encode is set to zero and extended_elgamal_decrypt is not meaningful on
+// the result.
+pub fn add_ciphertexts(a: &DataInTransmit, b: &DataInTransmit) -> DataInTransmit {
+    DataInTransmit {
+        ct: ElGamalCiphertext {
+            c1: a.ct.c1 + b.ct.c1,
+            c2: a.ct.c2 + b.ct.c2,
+        },
+        r_encode: pallas::Base::zero(),
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
+    use crate::elgamal::elgamal::elgamal_decrypt;
 
     #[test]
     fn test_elgamal_encryption_and_decryption() {
@@ -67,7 +582,8 @@ mod tests {
             let rng = OsRng;
             // encode and encrypt a random message m
             let m = pallas::Base::random(rng);
-            let (data_in_transmit, _) = extended_elgamal_encrypt(&keypair.public_key, m);
+            let (data_in_transmit, _) =
+                extended_elgamal_encrypt(&keypair.public_key, m).expect("Encryption failed");
             let decrypted_plaintext =
                 extended_elgamal_decrypt(&keypair.private_key, data_in_transmit)
                     .expect("Decryption failed");
@@ -76,4 +592,525 @@ mod tests {
             assert_eq!(m, decrypted_plaintext);
         }
     }
+
+    #[test]
+    fn test_accessors() {
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let r_enc = pallas::Scalar::from(7);
+        let (data_in_transmit, witness) = extended_elgamal_encrypt_with_randomness(
+            &keypair.public_key,
+            m,
+            r_enc,
+            pallas::Base::zero(),
+        )
+        .expect("Encryption failed");
+
+        assert_eq!(witness.m(), m);
+        assert_eq!(witness.r_enc(), r_enc);
+        assert_eq!(data_in_transmit.c1(), pallas::Point::generator() * r_enc);
+        assert_eq!(
+            data_in_transmit.c2(),
+            witness.p_m() + keypair.public_key * r_enc
+        );
+        assert_eq!(data_in_transmit.ct().c1(), data_in_transmit.c1());
+        assert_eq!(data_in_transmit.ct().c2(), data_in_transmit.c2());
+
+        // the received data can be rebuilt from its parts
+        let ct = ElGamalCiphertext::new(data_in_transmit.c1(), data_in_transmit.c2());
+        let received = DataInTransmit::new(ct, data_in_transmit.r_encode());
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, received),
+            Ok(m)
+        );
+    }
+
+    #[test]
+    fn test_encrypt_to_many() {
+        let alice = ElGamalKeypair::new();
+        let bob = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+
+        let blocks =
+            encrypt_to_many(&[alice.public_key, bob.public_key], m).expect("Encryption failed");
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            extended_elgamal_decrypt(&alice.private_key, blocks[0].clone()),
+            Ok(m)
+        );
+        assert_eq!(
+            extended_elgamal_decrypt(&bob.private_key, blocks[1].clone()),
+            Ok(m)
+        );
+
+        // the randomness is independent
+        assert_ne!(blocks[0].c1(), blocks[1].c1());
+        assert_ne!(
+            extended_elgamal_decrypt(&bob.private_key, blocks[0].clone()),
+            Ok(m)
+        );
+
+        assert_eq!(
+            encrypt_to_many(&[alice.public_key, pallas::Point::identity()], m).unwrap_err(),
+            EncryptError::InvalidPublicKey
+        );
+        assert!(encrypt_to_many(&[], m).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_randomness_tracker() {
+        let keypair = ElGamalKeypair::new();
+        let mut tracker = RandomnessTracker::new();
+        let r_enc = pallas::Scalar::random(OsRng);
+
+        let (data_in_transmit, _) = extended_elgamal_encrypt_tracked(
+            &mut tracker,
+            &keypair.public_key,
+            pallas::Base::from(1),
+            r_enc,
+            pallas::Base::random(OsRng),
+        )
+        .expect("Encryption failed");
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, data_in_transmit),
+            Ok(pallas::Base::from(1))
+        );
+
+        // the same r_enc is rejected, even for another message or another key
+        for public_key in [keypair.public_key, ElGamalKeypair::new().public_key] {
+            let result = extended_elgamal_encrypt_tracked(
+                &mut tracker,
+                &public_key,
+                pallas::Base::from(2),
+                r_enc,
+                pallas::Base::random(OsRng),
+            );
+            assert_eq!(result.unwrap_err(), EncryptError::RandomnessReused);
+        }
+
+        // a fresh r_enc is accepted
+        extended_elgamal_encrypt_tracked(
+            &mut tracker,
+            &keypair.public_key,
+            pallas::Base::from(2),
+            pallas::Scalar::random(OsRng),
+            pallas::Base::random(OsRng),
+        )
+        .expect("Encryption failed");
+        assert_eq!(tracker.len(), 2);
+    }
+
+    #[test]
+    fn test_encrypt_from_transcript() {
+        use halo2_proofs::transcript::Blake2bWrite;
+
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let seed = pallas::Base::random(OsRng);
+        let transcript = |absorbed: pallas::Base| {
+            let mut transcript = Blake2bWrite::<_, vesta::Affine, Challenge255<_>>::init(vec![]);
+            transcript.common_scalar(absorbed).unwrap();
+            transcript
+        };
+
+        // the same transcript state yields the same ciphertext
+        let (data_in_transmit_1, witness_1) =
+            encrypt_from_transcript(&keypair.public_key, m, &mut transcript(seed))
+                .expect("Encryption failed");
+        let (data_in_transmit_2, _) =
+            encrypt_from_transcript(&keypair.public_key, m, &mut transcript(seed))
+                .expect("Encryption failed");
+        assert_eq!(data_in_transmit_1.c1(), data_in_transmit_2.c1());
+        assert_eq!(data_in_transmit_1.c2(), data_in_transmit_2.c2());
+        assert_eq!(data_in_transmit_1.r_encode(), data_in_transmit_2.r_encode());
+        assert_eq!(
+            data_in_transmit_1.c1(),
+            pallas::Point::generator() * witness_1.r_enc()
+        );
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, data_in_transmit_1.clone()),
+            Ok(m)
+        );
+
+        // another transcript state yields another randomness
+        let mut other = transcript(seed + pallas::Base::one());
+        let (data_in_transmit_3, _) =
+            encrypt_from_transcript(&keypair.public_key, m, &mut other).expect("Encryption failed");
+        assert_ne!(data_in_transmit_1.c1(), data_in_transmit_3.c1());
+        assert_ne!(data_in_transmit_1.r_encode(), data_in_transmit_3.r_encode());
+
+        // the transcript advances: a second encryption from it uses fresh randomness
+        let mut shared = transcript(seed);
+        let (first, _) = encrypt_from_transcript(&keypair.public_key, m, &mut shared).unwrap();
+        let (second, _) = encrypt_from_transcript(&keypair.public_key, m, &mut shared).unwrap();
+        assert_eq!(first.c1(), data_in_transmit_1.c1());
+        assert_ne!(first.c1(), second.c1());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_data_in_transmit_json_round_trip() {
+        use rand::rngs::OsRng;
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::random(OsRng);
+        let (data_in_transmit, _) =
+            extended_elgamal_encrypt(&keypair.public_key, m).expect("Encryption failed");
+
+        let json = serde_json::to_string(&data_in_transmit).expect("Serialization failed");
+        let parsed: DataInTransmit = serde_json::from_str(&json).expect("Deserialization failed");
+        assert_eq!(parsed.ct.c1, data_in_transmit.ct.c1);
+        assert_eq!(parsed.ct.c2, data_in_transmit.ct.c2);
+        assert_eq!(parsed.r_encode, data_in_transmit.r_encode);
+
+        let decrypted_plaintext =
+            extended_elgamal_decrypt(&keypair.private_key, parsed).expect("Decryption failed");
+        assert_eq!(m, decrypted_plaintext);
+
+        // a c1 whose x-coordinate is 2 is not on the curve (2^3 + 5 is not a square)
+        let mut invalid_point = [0u8; 32];
+        invalid_point[0] = 2;
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["ct"]["c1"] = serde_json::to_value(invalid_point).unwrap();
+        assert!(serde_json::from_value::<DataInTransmit>(value).is_err());
+    }
+
+    #[test]
+    fn test_rerandomize() {
+        use rand::rngs::OsRng;
+        let mut rng = OsRng;
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::random(&mut rng);
+        let (data_in_transmit, _) =
+            extended_elgamal_encrypt(&keypair.public_key, m).expect("Encryption failed");
+
+        let rerandomized = rerandomize(&data_in_transmit, &keypair.public_key, &mut rng);
+        assert_ne!(rerandomized.ct.c1, data_in_transmit.ct.c1);
+        assert_ne!(rerandomized.ct.c2, data_in_transmit.ct.c2);
+        assert_eq!(rerandomized.r_encode, data_in_transmit.r_encode);
+
+        // both ciphertexts decrypt to the same message
+        let decrypted = extended_elgamal_decrypt(&keypair.private_key, data_in_transmit)
+            .expect("Decryption failed");
+        let decrypted_rerandomized = extended_elgamal_decrypt(&keypair.private_key, rerandomized)
+            .expect("Decryption failed");
+        assert_eq!(decrypted, m);
+        assert_eq!(decrypted_rerandomized, m);
+    }
+
+    #[test]
+    fn test_add_ciphertexts() {
+        use crate::elgamal::elgamal::elgamal_decrypt;
+        use rand::rngs::OsRng;
+        let keypair = ElGamalKeypair::new();
+        let (data_in_transmit_1, witness_1) =
+            extended_elgamal_encrypt(&keypair.public_key, pallas::Base::random(OsRng))
+                .expect("Encryption failed");
+        let (data_in_transmit_2, witness_2) =
+            extended_elgamal_encrypt(&keypair.public_key, pallas::Base::random(OsRng))
+                .expect("Encryption failed");
+
+        let sum = add_ciphertexts(&data_in_transmit_1, &data_in_transmit_2);
+
+        // the decrypted point is the sum of the encoded points
+        let decrypted_point =
+            elgamal_decrypt(&keypair.private_key, &sum.ct).expect("Decryption failed");
+        assert_eq!(decrypted_point, witness_1.p_m + witness_2.p_m);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize() {
+        use rand::rngs::OsRng;
+        use zeroize::Zeroizing;
+
+        let keypair = ElGamalKeypair::new();
+        let (_, witness) =
+            extended_elgamal_encrypt(&keypair.public_key, pallas::Base::random(OsRng))
+                .expect("Encryption failed");
+        let mut witness = Zeroizing::new(witness);
+        witness.zeroize();
+        assert_eq!(witness.m, pallas::Base::zero());
+        assert_eq!(witness.p_m, pallas::Point::identity());
+        assert_eq!(witness.r_enc, pallas::Scalar::zero());
+    }
+
+    #[test]
+    fn test_encrypt_with_randomness() {
+        use crate::encode::encode::encode;
+        use rand::rngs::OsRng;
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::random(OsRng);
+        let (_, r_encode) = encode(m);
+        let r_enc = pallas::Scalar::random(OsRng);
+
+        let (data_in_transmit_1, witness_1) =
+            extended_elgamal_encrypt_with_randomness(&keypair.public_key, m, r_enc, r_encode)
+                .expect("r_encode is valid");
+        let (data_in_transmit_2, _) =
+            extended_elgamal_encrypt_with_randomness(&keypair.public_key, m, r_enc, r_encode)
+                .expect("r_encode is valid");
+
+        // the same randomness yields the same ciphertext
+        assert_eq!(data_in_transmit_1.ct.c1, data_in_transmit_2.ct.c1);
+        assert_eq!(data_in_transmit_1.ct.c2, data_in_transmit_2.ct.c2);
+        assert_eq!(data_in_transmit_1.r_encode, r_encode);
+        assert_eq!(witness_1.r_enc, r_enc);
+
+        let decrypted_plaintext =
+            extended_elgamal_decrypt(&keypair.private_key, data_in_transmit_1)
+                .expect("Decryption failed");
+        assert_eq!(m, decrypted_plaintext);
+    }
+
+    #[test]
+    fn test_decrypt_recovers_x_minus_r_encode() {
+        use group::Curve;
+        use pasta_curves::arithmetic::CurveAffine;
+        use rand::rngs::OsRng;
+        let keypair = ElGamalKeypair::new();
+        let messages = [
+            pallas::Base::zero(),
+            pallas::Base::one(),
+            -pallas::Base::one(),
+            pallas::Base::random(OsRng),
+        ];
+        for m in messages {
+            let (data_in_transmit, witness) =
+                extended_elgamal_encrypt(&keypair.public_key, m).expect("Encryption failed");
+            let x = *witness.p_m.to_affine().coordinates().unwrap().x();
+            let decrypted_plaintext =
+                extended_elgamal_decrypt(&keypair.private_key, data_in_transmit.clone())
+                    .expect("Decryption failed");
+            assert_eq!(decrypted_plaintext, x - data_in_transmit.r_encode);
+            assert_eq!(decrypted_plaintext, m);
+        }
+    }
+
+    #[test]
+    fn test_encrypt_retries_r_encode() {
+        use rand::rngs::OsRng;
+        let keypair = ElGamalKeypair::new();
+        let r_enc = pallas::Scalar::random(OsRng);
+
+        // 0^3 + 5 is not a square, 1^3 + 5 is a square
+        let m = pallas::Base::zero();
+        let (data_in_transmit, _) =
+            extended_elgamal_encrypt_with_randomness(&keypair.public_key, m, r_enc, m)
+                .expect("r_encode + 1 is valid");
+        assert_eq!(data_in_transmit.r_encode, pallas::Base::one());
+
+        let decrypted_plaintext = extended_elgamal_decrypt(&keypair.private_key, data_in_transmit)
+            .expect("Decryption failed");
+        assert_eq!(m, decrypted_plaintext);
+    }
+
+    #[test]
+    fn test_decrypt_tampered_ciphertext() {
+        use rand::rngs::OsRng;
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::random(OsRng);
+        let (data_in_transmit, _) =
+            extended_elgamal_encrypt(&keypair.public_key, m).expect("Encryption failed");
+
+        // c2 = [private_key]c1 decrypts to the identity
+        let mut tampered = data_in_transmit.clone();
+        tampered.ct.c2 = tampered.ct.c1 * keypair.private_key;
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, tampered),
+            Err(DecryptError::IdentityPoint)
+        );
+
+        // c1 = identity
+        let mut tampered = data_in_transmit;
+        tampered.ct.c1 = pallas::Point::identity();
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, tampered),
+            Err(DecryptError::InvalidCiphertext)
+        );
+    }
+
+    #[test]
+    fn test_encrypt_identity_public_key() {
+        use rand::rngs::OsRng;
+        let identity = pallas::Point::identity();
+        let m = pallas::Base::random(OsRng);
+
+        let result = extended_elgamal_encrypt(&identity, m);
+        assert_eq!(result.err(), Some(EncryptError::InvalidPublicKey));
+
+        let result = extended_elgamal_encrypt_with_generator(
+            &pallas::Point::generator(),
+            &identity,
+            m,
+            pallas::Scalar::random(OsRng),
+            pallas::Base::random(OsRng),
+        );
+        assert_eq!(result.err(), Some(EncryptError::InvalidPublicKey));
+    }
+
+    #[test]
+    fn test_data_in_transmit_bytes() {
+        let keypair = ElGamalKeypair::new();
+        let (data_in_transmit, _) =
+            extended_elgamal_encrypt(&keypair.public_key, pallas::Base::from(42)).unwrap();
+
+        let bytes = data_in_transmit.to_bytes();
+        assert_eq!(bytes.len(), DATA_IN_TRANSMIT_BYTES);
+        let decoded = DataInTransmit::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.c1(), data_in_transmit.c1());
+        assert_eq!(decoded.c2(), data_in_transmit.c2());
+        assert_eq!(decoded.r_encode(), data_in_transmit.r_encode());
+
+        assert_eq!(
+            DataInTransmit::from_bytes(&bytes[1..]).unwrap_err(),
+            DataInTransmitError::InvalidLength {
+                expected: DATA_IN_TRANSMIT_BYTES,
+                actual: DATA_IN_TRANSMIT_BYTES - 1
+            }
+        );
+
+        // 2^256 - 1 is not a canonical r_encode
+        let mut invalid = bytes.clone();
+        invalid[64..].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            DataInTransmit::from_bytes(&invalid).unwrap_err(),
+            DataInTransmitError::InvalidREncode
+        );
+    }
+
+    #[test]
+    fn test_encrypt_with_context() {
+        use crate::encode::encode::is_derived_r_encode;
+
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let encrypt = |context: &[u8]| {
+            extended_elgamal_encrypt_with_context(
+                &keypair.public_key,
+                m,
+                b"VE",
+                context,
+                &mut OsRng,
+            )
+            .unwrap()
+            .0
+        };
+        let data_in_transmit = encrypt(b"block 0");
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, data_in_transmit.clone()),
+            Ok(m)
+        );
+        assert!(is_derived_r_encode(
+            data_in_transmit.r_encode(),
+            b"VE",
+            b"block 0"
+        ));
+        assert!(!is_derived_r_encode(
+            data_in_transmit.r_encode(),
+            b"VE",
+            b"block 1"
+        ));
+
+        // the same context gives the same r_encode, with another r_enc
+        let other = encrypt(b"block 0");
+        assert_eq!(other.r_encode(), data_in_transmit.r_encode());
+        assert_ne!(other.c1(), data_in_transmit.c1());
+    }
+
+    #[test]
+    fn test_default_r_encode() {
+        use crate::encode::encode::MAX_ENCODE_ATTEMPTS;
+        use rand::rngs::OsRng;
+
+        let keypair = ElGamalKeypair::new();
+        let other = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let (data_in_transmit, _) = extended_elgamal_encrypt(&keypair.public_key, m).unwrap();
+        assert!(has_default_r_encode(&data_in_transmit, &keypair.public_key));
+        assert!(!has_default_r_encode(&data_in_transmit, &other.public_key));
+
+        // any accepted offset from the derived r_encode, but not a sampled r_encode
+        let derived = default_r_encode(&keypair.public_key, &data_in_transmit.c1());
+        let mut offset = data_in_transmit.clone();
+        offset.r_encode = derived + pallas::Base::from(MAX_ENCODE_ATTEMPTS - 1);
+        assert!(has_default_r_encode(&offset, &keypair.public_key));
+        offset.r_encode = derived + pallas::Base::from(MAX_ENCODE_ATTEMPTS);
+        assert!(!has_default_r_encode(&offset, &keypair.public_key));
+        let (sampled, _) =
+            extended_elgamal_encrypt_with_rng(&keypair.public_key, m, &mut OsRng).unwrap();
+        assert!(!has_default_r_encode(&sampled, &keypair.public_key));
+    }
+
+    #[test]
+    fn test_data_in_transmit_ct_eq() {
+        let keypair = ElGamalKeypair::new();
+        let m = pallas::Base::from(42);
+        let (data_in_transmit, _) = extended_elgamal_encrypt(&keypair.public_key, m).unwrap();
+        let (other, _) = extended_elgamal_encrypt(&keypair.public_key, m).unwrap();
+
+        let parsed = DataInTransmit::from_bytes(&data_in_transmit.to_bytes()).unwrap();
+        let mut other_r_encode = data_in_transmit.clone();
+        other_r_encode.r_encode += pallas::Base::one();
+        let swapped = DataInTransmit::new(
+            ElGamalCiphertext::new(data_in_transmit.c2(), data_in_transmit.c1()),
+            data_in_transmit.r_encode(),
+        );
+
+        // ct_eq agrees with the equality of the points and of r_encode
+        let eq = |a: &DataInTransmit, b: &DataInTransmit| {
+            (a.c1(), a.c2(), a.r_encode()) == (b.c1(), b.c2(), b.r_encode())
+        };
+        for a in [
+            &data_in_transmit,
+            &other,
+            &parsed,
+            &other_r_encode,
+            &swapped,
+        ] {
+            for b in [
+                &data_in_transmit,
+                &other,
+                &parsed,
+                &other_r_encode,
+                &swapped,
+            ] {
+                assert_eq!(bool::from(a.ct_eq(b)), eq(a, b));
+            }
+        }
+        assert!(bool::from(data_in_transmit.ct_eq(&parsed)));
+        assert!(!bool::from(data_in_transmit.ct_eq(&other)));
+        assert!(!bool::from(data_in_transmit.ct_eq(&other_r_encode)));
+        assert!(!bool::from(data_in_transmit.ct_eq(&swapped)));
+    }
+}
+
+// The API without the std feature: the randomness comes from the caller, there is no OsRng.
+// The test harness links std, so the no_std build itself is checked by
+// `tests/build_targets.rs`, for a target without std.
+#[cfg(all(test, not(feature = "std")))]
+mod tests {
+    use super::*;
+    use crate::encode::encode::{base_to_signed, decode, encode_with_rng, signed_to_base};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn core_without_std() {
+        let mut rng = ChaCha20Rng::from_seed([7u8; 32]);
+        let keypair = ElGamalKeypair::from_rng(&mut rng);
+        let m = pallas::Base::from(42);
+
+        let (data_in_transmit, witness) =
+            extended_elgamal_encrypt_with_rng(&keypair.public_key, m, &mut rng)
+                .expect("Encryption failed");
+        assert_eq!(
+            extended_elgamal_decrypt(&keypair.private_key, data_in_transmit),
+            Ok(m)
+        );
+
+        assert_eq!(witness.m(), m);
+
+        let (p_m, r_encode) = encode_with_rng(signed_to_base(-42), &mut rng);
+        assert_eq!(base_to_signed(decode(p_m, r_encode)), Ok(-42));
+    }
 }
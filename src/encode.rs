@@ -1,3 +1,5 @@
+pub mod bytes;
 pub mod encode;
+pub mod framing;
 
 pub mod utf8;
@@ -0,0 +1,328 @@
+/// Binary data transformation functions
+/// Split arbitrary binary data into blocks that fit in pallas base field elements, and back
+use alloc::vec::Vec;
+use core::fmt;
+use ff::PrimeField;
+use pasta_curves::pallas;
+
+/// Maximum number of bytes of a message block.
+/// Any 31-byte value is smaller than 2^248 and fits in the pallas base field.
+pub const MAX_BLOCK_BYTES: usize = 31;
+
+// Errors returned when converting messages to field elements
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    BlockTooLong { actual: usize, max: usize }, // the block does not fit in a field element
+    InvalidBlockSize { block_size: usize, max: usize }, // the block size is 0 or larger than max
+    InvalidPadding, // the decoded blocks do not end with a valid padding
+    InvalidUtf8,    // the decoded bytes are not a UTF-8 string
+    NoPointFound { attempts: u64 }, // no r_encode tried gives a point on the curve
+    OutOfRange,     // the decoded message is not the encoding of an i64
+    InvalidFrame,   // the length block does not match the data blocks of a framed message
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::BlockTooLong { actual, max } => write!(
+                f,
+                "message block is {} bytes long, at most {} bytes are allowed",
+                actual, max
+            ),
+            EncodeError::InvalidBlockSize { block_size, max } => write!(
+                f,
+                "block size {} is invalid, it must be between 1 and {}",
+                block_size, max
+            ),
+            EncodeError::InvalidPadding => write!(f, "message blocks have an invalid padding"),
+            EncodeError::InvalidUtf8 => write!(f, "decoded message is not valid UTF-8"),
+            EncodeError::NoPointFound { attempts } => {
+                write!(
+                    f,
+                    "no point found to encode the message in {} attempts",
+                    attempts
+                )
+            }
+            EncodeError::OutOfRange => write!(f, "decoded message is not a signed 64-bit value"),
+            EncodeError::InvalidFrame => {
+                write!(
+                    f,
+                    "the length of the framed message does not match its blocks"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
+
+/// Convert at most MAX_BLOCK_BYTES little-endian bytes to the limbs of a field element.
+/// The input is zero-padded, so the result is always a canonical pallas base field element.
+pub fn convert_u8_array_to_u64_array(input: &[u8]) -> Result<[u64; 4], EncodeError> {
+    if input.len() > MAX_BLOCK_BYTES {
+        return Err(EncodeError::BlockTooLong {
+            actual: input.len(),
+            max: MAX_BLOCK_BYTES,
+        });
+    }
+
+    let mut padded = [0u8; 32];
+    padded[..input.len()].copy_from_slice(input);
+
+    let mut output = [0u64; 4];
+    for (i, chunk) in padded.chunks_exact(8).enumerate() {
+        let bytes: [u8; 8] = chunk.try_into().expect("slice with incorrect length");
+        output[i] = u64::from_le_bytes(bytes); // Use from_le_bytes if the input is little-endian
+    }
+    Ok(output)
+}
+
+pub fn convert_u64_array_to_u8_array(input: [u64; 4]) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    for (i, limb) in input.iter().enumerate() {
+        output[i * 8..(i + 1) * 8].copy_from_slice(&limb.to_le_bytes());
+    }
+    output
+}
+
+/// Check that blocks of `block_size` bytes fit in a field element,
+/// 1 <= block_size <= MAX_BLOCK_BYTES.
+/// Every function taking a block size checks it with this function, so the limit is the same
+/// across the encodings.
+pub fn validate_block_size(block_size: usize) -> Result<(), EncodeError> {
+    if block_size == 0 || block_size > MAX_BLOCK_BYTES {
+        return Err(EncodeError::InvalidBlockSize {
+            block_size,
+            max: MAX_BLOCK_BYTES,
+        });
+    }
+    Ok(())
+}
+
+/// Split data into blocks of exactly `block_size` bytes, 1 <= block_size <= MAX_BLOCK_BYTES.
+///
+/// The data is padded PKCS#7-style: n bytes of value n are appended, with
+/// 1 <= n <= block_size, so that the length is a multiple of `block_size`. A padding is
+/// always added, hence the data (including trailing null bytes) is recovered exactly by
+/// `decode_bytes`, and empty data gives a single block of padding.
+pub fn split_bytes_into_blocks(
+    data: &[u8],
+    block_size: usize,
+) -> Result<Vec<Vec<u8>>, EncodeError> {
+    Ok(block_iter(data, block_size)?.collect())
+}
+
+/// Iterator over the blocks of `split_bytes_into_blocks`, in the same order.
+/// Each block is only copied when it is yielded, so the blocks of large data can be
+/// encrypted and proven one at a time without materializing the whole split.
+pub fn block_iter(
+    data: &[u8],
+    block_size: usize,
+) -> Result<impl Iterator<Item = Vec<u8>> + '_, EncodeError> {
+    validate_block_size(block_size)?;
+
+    // Split into chunks of `block_size` bytes, the remainder goes in the padded last block
+    let chunks = data.chunks_exact(block_size);
+    let mut last = chunks.remainder().to_vec();
+    let padding = block_size - last.len();
+    last.resize(block_size, padding as u8);
+
+    Ok(chunks
+        .map(|chunk| chunk.to_vec())
+        .chain(core::iter::once(last)))
+}
+
+/// Split data into padded blocks and convert each block to a field element message.
+pub fn encode_bytes(data: &[u8], block_size: usize) -> Result<Vec<pallas::Base>, EncodeError> {
+    split_bytes_into_blocks(data, block_size)?
+        .iter()
+        .map(|block| convert_u8_array_to_u64_array(block).map(pallas::Base::from_raw))
+        .collect()
+}
+
+/// Recover data split by `split_bytes_into_blocks` from its field element messages, in order.
+/// The first `block_size` bytes of each block are concatenated and the padding is removed.
+pub fn decode_bytes(blocks: &[pallas::Base], block_size: usize) -> Result<Vec<u8>, EncodeError> {
+    validate_block_size(block_size)?;
+
+    let mut bytes = Vec::with_capacity(blocks.len() * block_size);
+    for m in blocks {
+        let repr = m.to_repr();
+        if repr[block_size..].iter().any(|&byte| byte != 0) {
+            return Err(EncodeError::InvalidPadding);
+        }
+        bytes.extend_from_slice(&repr[..block_size]);
+    }
+
+    // the last byte is the number of padding bytes, in 1..=block_size
+    let padding = *bytes.last().ok_or(EncodeError::InvalidPadding)? as usize;
+    if padding == 0
+        || padding > block_size
+        || bytes[bytes.len() - padding..]
+            .iter()
+            .any(|&byte| byte as usize != padding)
+    {
+        return Err(EncodeError::InvalidPadding);
+    }
+    bytes.truncate(bytes.len() - padding);
+
+    Ok(bytes)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::elgamal::extended_elgamal::{extended_elgamal_decrypt, extended_elgamal_encrypt};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+
+    #[test]
+    fn test_convert_block_boundary() {
+        // 31 bytes is the largest block
+        let block = [0xffu8; 31];
+        let limbs = convert_u8_array_to_u64_array(&block).expect("31 bytes must fit");
+        let m = pallas::Base::from_raw(limbs);
+        assert_eq!(&m.to_repr()[..31], &block[..]);
+        assert_eq!(m.to_repr()[31], 0);
+
+        // 32 bytes is rejected
+        assert_eq!(
+            convert_u8_array_to_u64_array(&[0xffu8; 32]),
+            Err(EncodeError::BlockTooLong {
+                actual: 32,
+                max: MAX_BLOCK_BYTES
+            })
+        );
+    }
+
+    #[test]
+    fn test_block_iter() {
+        let mut data = [0u8; 100];
+        OsRng.fill_bytes(&mut data);
+        for block_size in 1..=MAX_BLOCK_BYTES {
+            for len in [
+                0,
+                1,
+                block_size - 1,
+                block_size,
+                2 * block_size + 1,
+                data.len(),
+            ] {
+                let blocks: Vec<Vec<u8>> = block_iter(&data[..len], block_size).unwrap().collect();
+                let mut padded = data[..len].to_vec();
+                let padding = block_size - len % block_size;
+                padded.resize(len + padding, padding as u8);
+                let expected: Vec<Vec<u8>> = padded
+                    .chunks(block_size)
+                    .map(|chunk| chunk.to_vec())
+                    .collect();
+                assert_eq!(blocks, expected);
+                assert_eq!(
+                    split_bytes_into_blocks(&data[..len], block_size).unwrap(),
+                    expected
+                );
+            }
+        }
+
+        assert!(block_iter(&data, 0).is_err());
+        assert!(block_iter(&data, MAX_BLOCK_BYTES + 1).is_err());
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_binary_data() {
+        // a random 40-byte blob spans two blocks
+        let mut data = [0u8; 40];
+        OsRng.fill_bytes(&mut data);
+        let blocks = encode_bytes(&data, MAX_BLOCK_BYTES).unwrap();
+        assert_eq!(blocks.len(), 2);
+
+        let keypair = ElGamalKeypair::new();
+        let decrypted: Vec<pallas::Base> = blocks
+            .iter()
+            .map(|m| {
+                let (data_in_transmit, _) =
+                    extended_elgamal_encrypt(&keypair.public_key, *m).expect("Encryption failed");
+                extended_elgamal_decrypt(&keypair.private_key, data_in_transmit)
+                    .expect("Decryption failed")
+            })
+            .collect();
+
+        assert_eq!(decode_bytes(&decrypted, MAX_BLOCK_BYTES).unwrap(), data);
+    }
+
+    proptest! {
+        // a block of at most 31 bytes is the little-endian encoding of its field element
+        #[test]
+        fn prop_convert_block(block in vec(any::<u8>(), 0..=MAX_BLOCK_BYTES)) {
+            let limbs = convert_u8_array_to_u64_array(&block).unwrap();
+            let mut padded = [0u8; 32];
+            padded[..block.len()].copy_from_slice(&block);
+            prop_assert_eq!(convert_u64_array_to_u8_array(limbs), padded);
+            prop_assert_eq!(pallas::Base::from_raw(limbs).to_repr(), padded);
+        }
+
+        // a field element below 2^248 round-trips through its first 31 bytes
+        #[test]
+        fn prop_convert_field_element(bytes in any::<[u8; MAX_BLOCK_BYTES]>()) {
+            let mut repr = [0u8; 32];
+            repr[..MAX_BLOCK_BYTES].copy_from_slice(&bytes);
+            let m = pallas::Base::from_repr(repr).unwrap();
+            let limbs = convert_u8_array_to_u64_array(&m.to_repr()[..MAX_BLOCK_BYTES]).unwrap();
+            prop_assert_eq!(pallas::Base::from_raw(limbs), m);
+        }
+
+        // the padding always adds a block when the data fills the last one
+        #[test]
+        fn prop_bytes_round_trip(
+            data in vec(any::<u8>(), 0..200),
+            block_size in 1..=MAX_BLOCK_BYTES,
+        ) {
+            let blocks = encode_bytes(&data, block_size).unwrap();
+            prop_assert_eq!(blocks.len(), data.len() / block_size + 1);
+            prop_assert_eq!(decode_bytes(&blocks, block_size).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_block_size_limit() {
+        use crate::encode::framing::{frame_message, unframe_message};
+        use crate::encode::utf8::{decode_message_blocks, split_message_into_blocks};
+
+        let invalid = EncodeError::InvalidBlockSize {
+            block_size: MAX_BLOCK_BYTES + 1,
+            max: MAX_BLOCK_BYTES,
+        };
+        for block_size in [MAX_BLOCK_BYTES, MAX_BLOCK_BYTES + 1] {
+            let valid = block_size <= MAX_BLOCK_BYTES;
+            assert_eq!(validate_block_size(block_size).is_ok(), valid);
+
+            let blocks = encode_bytes(b"data", block_size);
+            let framed = frame_message(b"data", block_size);
+            assert_eq!(blocks.is_ok(), valid);
+            assert_eq!(framed.is_ok(), valid);
+            assert_eq!(split_bytes_into_blocks(b"data", block_size).is_ok(), valid);
+            assert_eq!(block_iter(b"data", block_size).is_ok(), valid);
+            assert_eq!(split_message_into_blocks("data", block_size).is_ok(), valid);
+
+            // blocks encoded with the largest block size are decoded with it only
+            let blocks = encode_bytes(b"data", MAX_BLOCK_BYTES).unwrap();
+            let framed = frame_message(b"data", MAX_BLOCK_BYTES).unwrap();
+            if valid {
+                assert_eq!(decode_bytes(&blocks, block_size).unwrap(), b"data");
+                assert_eq!(decode_message_blocks(&blocks, block_size).unwrap(), "data");
+                assert_eq!(unframe_message(&framed, block_size).unwrap(), b"data");
+            } else {
+                assert_eq!(decode_bytes(&blocks, block_size), Err(invalid.clone()));
+                assert_eq!(
+                    decode_message_blocks(&blocks, block_size),
+                    Err(invalid.clone())
+                );
+                assert_eq!(unframe_message(&framed, block_size), Err(invalid.clone()));
+            }
+        }
+    }
+}
@@ -1,23 +1,32 @@
 /// Defined in [Verifiable Encryption using Halo2][Section 2.3. Encode a Message into a Point].
 /// encode allows to encode a Fp message to an ECC point
 /// decode to decode an ECC point to a Fp message
+use crate::encode::bytes::EncodeError;
 use crate::encode::utf8::convert_u8_array_to_u64_array;
-use ff::Field;
+use blake2b_simd::Params;
+use ff::{Field, FromUniformBytes, PrimeField};
 use group::prime::PrimeCurveAffine;
 use group::Curve;
-use pasta_curves::arithmetic::CurveAffine;
-use pasta_curves::{pallas, Fp};
+use pasta_curves::arithmetic::{CurveAffine, CurveExt};
+use pasta_curves::pallas;
+#[cfg(feature = "std")]
 use rand::rngs::OsRng;
+use rand::RngCore;
 use subtle::CtOption;
 
 /// Defined in [Verifiable Encryption using Halo2][Section 2.3. Encode a Message into a Point][Encode, step 3 - 4].
 pub fn find_point_from_scalar(x_m: pallas::Base) -> Option<pallas::Point> {
+    find_point_on(x_m)
+}
+
+/// `find_point_from_scalar` on a pasta curve y^2 = x^3 + 5, e.g. vesta with x_m in its base field.
+pub fn find_point_on<C: CurveExt>(x_m: C::Base) -> Option<C> {
     // compute a point on curve by the x value
-    let y_square = x_m * x_m * x_m + pallas::Base::from(5);
-    let y: CtOption<Fp> = y_square.sqrt();
+    let y_square = x_m * x_m * x_m + C::b();
+    let y: CtOption<C::Base> = y_square.sqrt();
     // if a square root y exists, return p_m =(x_m,y), otherwise, return None
     if y.is_some().unwrap_u8() == 1u8 {
-        let m_point = pallas::Affine::from_xy(x_m, y.unwrap()).unwrap();
+        let m_point = C::AffineExt::from_xy(x_m, y.unwrap()).unwrap();
         let p_m = m_point.to_curve();
         return Some(p_m);
     }
@@ -25,8 +34,13 @@ pub fn find_point_from_scalar(x_m: pallas::Base) -> Option<pallas::Point> {
 }
 
 /// Encode function
+#[cfg(feature = "std")]
 pub fn encode(m: pallas::Base) -> (pallas::Point, pallas::Base) {
-    let mut x_m;
+    encode_with_rng(m, &mut OsRng)
+}
+
+/// Encode function, with the randomness drawn from the given RNG
+pub fn encode_with_rng<R: RngCore>(m: pallas::Base, rng: &mut R) -> (pallas::Point, pallas::Base) {
     let mut r;
     let p_m;
 
@@ -34,12 +48,10 @@ pub fn encode(m: pallas::Base) -> (pallas::Point, pallas::Base) {
         // Defined in [Verifiable Encryption using Halo2][Section 2.3. Encode a Message into a Point][Encode, step 1-2].
         // add a random element r to the message to ensure there exists a point with x-coordinates x_m on curve
         // repeat until a point is found
-        let rng = OsRng;
-        r = pallas::Base::random(rng);
-        x_m = m + r;
+        r = pallas::Base::random(&mut *rng);
 
-        // find a point by x_m
-        match find_point_from_scalar(x_m) {
+        // find a point by x_m = m + r
+        match encode_message(m, r) {
             Some(point) => {
                 p_m = point;
                 break;
@@ -50,18 +62,149 @@ pub fn encode(m: pallas::Base) -> (pallas::Point, pallas::Base) {
     return (p_m, r);
 }
 
+/// Encode a message with a given randomness r_encode, that is,
+/// p_m.x = r_encode + m and p_m.y is a square root of p_m.x^3 + 5.
+/// Returns None if p_m.x^3 + 5 is not a square, so that there is no point with x-coordinate
+/// m + r_encode on the curve. The caller must then retry with a new r_encode, as `encode` does.
+pub fn encode_message(m: pallas::Base, r_encode: pallas::Base) -> Option<pallas::Point> {
+    find_point_from_scalar(m + r_encode)
+}
+
+/// Maximal number of r_encode values tried by `encode_with_retry`.
+/// About half of the x-coordinates are on the curve, so all the attempts fail with
+/// probability 2^-MAX_ENCODE_ATTEMPTS.
+pub const MAX_ENCODE_ATTEMPTS: u64 = 128;
+
+/// Encode a message starting from r_encode, and retrying with r_encode + 1, r_encode + 2, ...
+/// until a point is found. Returns the point and the r_encode that gives it.
+/// Fails after MAX_ENCODE_ATTEMPTS attempts.
+pub fn encode_with_retry(
+    m: pallas::Base,
+    r_encode: pallas::Base,
+) -> Result<(pallas::Point, pallas::Base), EncodeError> {
+    encode_with_retry_on(m, r_encode)
+}
+
+/// `encode_with_retry` on a pasta curve, see `find_point_on`.
+pub fn encode_with_retry_on<C: CurveExt>(
+    m: C::Base,
+    r_encode: C::Base,
+) -> Result<(C, C::Base), EncodeError> {
+    let mut r = r_encode;
+    for _ in 0..MAX_ENCODE_ATTEMPTS {
+        if let Some(p_m) = find_point_on(m + r) {
+            return Ok((p_m, r));
+        }
+        r += C::Base::ONE;
+    }
+    Err(EncodeError::NoPointFound {
+        attempts: MAX_ENCODE_ATTEMPTS,
+    })
+}
+
+/// Personalization of BLAKE2b for `derive_r_encode`
+const R_ENCODE_PERSONALIZATION: &[u8; 16] = b"QED-it:VE-REncod";
+
+/// Derive r_encode deterministically from a public context, e.g. a session identifier and
+/// the index of a block, so that the verifier can recompute it and the encryptor does not
+/// choose it freely. r_encode is a 512-bit BLAKE2b hash of `domain_sep` and
+/// `context` reduced modulo the base field modulus, which is uniform up to a statistical
+/// distance below 2^-250. `domain_sep` is prefixed with its length, so that distinct pairs
+/// of `domain_sep` and `context` are distinct hash inputs.
+///
+/// The encoding starts from the derived r_encode and increments it until a point is found,
+/// see `encode_with_retry`, so the r_encode of a ciphertext is checked with
+/// `is_derived_r_encode`.
+pub fn derive_r_encode(domain_sep: &[u8], context: &[u8]) -> pallas::Base {
+    let hash = Params::new()
+        .hash_length(64)
+        .personal(R_ENCODE_PERSONALIZATION)
+        .to_state()
+        .update(&(domain_sep.len() as u64).to_le_bytes())
+        .update(domain_sep)
+        .update(context)
+        .finalize();
+    pallas::Base::from_uniform_bytes(hash.as_array())
+}
+
+/// Check that r_encode is one of the MAX_ENCODE_ATTEMPTS values tried by `encode_with_retry`
+/// from the r_encode derived from the context, see `derive_r_encode`.
+/// Any of these offsets is accepted, so the encryptor can still choose among up to
+/// MAX_ENCODE_ATTEMPTS values of r_encode for a given context: r_encode is constrained by
+/// the context, not fixed by it.
+pub fn is_derived_r_encode(r_encode: pallas::Base, domain_sep: &[u8], context: &[u8]) -> bool {
+    let attempt = (r_encode - derive_r_encode(domain_sep, context)).to_repr();
+    attempt[8..].iter().all(|&byte| byte == 0)
+        && u64::from_le_bytes(attempt[..8].try_into().unwrap()) < MAX_ENCODE_ATTEMPTS
+}
+
 /// Decode function
 pub fn decode(pt: pallas::Point, r: pallas::Base) -> pallas::Base {
+    decode_on(pt, r)
+}
+
+/// `decode` on a pasta curve, see `find_point_on`.
+pub fn decode_on<C: CurveExt>(pt: C, r: C::Base) -> C::Base {
     // get the x-coordinate x_m of the affine point (x_m, y)
     // compute m = x_m -r
-    pt.to_affine().coordinates().unwrap().x() - r
+    *pt.to_affine().coordinates().unwrap().x() - r
+}
+
+/// Inverse of `encode_message`: m = p_m.x - r_encode
+pub fn decode_point(p_m: pallas::Point, r_encode: pallas::Base) -> pallas::Base {
+    decode(p_m, r_encode)
+}
+
+/// Field element of a signed message: i >= 0 is mapped to i, and -k to p - k,
+/// where p is the modulus of the pallas base field, so that field arithmetic on the
+/// messages matches the integer arithmetic as long as the results stay in the i64 range.
+pub fn signed_to_base(i: i64) -> pallas::Base {
+    if i < 0 {
+        -pallas::Base::from(i.unsigned_abs())
+    } else {
+        pallas::Base::from(i as u64)
+    }
+}
+
+/// Inverse of `signed_to_base`.
+/// The representable range is [-2^63, 2^63): the elements [0, 2^63) are the non-negative
+/// values and [p - 2^63, p) the negative ones. Any other element, e.g. a wrapped sum of
+/// messages, fails with `EncodeError::OutOfRange` instead of being read as another value.
+pub fn base_to_signed(m: pallas::Base) -> Result<i64, EncodeError> {
+    let fits_in_i64 = |m: pallas::Base| -> Option<u64> {
+        let repr = m.to_repr();
+        if repr[8..].iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        let value = u64::from_le_bytes(repr[..8].try_into().unwrap());
+        (value <= 1 << 63).then_some(value)
+    };
+    match (fits_in_i64(m), fits_in_i64(-m)) {
+        (Some(value), _) if value < 1 << 63 => Ok(value as i64),
+        (_, Some(value)) => Ok((value as i64).wrapping_neg()),
+        _ => Err(EncodeError::OutOfRange),
+    }
 }
 
-#[cfg(test)]
+/// Encode a signed message, see `signed_to_base` for the mapping to the field.
+#[cfg(feature = "std")]
+pub fn encode_signed(i: i64) -> (pallas::Point, pallas::Base) {
+    encode(signed_to_base(i))
+}
+
+/// Decode a signed message encoded by `encode_signed`.
+/// Fails if the decoded element is out of the i64 range.
+pub fn decode_signed(p_m: pallas::Point, r_encode: pallas::Base) -> Result<i64, EncodeError> {
+    base_to_signed(decode(p_m, r_encode))
+}
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
-    use crate::encode::utf8::{convert_string_to_u8_array, convert_u8_array_to_string};
-    use ff::PrimeField;
+    use crate::encode::utf8::{
+        convert_string_to_u8_array, convert_u64_array_to_u8_array, convert_u8_array_to_string,
+        decode_message_blocks, split_message_into_blocks,
+    };
 
     #[test]
     fn test_encode_decode_scalar() {
@@ -96,9 +239,9 @@ mod tests {
                 .map(char::from)
                 .collect();
 
-            let m = pallas::Base::from_raw(convert_u8_array_to_u64_array(
-                convert_string_to_u8_array(&random_string),
-            ));
+            let m = pallas::Base::from_raw(
+                convert_u8_array_to_u64_array(random_string.as_bytes()).unwrap(),
+            );
 
             let (encoded, r) = encode(m);
             let decoded = decode(encoded, r);
@@ -108,4 +251,91 @@ mod tests {
             assert_eq!(random_string, str);
         }
     }
+
+    #[test]
+    fn test_convert_u64_array_to_u8_array() {
+        let bytes = convert_string_to_u8_array("héllo 世界");
+        let limbs = convert_u8_array_to_u64_array("héllo 世界".as_bytes()).unwrap();
+        assert_eq!(convert_u64_array_to_u8_array(limbs), bytes);
+    }
+
+    #[test]
+    fn test_encode_decode_utf8_message() {
+        let message = "héllo 世界";
+
+        // encode each block to a point, then decode the points back to the message
+        let decoded: Vec<pallas::Base> = split_message_into_blocks(message, 3)
+            .unwrap()
+            .iter()
+            .map(|block| {
+                let m = pallas::Base::from_raw(
+                    convert_u8_array_to_u64_array(block).unwrap(),
+                );
+                let (encoded, r) = encode(m);
+                decode(encoded, r)
+            })
+            .collect();
+
+        // 13 bytes and 2 bytes of padding
+        assert_eq!(decoded.len(), 5);
+        assert_eq!(decode_message_blocks(&decoded, 3).unwrap(), message);
+    }
+
+    #[test]
+    fn test_encode_message_residue() {
+        // 1^3 + 5 = 6 is a square
+        let m = pallas::Base::from(1);
+        let p_m = encode_message(m, pallas::Base::zero()).unwrap();
+        assert_eq!(decode_point(p_m, pallas::Base::zero()), m);
+
+        let r_encode = pallas::Base::random(OsRng);
+        if let Some(p_m) = encode_message(m, r_encode) {
+            assert_eq!(decode_point(p_m, r_encode), m);
+        }
+    }
+
+    #[test]
+    fn test_encode_message_non_residue() {
+        // 0^3 + 5 = 5 and 2^3 + 5 = 13 are not squares
+        assert!(encode_message(pallas::Base::zero(), pallas::Base::zero()).is_none());
+        assert!(encode_message(pallas::Base::one(), pallas::Base::one()).is_none());
+    }
+
+    #[test]
+    fn test_encode_decode_signed() {
+        for i in [42, 0, -42, i64::MAX, i64::MIN] {
+            let (p_m, r_encode) = encode_signed(i);
+            assert_eq!(decode_signed(p_m, r_encode), Ok(i));
+        }
+        assert_eq!(signed_to_base(-1), -pallas::Base::one());
+
+        // a delta is added to a balance in the field
+        let balance = signed_to_base(100) + signed_to_base(-142);
+        assert_eq!(base_to_signed(balance), Ok(-42));
+
+        // values outside of [-2^63, 2^63) are rejected, e.g. a sum which wraps around
+        let wrapped = signed_to_base(i64::MAX) + pallas::Base::one();
+        assert_eq!(base_to_signed(wrapped), Err(EncodeError::OutOfRange));
+        assert_eq!(base_to_signed(-wrapped - pallas::Base::one()), Err(EncodeError::OutOfRange));
+        assert_eq!(base_to_signed(pallas::Base::random(OsRng)), Err(EncodeError::OutOfRange));
+    }
+
+    #[test]
+    fn test_derive_r_encode() {
+        let r_encode = derive_r_encode(b"domain", b"context");
+        assert_eq!(r_encode, derive_r_encode(b"domain", b"context"));
+        assert_ne!(r_encode, derive_r_encode(b"domain", b"other context"));
+        assert_ne!(r_encode, derive_r_encode(b"other domain", b"context"));
+        // the domain separator is not a prefix of the context
+        assert_ne!(derive_r_encode(b"ab", b"c"), derive_r_encode(b"a", b"bc"));
+
+        // the r_encode found by the encoding is accepted, unlike any other value
+        let (_, found) = encode_with_retry(pallas::Base::from(42), r_encode).unwrap();
+        assert!(is_derived_r_encode(found, b"domain", b"context"));
+        assert!(!is_derived_r_encode(found, b"domain", b"other context"));
+        let last = r_encode + pallas::Base::from(MAX_ENCODE_ATTEMPTS - 1);
+        assert!(is_derived_r_encode(last, b"domain", b"context"));
+        assert!(!is_derived_r_encode(last + pallas::Base::one(), b"domain", b"context"));
+        assert!(!is_derived_r_encode(r_encode - pallas::Base::one(), b"domain", b"context"));
+    }
 }
@@ -0,0 +1,118 @@
+/// Length-prefixed framing of binary data into field element messages
+/// The first block is the length of the data, so that the data blocks only need zero padding
+/// and the data is recovered exactly, including its trailing null bytes.
+use crate::encode::bytes::{convert_u8_array_to_u64_array, validate_block_size, EncodeError};
+use alloc::vec::Vec;
+use ff::PrimeField;
+use pasta_curves::pallas;
+
+/// Frame data into field element messages: the first message is the length of the data in
+/// bytes, followed by the data in blocks of `block_size` bytes, 1 <= block_size <= MAX_BLOCK_BYTES.
+/// The last data block is zero-padded, and empty data gives the length block only.
+pub fn frame_message(data: &[u8], block_size: usize) -> Result<Vec<pallas::Base>, EncodeError> {
+    validate_block_size(block_size)?;
+
+    let mut blocks = Vec::with_capacity(1 + data.len().div_ceil(block_size));
+    blocks.push(pallas::Base::from(data.len() as u64));
+    for chunk in data.chunks(block_size) {
+        blocks.push(pallas::Base::from_raw(convert_u8_array_to_u64_array(chunk)?));
+    }
+    Ok(blocks)
+}
+
+/// Recover data framed by `frame_message` from its messages, in order.
+/// Fails with `EncodeError::InvalidFrame` if the number of data blocks is not the one given by
+/// the length, or if a block has non-zero bytes after the data.
+pub fn unframe_message(
+    blocks: &[pallas::Base],
+    block_size: usize,
+) -> Result<Vec<u8>, EncodeError> {
+    validate_block_size(block_size)?;
+
+    let (length, data_blocks) = blocks.split_first().ok_or(EncodeError::InvalidFrame)?;
+    let length = length.to_repr();
+    if length[8..].iter().any(|&byte| byte != 0) {
+        return Err(EncodeError::InvalidFrame);
+    }
+    let length = u64::from_le_bytes(length[..8].try_into().unwrap());
+    let length = usize::try_from(length).map_err(|_| EncodeError::InvalidFrame)?;
+    if data_blocks.len() != length.div_ceil(block_size) {
+        return Err(EncodeError::InvalidFrame);
+    }
+
+    let mut data = Vec::with_capacity(data_blocks.len() * block_size);
+    for m in data_blocks {
+        let repr = m.to_repr();
+        if repr[block_size..].iter().any(|&byte| byte != 0) {
+            return Err(EncodeError::InvalidFrame);
+        }
+        data.extend_from_slice(&repr[..block_size]);
+    }
+
+    // the padding of the last block is zero
+    if data[length..].iter().any(|&byte| byte != 0) {
+        return Err(EncodeError::InvalidFrame);
+    }
+    data.truncate(length);
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame_message, unframe_message};
+    use crate::encode::bytes::{EncodeError, MAX_BLOCK_BYTES};
+    use pasta_curves::pallas;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        // 10 bytes in blocks of 4 bytes, with trailing null bytes which are kept
+        let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 0, 0];
+        let blocks = frame_message(&data, 4).unwrap();
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0], pallas::Base::from(10));
+        assert_eq!(unframe_message(&blocks, 4).unwrap(), data);
+
+        for length in [0, 1, 30, 31, 32, 100] {
+            let data: Vec<u8> = (0..length).map(|i| i as u8).collect();
+            let blocks = frame_message(&data, 31).unwrap();
+            assert_eq!(unframe_message(&blocks, 31).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_invalid_frame() {
+        let blocks = frame_message(&[1, 2, 3, 4, 5], 4).unwrap();
+        assert_eq!(unframe_message(&[], 4), Err(EncodeError::InvalidFrame));
+
+        // a missing or an extra block
+        assert_eq!(unframe_message(&blocks[..1], 4), Err(EncodeError::InvalidFrame));
+        let mut extra = blocks.clone();
+        extra.push(pallas::Base::zero());
+        assert_eq!(unframe_message(&extra, 4), Err(EncodeError::InvalidFrame));
+
+        // a length which does not fit the blocks, or data in the padding
+        let mut longer = blocks.clone();
+        longer[0] = pallas::Base::from(9);
+        assert_eq!(unframe_message(&longer, 4), Err(EncodeError::InvalidFrame));
+        let mut shorter = blocks.clone();
+        shorter[0] = pallas::Base::from(4);
+        assert_eq!(unframe_message(&shorter, 4), Err(EncodeError::InvalidFrame));
+        let mut padded = blocks;
+        padded[2] = pallas::Base::from(0x0105);
+        assert_eq!(unframe_message(&padded, 4), Err(EncodeError::InvalidFrame));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_frame_round_trip(
+            data in vec(any::<u8>(), 0..200),
+            block_size in 1..=MAX_BLOCK_BYTES,
+        ) {
+            let blocks = frame_message(&data, block_size).unwrap();
+            prop_assert_eq!(blocks.len(), 1 + data.len().div_ceil(block_size));
+            prop_assert_eq!(unframe_message(&blocks, block_size).unwrap(), data);
+        }
+    }
+}
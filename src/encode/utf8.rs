@@ -1,12 +1,12 @@
 /// Data type transformation functions
-pub fn convert_u8_array_to_u64_array(input: [u8; 32]) -> [u64; 4] {
-    let mut output = [0u64; 4];
-    for (i, chunk) in input.chunks_exact(8).enumerate() {
-        let bytes: [u8; 8] = chunk.try_into().expect("slice with incorrect length");
-        output[i] = u64::from_le_bytes(bytes); // Use from_le_bytes if the input is little-endian
-    }
-    output
-}
+use crate::encode::bytes::{decode_bytes, split_bytes_into_blocks};
+use alloc::{string::String, vec::Vec};
+use pasta_curves::pallas;
+
+pub use crate::encode::bytes::{
+    convert_u64_array_to_u8_array, convert_u8_array_to_u64_array, validate_block_size,
+    EncodeError, MAX_BLOCK_BYTES,
+};
 
 pub fn convert_string_to_u8_array(str: &str) -> [u8; 32] {
     let message_bytes = str.as_bytes();
@@ -35,11 +35,100 @@ pub fn convert_u8_array_to_string(arr: [u8; 32]) -> String {
     String::from_utf8(bytes).expect("Invalid UTF-8")
 }
 
-pub(crate) fn split_message_into_blocks(message: &str, block_size: usize) -> Vec<String> {
-    message
-        .chars() // Work with chars to respect UTF-8 character boundaries
-        .collect::<Vec<char>>() // Collect chars into a Vec to chunk by char count
-        .chunks(block_size) // Split into chunks of `block_size` chars
-        .map(|chunk| chunk.iter().collect::<String>()) // Collect each chunk of chars into a String
-        .collect()
+/// Recover a message split by `split_message_into_blocks` from its blocks, in order.
+pub fn decode_message_blocks(
+    blocks: &[pallas::Base],
+    block_size: usize,
+) -> Result<String, EncodeError> {
+    // the bytes are joined before UTF-8 decoding, so a character may span two blocks
+    String::from_utf8(decode_bytes(blocks, block_size)?).map_err(|_| EncodeError::InvalidUtf8)
+}
+
+/// Split a message into padded blocks of exactly `block_size` bytes, see `split_bytes_into_blocks`.
+/// A multibyte UTF-8 character may span two blocks; `decode_message_blocks` joins
+/// the blocks before UTF-8 decoding, so the message is recovered exactly.
+/// An empty message gives a single block of padding, never zero blocks, and whitespace is
+/// kept as is, so `""` and whitespace-only messages also round-trip exactly.
+pub fn split_message_into_blocks(
+    message: &str,
+    block_size: usize,
+) -> Result<Vec<Vec<u8>>, EncodeError> {
+    split_bytes_into_blocks(message.as_bytes(), block_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn encode_blocks(blocks: &[Vec<u8>]) -> Vec<pallas::Base> {
+        blocks
+            .iter()
+            .map(|block| pallas::Base::from_raw(convert_u8_array_to_u64_array(block).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_and_decode_with_padding() {
+        // trailing whitespace and null bytes are preserved
+        let message = "trailing spaces and nulls  \0\0 ";
+        for block_size in 1..=MAX_BLOCK_BYTES {
+            let blocks = split_message_into_blocks(message, block_size).unwrap();
+            assert!(blocks.iter().all(|block| block.len() == block_size));
+            let decoded = decode_message_blocks(&encode_blocks(&blocks), block_size).unwrap();
+            assert_eq!(decoded, message);
+        }
+
+        // an empty message is a single block of padding
+        let blocks = split_message_into_blocks("", 31).unwrap();
+        assert_eq!(blocks, vec![vec![31u8; 31]]);
+        assert_eq!(decode_message_blocks(&encode_blocks(&blocks), 31).unwrap(), "");
+    }
+
+    #[test]
+    fn test_empty_and_whitespace_messages() {
+        for message in ["", " ", "\n\n\n"] {
+            for block_size in 1..=MAX_BLOCK_BYTES {
+                let blocks = split_message_into_blocks(message, block_size).unwrap();
+                assert_eq!(blocks.len(), message.len() / block_size + 1);
+                let decoded = decode_message_blocks(&encode_blocks(&blocks), block_size).unwrap();
+                assert_eq!(decoded, message);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_block_size() {
+        for block_size in [0, 32] {
+            let expected = EncodeError::InvalidBlockSize {
+                block_size,
+                max: MAX_BLOCK_BYTES,
+            };
+            assert_eq!(
+                split_message_into_blocks("message", block_size),
+                Err(expected.clone())
+            );
+            assert_eq!(decode_message_blocks(&[], block_size), Err(expected));
+        }
+    }
+
+    #[test]
+    fn test_decode_invalid_padding() {
+        // no block at all
+        assert_eq!(decode_message_blocks(&[], 31), Err(EncodeError::InvalidPadding));
+        // a block without padding
+        let blocks = encode_blocks(&[vec![b'a'; 31]]);
+        assert_eq!(decode_message_blocks(&blocks, 31), Err(EncodeError::InvalidPadding));
+    }
+
+    proptest! {
+        // multibyte characters may span two blocks, and are joined before UTF-8 decoding
+        #[test]
+        fn prop_message_round_trip(message in any::<String>(), block_size in 1..=MAX_BLOCK_BYTES) {
+            let blocks = split_message_into_blocks(&message, block_size).unwrap();
+            prop_assert!(blocks.iter().all(|block| block.len() == block_size));
+            let decoded = decode_message_blocks(&encode_blocks(&blocks), block_size).unwrap();
+            prop_assert_eq!(decoded, message);
+        }
+    }
 }
@@ -0,0 +1,261 @@
+//! Byte-oriented API for command-line tools: keygen, encrypt a file, prove, verify and decrypt.
+//! Every input and output is a byte string, no halo2 type is exposed, so that a binary only
+//! reads and writes files.
+//!
+//! Byte formats:
+//! - keypair: 64 bytes, `private_key || public_key`, see `ElGamalKeypair::to_bytes`.
+//! - public key: 32 bytes, the compressed pallas point.
+//! - encryption: one 128-byte record per block, `data_in_transmit || r_enc`, where
+//!   `data_in_transmit` is the 96-byte `DataInTransmit::to_bytes` and `r_enc` the 32-byte
+//!   little-endian encryption randomness. The data is framed by `framing::frame_message` in
+//!   blocks of [`BLOCK_SIZE`] bytes, the first block is the length of the data.
+//!   r_enc decrypts the data, so the encryption must be kept by the encryptor and only be
+//!   given to [`prove`].
+//! - ciphertext: the number n of blocks as a u32 little-endian, the n 96-byte
+//!   `DataInTransmit` of the blocks, and the n proofs of the blocks, each one as its length
+//!   as a u32 little-endian followed by the proof created by `prove_encryption`.
+//!
+//! The params of K = [`K`] and the keys of the circuit of task1 are generated by each call
+//! to [`prove`] and [`verify`], as they are a deterministic function of K and the circuit.
+
+use crate::circuits::verifiable_encryption::{empty_circuit, VeEncInstance};
+use crate::circuits::{keygen_vk, verify_encryption};
+use crate::elgamal::elgamal::{ElGamalKeypair, KeypairError};
+use crate::elgamal::extended_elgamal::{
+    extended_elgamal_encrypt, has_default_r_encode, DataInTransmit, DecryptError, EncryptError,
+    DATA_IN_TRANSMIT_BYTES,
+};
+use crate::encode::bytes::EncodeError;
+use crate::encode::framing::frame_message;
+use crate::message::{decrypt_framed, MessageError};
+use ff::PrimeField;
+use group::{Group, GroupEncoding};
+use halo2_proofs::poly::commitment::Params;
+use pasta_curves::{pallas, vesta};
+use std::fmt;
+#[cfg(feature = "prover")]
+use {
+    crate::circuits::prove_encryption,
+    crate::circuits::verifiable_encryption::circuit_from_witness,
+    crate::elgamal::extended_elgamal::Witness,
+    crate::encode::encode::decode,
+};
+
+pub use crate::circuits::verifiable_encryption::K;
+
+/// Number of bytes of data in each block
+pub const BLOCK_SIZE: usize = 31;
+
+/// Length of a block of an encryption
+pub const ENCRYPTION_RECORD_BYTES: usize = DATA_IN_TRANSMIT_BYTES + 32;
+
+// Errors returned by the facade
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidKeypair(KeypairError), // not a keypair returned by keygen
+    InvalidPublicKey,             // not a compressed point, or the identity
+    InvalidEncryption,            // not an encryption returned by encrypt_file for the public key
+    InvalidCiphertext,            // not a ciphertext returned by prove
+    Encode(EncodeError),          // the data cannot be framed, or the decrypted blocks unframed
+    Encrypt(EncryptError),        // a block cannot be encrypted
+    Decrypt(DecryptError),        // a block cannot be decrypted
+    Proof(String),                // a proof cannot be created, or does not verify
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidKeypair(error) => write!(f, "invalid keypair: {:?}", error),
+            Error::InvalidPublicKey => write!(f, "invalid public key"),
+            Error::InvalidEncryption => write!(f, "invalid encryption"),
+            Error::InvalidCiphertext => write!(f, "invalid ciphertext"),
+            Error::Encode(error) => write!(f, "encoding failed: {}", error),
+            Error::Encrypt(error) => write!(f, "encryption failed: {}", error),
+            Error::Decrypt(error) => write!(f, "decryption failed: {}", error),
+            Error::Proof(error) => write!(f, "proof failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<MessageError> for Error {
+    fn from(error: MessageError) -> Self {
+        match error {
+            MessageError::Encode(error) => Error::Encode(error),
+            MessageError::Encrypt(error) => Error::Encrypt(error),
+            MessageError::Decrypt(error) => Error::Decrypt(error),
+            error => Error::Proof(error.to_string()),
+        }
+    }
+}
+
+fn proof_error(error: impl fmt::Display) -> Error {
+    Error::Proof(error.to_string())
+}
+
+fn read_public_key(public_key: &[u8]) -> Result<pallas::Point, Error> {
+    let bytes: [u8; 32] = public_key.try_into().map_err(|_| Error::InvalidPublicKey)?;
+    let public_key: Option<pallas::Point> = pallas::Point::from_bytes(&bytes).into();
+    match public_key {
+        Some(public_key) if !bool::from(public_key.is_identity()) => Ok(public_key),
+        _ => Err(Error::InvalidPublicKey),
+    }
+}
+
+// A ciphertext: the blocks and their proofs
+fn write_ciphertext(blocks: &[DataInTransmit], proofs: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = (blocks.len() as u32).to_le_bytes().to_vec();
+    for block in blocks {
+        bytes.extend(block.to_bytes());
+    }
+    for proof in proofs {
+        bytes.extend((proof.len() as u32).to_le_bytes());
+        bytes.extend(proof);
+    }
+    bytes
+}
+
+fn read_u32(bytes: &mut &[u8]) -> Result<usize, Error> {
+    let (value, rest) = bytes.split_first_chunk::<4>().ok_or(Error::InvalidCiphertext)?;
+    *bytes = rest;
+    Ok(u32::from_le_bytes(*value) as usize)
+}
+
+fn read_ciphertext(mut bytes: &[u8]) -> Result<(Vec<DataInTransmit>, Vec<&[u8]>), Error> {
+    // the framed data has at least the length block
+    let count = read_u32(&mut bytes)?;
+    if count == 0 {
+        return Err(Error::InvalidCiphertext);
+    }
+    let blocks_length = count
+        .checked_mul(DATA_IN_TRANSMIT_BYTES)
+        .filter(|length| *length <= bytes.len())
+        .ok_or(Error::InvalidCiphertext)?;
+    let (blocks, mut bytes) = bytes.split_at(blocks_length);
+    let blocks = blocks
+        .chunks_exact(DATA_IN_TRANSMIT_BYTES)
+        .map(|block| DataInTransmit::from_bytes(block).map_err(|_| Error::InvalidCiphertext))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut proofs = Vec::with_capacity(count);
+    for _ in 0..count {
+        let length = read_u32(&mut bytes)?;
+        if length > bytes.len() {
+            return Err(Error::InvalidCiphertext);
+        }
+        let (proof, rest) = bytes.split_at(length);
+        proofs.push(proof);
+        bytes = rest;
+    }
+    if !bytes.is_empty() {
+        return Err(Error::InvalidCiphertext);
+    }
+    Ok((blocks, proofs))
+}
+
+/// Generate a keypair, returned as 64 bytes `private_key || public_key`.
+pub fn keygen() -> Vec<u8> {
+    ElGamalKeypair::new().to_bytes()
+}
+
+/// The 32-byte public key of a 64-byte keypair returned by [`keygen`].
+pub fn public_key(keypair: &[u8]) -> Result<Vec<u8>, Error> {
+    let keypair = ElGamalKeypair::from_bytes(keypair).map_err(Error::InvalidKeypair)?;
+    Ok(keypair.public_key.to_bytes().to_vec())
+}
+
+/// Encrypt the data to a 32-byte public key. Returns the encryption, one 128-byte record per
+/// block, which contains the secret randomness of the blocks, see [`prove`].
+pub fn encrypt_file(public_key: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    let public_key = read_public_key(public_key)?;
+
+    let mut bytes = Vec::new();
+    for m in frame_message(data, BLOCK_SIZE).map_err(Error::Encode)? {
+        let (data_in_transmit, witness) =
+            extended_elgamal_encrypt(&public_key, m).map_err(Error::Encrypt)?;
+        bytes.extend(data_in_transmit.to_bytes());
+        bytes.extend(witness.r_enc.to_repr());
+    }
+    Ok(bytes)
+}
+
+/// Prove the encryption returned by [`encrypt_file`] for the same 32-byte public key.
+/// Returns the ciphertext: the blocks without their randomness, and one proof per block.
+#[cfg(feature = "prover")]
+pub fn prove(public_key: &[u8], encryption: &[u8]) -> Result<Vec<u8>, Error> {
+    let public_key = read_public_key(public_key)?;
+    if encryption.is_empty() || encryption.len() % ENCRYPTION_RECORD_BYTES != 0 {
+        return Err(Error::InvalidEncryption);
+    }
+
+    let params = Params::new(K);
+    let (_, proving_key) =
+        crate::circuits::keygen(&params, &empty_circuit(public_key)).map_err(proof_error)?;
+
+    let mut blocks = vec![];
+    let mut proofs = vec![];
+    for record in encryption.chunks_exact(ENCRYPTION_RECORD_BYTES) {
+        let (block, r_enc) = record.split_at(DATA_IN_TRANSMIT_BYTES);
+        let data_in_transmit =
+            DataInTransmit::from_bytes(block).map_err(|_| Error::InvalidEncryption)?;
+        let r_enc: Option<pallas::Scalar> =
+            pallas::Scalar::from_repr(r_enc.try_into().unwrap()).into();
+        let r_enc = r_enc.ok_or(Error::InvalidEncryption)?;
+
+        // the witness of the encryptor, from its randomness, and the r_encode of encrypt_file
+        if data_in_transmit.c1() != pallas::Point::generator() * r_enc
+            || !has_default_r_encode(&data_in_transmit, &public_key)
+        {
+            return Err(Error::InvalidEncryption);
+        }
+        let p_m = data_in_transmit.c2() - public_key * r_enc;
+        let witness = Witness {
+            m: decode(p_m, data_in_transmit.r_encode()),
+            p_m,
+            r_enc,
+        };
+
+        let circuit = circuit_from_witness(data_in_transmit.clone(), public_key, &witness)
+            .map_err(proof_error)?;
+        let instance = VeEncInstance::new(data_in_transmit.clone(), public_key)
+            .to_halo2_instance()
+            .map_err(proof_error)?;
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        proofs.push(
+            prove_encryption(&params, &proving_key, circuit, &instance).map_err(proof_error)?,
+        );
+        blocks.push(data_in_transmit);
+    }
+    Ok(write_ciphertext(&blocks, &proofs))
+}
+
+/// Verify the proofs of a ciphertext returned by [`prove`] for the 32-byte public key.
+/// Fails with `Error::InvalidCiphertext` if the r_encode of a block is not derived from the
+/// public key and its c1 as by [`encrypt_file`], see `has_default_r_encode`.
+pub fn verify(public_key: &[u8], ciphertext: &[u8]) -> Result<(), Error> {
+    let public_key = read_public_key(public_key)?;
+    let (blocks, proofs) = read_ciphertext(ciphertext)?;
+
+    let params = Params::<vesta::Affine>::new(K);
+    let vk = keygen_vk(&params, &empty_circuit(public_key)).map_err(proof_error)?;
+    for (block, proof) in blocks.into_iter().zip(proofs) {
+        if !has_default_r_encode(&block, &public_key) {
+            return Err(Error::InvalidCiphertext);
+        }
+        let instance = VeEncInstance::new(block, public_key)
+            .to_halo2_instance()
+            .map_err(proof_error)?;
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        verify_encryption(&params, &vk, &instance, proof).map_err(proof_error)?;
+    }
+    Ok(())
+}
+
+/// Decrypt a ciphertext returned by [`prove`] with a 64-byte keypair, and return the data.
+/// The proofs are not checked, see [`verify`].
+pub fn decrypt(keypair: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    let keypair = ElGamalKeypair::from_bytes(keypair).map_err(Error::InvalidKeypair)?;
+    let (blocks, _) = read_ciphertext(ciphertext)?;
+    Ok(decrypt_framed(&blocks, BLOCK_SIZE, &keypair.private_key)?)
+}
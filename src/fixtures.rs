@@ -0,0 +1,76 @@
+/// Fixtures of the tests: byte strings created by the prover and committed under
+/// `tests/fixtures`, so that the tests built without the `prover` feature verify real proofs,
+/// and that the test vectors detect any change of the proofs.
+///
+/// A fixture is a text file of `name hex` lines, written by the test vectors when the
+/// environment variable `VE_UPDATE_FIXTURES` is set:
+/// `VE_UPDATE_FIXTURES=1 cargo test --lib test_vectors`
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+#[cfg(feature = "prover")]
+use std::{env, fmt::Write};
+
+/// Entries of a fixture, by name
+pub(crate) type Fixture = BTreeMap<String, Vec<u8>>;
+
+const UPDATE_VAR: &str = "VE_UPDATE_FIXTURES";
+
+fn path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(format!("{}.txt", name))
+}
+
+fn decode_hex(hex: &str) -> Vec<u8> {
+    assert!(hex.len() % 2 == 0, "odd number of hex digits");
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("hex digits"))
+        .collect()
+}
+
+/// Whether the fixtures are written instead of checked
+#[cfg(feature = "prover")]
+pub(crate) fn update() -> bool {
+    env::var_os(UPDATE_VAR).is_some()
+}
+
+/// Read the fixture `name`.
+/// Panics if it is missing or malformed, with the command which writes it.
+pub(crate) fn read(name: &str) -> Fixture {
+    let path = path(name);
+    let text = fs::read_to_string(&path).unwrap_or_else(|error| {
+        panic!(
+            "fixture {}: {}, write it with `{}=1 cargo test --lib test_vectors`",
+            path.display(),
+            error,
+            UPDATE_VAR
+        )
+    });
+    text.lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (key, value) = line.split_once(' ').expect("a `name hex` line");
+            (key.to_string(), decode_hex(value))
+        })
+        .collect()
+}
+
+/// Write the fixture `name`, replacing the previous one.
+#[cfg(feature = "prover")]
+pub(crate) fn write(name: &str, fixture: &Fixture) {
+    let mut text = String::new();
+    for (key, value) in fixture {
+        write!(text, "{} ", key).unwrap();
+        for byte in value {
+            write!(text, "{:02x}", byte).unwrap();
+        }
+        text.push('\n');
+    }
+    let path = path(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, text).unwrap();
+}
@@ -0,0 +1,231 @@
+//! Gadgets shared by the verifiable encryption circuits.
+use crate::constants::fixed_bases::VerifiableEncryptionFixedBases;
+use crate::constants::sinsemilla::{
+    VerifiableEncryptionCommitDomain, VerifiableEncryptionHashDomain,
+};
+use halo2_gadgets::ecc::chip::EccChip;
+use halo2_gadgets::ecc::{Point, ScalarFixed};
+use halo2_gadgets::sinsemilla::chip::SinsemillaChip;
+use halo2_gadgets::sinsemilla::{CommitDomain, Message};
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::plonk::{Column, Error, Instance};
+use pasta_curves::pallas;
+
+/// Sinsemilla chip of the verifiable encryption commitment
+pub type VeSinsemillaChip = SinsemillaChip<
+    VerifiableEncryptionHashDomain,
+    VerifiableEncryptionCommitDomain,
+    VerifiableEncryptionFixedBases,
+>;
+
+/// ECC chip of the verifiable encryption circuits
+pub type VeEccChip = EccChip<VerifiableEncryptionFixedBases>;
+
+/// Sinsemilla message of the verifiable encryption commitment
+pub type VeMessage = Message<
+    pallas::Affine,
+    VeSinsemillaChip,
+    { halo2_gadgets::sinsemilla::primitives::K },
+    { halo2_gadgets::sinsemilla::primitives::C },
+>;
+
+/// Commit to a message given as little-endian bits, with randomness r:
+/// the result is [r]R + SinsemillaHash(Q, message_bits), with R and Q given by the domain.
+/// The number of bits must be a multiple of 10, the size of a Sinsemilla word.
+pub fn commit_message(
+    mut layouter: impl Layouter<pallas::Base>,
+    chip: VeSinsemillaChip,
+    ecc_chip: VeEccChip,
+    domain: &VerifiableEncryptionCommitDomain,
+    message_bits: Vec<Value<bool>>,
+    r: Value<pallas::Scalar>,
+) -> Result<Point<pallas::Affine, VeEccChip>, Error> {
+    let message = Message::from_bitstring(
+        chip.clone(),
+        layouter.namespace(|| "message bits"),
+        message_bits,
+    )?;
+    commit(layouter, chip, ecc_chip, domain, message, r)
+}
+
+/// Commit to a message already split into pieces, with randomness r.
+pub(crate) fn commit(
+    mut layouter: impl Layouter<pallas::Base>,
+    chip: VeSinsemillaChip,
+    ecc_chip: VeEccChip,
+    domain: &VerifiableEncryptionCommitDomain,
+    message: VeMessage,
+    r: Value<pallas::Scalar>,
+) -> Result<Point<pallas::Affine, VeEccChip>, Error> {
+    let r = ScalarFixed::new(ecc_chip.clone(), layouter.namespace(|| "r"), r)?;
+    let domain = CommitDomain::new(chip, ecc_chip, domain);
+    let (point, _) = domain.commit(layouter.namespace(|| "commit"), message, r)?;
+    Ok(point)
+}
+
+/// Constrain the coordinates of a point to equal the public inputs at rows x_row and y_row
+/// of the instance column. A non-identity point is converted with `Point::from`.
+pub fn constrain_point_instance(
+    mut layouter: impl Layouter<pallas::Base>,
+    point: &Point<pallas::Affine, VeEccChip>,
+    instance: Column<Instance>,
+    x_row: usize,
+    y_row: usize,
+) -> Result<(), Error> {
+    layouter.constrain_instance(point.inner().x().cell(), instance, x_row)?;
+    layouter.constrain_instance(point.inner().y().cell(), instance, y_row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{commit_message, constrain_point_instance, VeSinsemillaChip};
+    use crate::circuits::verifiable_encryption::{VeConfig, VeEncCircuit};
+    use crate::circuits::verifiable_encryption_with_commitment::{VeCommitCircuit, VeCommitConfig};
+    use crate::constants::sinsemilla::{
+        VerifiableEncryptionCommitDomain, VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION,
+    };
+    use ff::Field;
+    use group::{Curve, Group};
+    use halo2_gadgets::ecc::chip::EccChip;
+    use halo2_gadgets::ecc::{NonIdentityPoint, Point};
+    use halo2_gadgets::sinsemilla::primitives::CommitDomain;
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+    use pasta_curves::arithmetic::CurveAffine;
+    use pasta_curves::pallas;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    #[derive(Default)]
+    struct CommitCircuit {
+        bits: Vec<Value<bool>>,
+        r: Value<pallas::Scalar>,
+    }
+
+    impl Circuit<pallas::Base> for CommitCircuit {
+        type Config = VeCommitConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                bits: vec![Value::unknown(); self.bits.len()],
+                r: Value::unknown(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            VeCommitCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            VeSinsemillaChip::load(config.sinsemilla_config.clone(), &mut layouter)?;
+
+            let cm = commit_message(
+                layouter.namespace(|| "commit message"),
+                VeSinsemillaChip::construct(config.sinsemilla_config.clone()),
+                EccChip::construct(config.ve_config.ecc_config.clone()),
+                &VerifiableEncryptionCommitDomain::default(),
+                self.bits.clone(),
+                self.r,
+            )?;
+
+            layouter.constrain_instance(cm.inner().x().cell(), config.ve_config.instance, 0)?;
+            layouter.constrain_instance(cm.inner().y().cell(), config.ve_config.instance, 1)
+        }
+    }
+
+    #[test]
+    fn commitment_matches_primitive() {
+        let mut rng = OsRng;
+        let bits: Vec<bool> = (0..40).map(|_| rng.gen()).collect();
+        let r = pallas::Scalar::random(rng);
+
+        let expected = CommitDomain::new(VERIFIABLE_ENCRYPTION_COMMITMENT_PERSONALIZATION)
+            .commit(bits.iter().copied(), &r)
+            .unwrap()
+            .to_affine();
+        let expected = expected.coordinates().unwrap();
+
+        let circuit = CommitCircuit {
+            bits: bits.iter().map(|bit| Value::known(*bit)).collect(),
+            r: Value::known(r),
+        };
+        let instance = vec![*expected.x(), *expected.y()];
+        let prover = MockProver::run(11, &circuit, vec![instance.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // another point is rejected
+        let prover = MockProver::run(11, &circuit, vec![vec![instance[1], instance[0]]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // Witness a point and constrain it to the public inputs at rows (x_row, y_row)
+    struct PointInstanceCircuit {
+        point: pallas::Affine,
+        x_row: usize,
+        y_row: usize,
+    }
+
+    impl Circuit<pallas::Base> for PointInstanceCircuit {
+        type Config = VeConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self { ..*self }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            VeEncCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            config.ecc_config.lookup_config.load(&mut layouter)?;
+            let ecc_chip = EccChip::construct(config.ecc_config);
+            let point = NonIdentityPoint::new(
+                ecc_chip,
+                layouter.namespace(|| "load point"),
+                Value::known(self.point),
+            )?;
+            constrain_point_instance(
+                layouter.namespace(|| "constrain point"),
+                &Point::from(point),
+                config.instance,
+                self.x_row,
+                self.y_row,
+            )
+        }
+    }
+
+    #[test]
+    fn point_instance_coordinates() {
+        let point = pallas::Point::random(OsRng).to_affine();
+        let coordinates = point.coordinates().unwrap();
+        let instance = vec![vec![*coordinates.x(), *coordinates.y()]];
+
+        let circuit = PointInstanceCircuit {
+            point,
+            x_row: 0,
+            y_row: 1,
+        };
+        let prover = MockProver::run(11, &circuit, instance.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // swapped rows constrain x to the public y, and y to the public x
+        let circuit = PointInstanceCircuit {
+            point,
+            x_row: 1,
+            y_row: 0,
+        };
+        let prover = MockProver::run(11, &circuit, instance).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -1,5 +1,27 @@
+// The test harness needs std: the no_std build is checked by tests/build_targets.rs.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod add_sub_mul;
 pub mod encode;
 pub mod elgamal;
+#[cfg(feature = "std")]
 pub mod constants;
-mod circuits;
+#[cfg(feature = "std")]
+pub mod circuits;
+#[cfg(feature = "std")]
+pub mod facade;
+#[cfg(feature = "std")]
+pub mod gadgets;
+#[cfg(feature = "std")]
+pub mod message;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(any(feature = "hex", feature = "base64"))]
+pub mod text;
+#[cfg(all(test, feature = "std"))]
+mod fixtures;
+#[cfg(all(test, feature = "prover"))]
+mod test_vectors;
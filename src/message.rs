@@ -0,0 +1,598 @@
+//! Verifiable encryption of a whole message.
+//! The message is split into padded blocks, each block is encrypted and proven with
+//! the circuit of task1, and the blocks are decrypted and unpadded in order.
+use crate::circuits::verifiable_encryption::VeEncInstance;
+use crate::circuits::{verify_encryption, ConversionError};
+use crate::elgamal::extended_elgamal::{
+    extended_elgamal_decrypt, has_default_r_encode, DataInTransmit, DecryptError, EncryptError,
+};
+use crate::encode::bytes::{validate_block_size, EncodeError};
+use crate::encode::framing::unframe_message;
+use crate::encode::utf8::decode_message_blocks;
+use halo2_proofs::plonk::{self, VerifyingKey};
+use halo2_proofs::poly::commitment::Params;
+use pasta_curves::{pallas, vesta};
+use std::fmt;
+#[cfg(feature = "prover")]
+use {
+    crate::circuits::verifiable_encryption::{circuit_from_witness, VeEncCircuit},
+    crate::circuits::{keygen, prove_encryption, recommended_k},
+    crate::elgamal::extended_elgamal::extended_elgamal_encrypt,
+    crate::encode::utf8::{convert_u8_array_to_u64_array, split_message_into_blocks},
+    halo2_proofs::plonk::ProvingKey,
+};
+
+// Errors returned by the message API
+#[derive(Debug)]
+pub enum MessageError {
+    Encode(EncodeError),   // the message cannot be split into blocks, or decoded
+    Encrypt(EncryptError), // a block cannot be encrypted, e.g. the public key is invalid
+    Decrypt(DecryptError), // a block cannot be decrypted
+    Proof(plonk::Error),   // a proof cannot be created or does not verify
+    InvalidProofCount { blocks: usize, proofs: usize }, // there is not one proof per block
+    Conversion(ConversionError), // the randomness of a block cannot be witnessed by the circuit
+    TooManyBlocks { blocks: usize, max_blocks: usize }, // more blocks than the limit
+    InvalidREncode { block: usize }, // the r_encode of a block is not derived by default
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::Encode(error) => write!(f, "encoding failed: {}", error),
+            MessageError::Encrypt(error) => write!(f, "encryption failed: {}", error),
+            MessageError::Decrypt(error) => write!(f, "decryption failed: {}", error),
+            MessageError::Proof(error) => write!(f, "proof failed: {}", error),
+            MessageError::InvalidProofCount { blocks, proofs } => {
+                write!(f, "the message has {} blocks but {} proofs", blocks, proofs)
+            }
+            MessageError::Conversion(error) => write!(f, "witness conversion failed: {}", error),
+            MessageError::TooManyBlocks { blocks, max_blocks } => write!(
+                f,
+                "the message has {} blocks, more than the limit of {} blocks",
+                blocks, max_blocks
+            ),
+            MessageError::InvalidREncode { block } => write!(
+                f,
+                "the r_encode of block {} is not derived from its public key and c1",
+                block
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+impl From<EncodeError> for MessageError {
+    fn from(error: EncodeError) -> Self {
+        MessageError::Encode(error)
+    }
+}
+
+impl From<ConversionError> for MessageError {
+    fn from(error: ConversionError) -> Self {
+        MessageError::Conversion(error)
+    }
+}
+
+impl From<EncryptError> for MessageError {
+    fn from(error: EncryptError) -> Self {
+        MessageError::Encrypt(error)
+    }
+}
+
+impl From<DecryptError> for MessageError {
+    fn from(error: DecryptError) -> Self {
+        MessageError::Decrypt(error)
+    }
+}
+
+impl From<plonk::Error> for MessageError {
+    fn from(error: plonk::Error) -> Self {
+        MessageError::Proof(error)
+    }
+}
+
+/// The encrypted blocks of a message, in order, with one proof per block.
+#[derive(Clone, Debug)]
+pub struct EncryptedMessage {
+    pub block_size: usize,
+    pub elgamal_public_key: pallas::Point,
+    pub blocks: Vec<DataInTransmit>,
+    pub proofs: Vec<Vec<u8>>,
+}
+
+/// Split the message into blocks of block_size bytes, encrypt each block
+/// with the public key and prove each encryption.
+/// The params and the proving key are generated for [`recommended_k`]`(1)`, as each block has
+/// its own proof, so the caller does not choose K. The verifier creates the same params with
+/// `Params::new(recommended_k(1))`, see [`verify_message`].
+/// Use [`encrypt_and_prove_with_keys`] to share the keys between messages.
+///
+/// Fails with `MessageError::TooManyBlocks` if the message has more than
+/// [`DEFAULT_MAX_BLOCKS`] blocks, see [`encrypt_and_prove_with_max_blocks`].
+#[cfg(feature = "prover")]
+pub fn encrypt_and_prove(
+    message: &str,
+    elgamal_public_key: &pallas::Point,
+    block_size: usize,
+) -> Result<EncryptedMessage, MessageError> {
+    encrypt_and_prove_with_max_blocks(message, elgamal_public_key, block_size, DEFAULT_MAX_BLOCKS)
+}
+
+/// Default limit on the number of blocks of a message proven by [`encrypt_and_prove`]:
+/// 31 KiB of text with blocks of 31 bytes. Each block has its own proof, so the time of
+/// proving and the size of the encrypted message are linear in the number of blocks.
+pub const DEFAULT_MAX_BLOCKS: usize = 1024;
+
+/// Same as [`encrypt_and_prove`], with a limit of `max_blocks` blocks instead of
+/// [`DEFAULT_MAX_BLOCKS`], for services which accept messages of untrusted sizes.
+/// The number of blocks is checked before the keys are generated and before any block is
+/// encrypted or proven: fails with `MessageError::TooManyBlocks` if it is above the limit.
+#[cfg(feature = "prover")]
+pub fn encrypt_and_prove_with_max_blocks(
+    message: &str,
+    elgamal_public_key: &pallas::Point,
+    block_size: usize,
+    max_blocks: usize,
+) -> Result<EncryptedMessage, MessageError> {
+    check_block_count(message, block_size, max_blocks)?;
+
+    let params = Params::new(recommended_k(1));
+    let circuit = VeEncCircuit {
+        elgamal_public_key: *elgamal_public_key,
+        ..Default::default()
+    };
+    let (_, proving_key) = keygen(&params, &circuit)?;
+    encrypt_and_prove_with_keys(
+        &params,
+        &proving_key,
+        message,
+        elgamal_public_key,
+        block_size,
+        max_blocks,
+    )
+}
+
+// Fails with `MessageError::TooManyBlocks` if the message has more than max_blocks blocks
+#[cfg(feature = "prover")]
+fn check_block_count(
+    message: &str,
+    block_size: usize,
+    max_blocks: usize,
+) -> Result<(), MessageError> {
+    validate_block_size(block_size)?;
+    // the padding always adds a block, see split_bytes_into_blocks
+    let blocks = message.len() / block_size + 1;
+    if blocks > max_blocks {
+        return Err(MessageError::TooManyBlocks { blocks, max_blocks });
+    }
+    Ok(())
+}
+
+/// Same as [`encrypt_and_prove_with_max_blocks`], with the params and the proving key of the
+/// caller. The number of blocks is checked against `max_blocks`, e.g. [`DEFAULT_MAX_BLOCKS`],
+/// before any block is encrypted or proven.
+#[cfg(feature = "prover")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(block_size = block_size)))]
+pub fn encrypt_and_prove_with_keys(
+    params: &Params<vesta::Affine>,
+    proving_key: &ProvingKey<vesta::Affine>,
+    message: &str,
+    elgamal_public_key: &pallas::Point,
+    block_size: usize,
+    max_blocks: usize,
+) -> Result<EncryptedMessage, MessageError> {
+    check_block_count(message, block_size, max_blocks)?;
+
+    let mut blocks = vec![];
+    let mut proofs = vec![];
+    for (index, block) in split_message_into_blocks(message, block_size)?
+        .into_iter()
+        .enumerate()
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("block", index).entered();
+        #[cfg(not(feature = "tracing"))]
+        let _ = index;
+        let m = pallas::Base::from_raw(convert_u8_array_to_u64_array(&block)?);
+        let (data_in_transmit, witness) = extended_elgamal_encrypt(elgamal_public_key, m)?;
+        let circuit =
+            circuit_from_witness(data_in_transmit.clone(), *elgamal_public_key, &witness)?;
+
+        let instance = VeEncInstance::new(data_in_transmit.clone(), *elgamal_public_key)
+            .to_halo2_instance()?;
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        proofs.push(prove_encryption(params, proving_key, circuit, &instance)?);
+        blocks.push(data_in_transmit);
+    }
+
+    Ok(EncryptedMessage {
+        block_size,
+        elgamal_public_key: *elgamal_public_key,
+        blocks,
+        proofs,
+    })
+}
+
+/// Verify the proof of every block of the message, and check that the r_encode of every block
+/// is derived from the public key and its c1, as by `encrypt_and_prove`, see
+/// `has_default_r_encode`. Fails with `MessageError::InvalidREncode` otherwise.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(blocks = encrypted_message.blocks.len()))
+)]
+pub fn verify_message(
+    params: &Params<vesta::Affine>,
+    vk: &VerifyingKey<vesta::Affine>,
+    encrypted_message: &EncryptedMessage,
+) -> Result<(), MessageError> {
+    if encrypted_message.blocks.len() != encrypted_message.proofs.len() {
+        return Err(MessageError::InvalidProofCount {
+            blocks: encrypted_message.blocks.len(),
+            proofs: encrypted_message.proofs.len(),
+        });
+    }
+
+    for (index, (block, proof)) in encrypted_message
+        .blocks
+        .iter()
+        .zip(encrypted_message.proofs.iter())
+        .enumerate()
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("block", index).entered();
+        if !has_default_r_encode(block, &encrypted_message.elgamal_public_key) {
+            return Err(MessageError::InvalidREncode { block: index });
+        }
+        let instance = VeEncInstance::new(block.clone(), encrypted_message.elgamal_public_key)
+            .to_halo2_instance()?;
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        verify_encryption(params, vk, &instance, proof)?;
+    }
+    Ok(())
+}
+
+/// Decrypt every block of the message with the private key, and remove the padding.
+/// The proofs are not checked, see `verify_message`.
+pub fn decrypt_message(
+    encrypted_message: &EncryptedMessage,
+    private_key: &pallas::Scalar,
+) -> Result<String, MessageError> {
+    try_decrypt_to_string(
+        private_key,
+        &encrypted_message.blocks,
+        encrypted_message.block_size,
+    )
+}
+
+/// Decrypt the blocks of a message split by `split_message_into_blocks`, in order, and decode
+/// the message, e.g. to check a received ciphertext before verifying its proofs.
+/// Fails with `MessageError::Encode` if the blocks are not a padded UTF-8 message.
+pub fn try_decrypt_to_string(
+    private_key: &pallas::Scalar,
+    blocks: &[DataInTransmit],
+    block_size: usize,
+) -> Result<String, MessageError> {
+    let blocks = decrypt_blocks(blocks, private_key)?;
+    Ok(decode_message_blocks(&blocks, block_size)?)
+}
+
+fn decrypt_blocks(
+    blocks: &[DataInTransmit],
+    private_key: &pallas::Scalar,
+) -> Result<Vec<pallas::Base>, MessageError> {
+    Ok(blocks
+        .iter()
+        .map(|block| extended_elgamal_decrypt(private_key, block.clone()))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Decrypt the blocks of data framed by `framing::frame_message`, in order, and trim the data
+/// to the length given by the first block. The proofs of the blocks are not checked.
+pub fn decrypt_framed(
+    blocks: &[DataInTransmit],
+    block_size: usize,
+    private_key: &pallas::Scalar,
+) -> Result<Vec<u8>, MessageError> {
+    let blocks = decrypt_blocks(blocks, private_key)?;
+    Ok(unframe_message(&blocks, block_size)?)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::{
+        decrypt_framed, decrypt_message, encrypt_and_prove, encrypt_and_prove_with_keys,
+        try_decrypt_to_string, verify_message, MessageError, DEFAULT_MAX_BLOCKS,
+    };
+    use crate::circuits::verifiable_encryption::{VeEncCircuit, K};
+    use crate::circuits::{keygen, keygen_vk, recommended_k};
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use halo2_proofs::poly::commitment::Params;
+
+    #[test]
+    fn round_trip() {
+        let keypair = ElGamalKeypair::new();
+        let params = Params::new(K);
+        let circuit = VeEncCircuit {
+            elgamal_public_key: keypair.public_key,
+            ..Default::default()
+        };
+        let (vk, pk) = keygen(&params, &circuit).unwrap();
+
+        // 71 bytes and 22 bytes of padding
+        let message = "A message which does not fit in a block, nor in two blocks of 31 bytes.";
+        let encrypted = encrypt_and_prove_with_keys(
+            &params,
+            &pk,
+            message,
+            &keypair.public_key,
+            31,
+            DEFAULT_MAX_BLOCKS,
+        )
+        .unwrap();
+        assert_eq!(encrypted.blocks.len(), 3);
+        assert_eq!(encrypted.proofs.len(), 3);
+
+        assert!(verify_message(&params, &vk, &encrypted).is_ok());
+        assert_eq!(
+            decrypt_message(&encrypted, &keypair.private_key).unwrap(),
+            message
+        );
+
+        // blocks in another order do not verify
+        let mut swapped = encrypted.clone();
+        swapped.blocks.swap(0, 1);
+        assert!(verify_message(&params, &vk, &swapped).is_err());
+    }
+
+    #[test]
+    fn empty_and_whitespace_messages() {
+        let keypair = ElGamalKeypair::new();
+        let params = Params::new(K);
+        let circuit = VeEncCircuit {
+            elgamal_public_key: keypair.public_key,
+            ..Default::default()
+        };
+        let (vk, pk) = keygen(&params, &circuit).unwrap();
+
+        // each message is a single block, which decrypts to the message exactly
+        for message in ["", " ", "\n\n\n"] {
+            let encrypted = encrypt_and_prove_with_keys(
+                &params,
+                &pk,
+                message,
+                &keypair.public_key,
+                31,
+                DEFAULT_MAX_BLOCKS,
+            )
+            .unwrap();
+            assert_eq!(encrypted.blocks.len(), 1);
+            assert!(verify_message(&params, &vk, &encrypted).is_ok());
+            assert_eq!(
+                decrypt_message(&encrypted, &keypair.private_key).unwrap(),
+                message
+            );
+        }
+    }
+
+    // Records the name and the fields of every span created while it is the default subscriber
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct SpanRecorder {
+        spans: std::sync::Arc<std::sync::Mutex<Vec<(String, Vec<(String, String)>)>>>,
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            struct Fields(Vec<(String, String)>);
+            impl tracing::field::Visit for Fields {
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    self.0
+                        .push((field.name().to_string(), format!("{:?}", value)));
+                }
+            }
+
+            let mut fields = Fields(vec![]);
+            span.record(&mut fields);
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((span.metadata().name().to_string(), fields.0));
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+        fn event(&self, _: &tracing::Event<'_>) {}
+        fn enter(&self, _: &tracing::span::Id) {}
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_spans() {
+        let recorder = SpanRecorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let keypair = ElGamalKeypair::new();
+            let params = Params::new(K);
+            let circuit = VeEncCircuit {
+                elgamal_public_key: keypair.public_key,
+                ..Default::default()
+            };
+            let (vk, pk) = keygen(&params, &circuit).unwrap();
+            let message = "A message of two blocks, with a span for each block.";
+            let encrypted = encrypt_and_prove_with_keys(
+                &params,
+                &pk,
+                message,
+                &keypair.public_key,
+                31,
+                DEFAULT_MAX_BLOCKS,
+            )
+            .unwrap();
+            assert!(verify_message(&params, &vk, &encrypted).is_ok());
+        });
+
+        let spans = recorder.spans.lock().unwrap();
+        let count = |name: &str, field: (&str, String)| {
+            let field = (field.0.to_string(), field.1);
+            spans
+                .iter()
+                .filter(|(span, fields)| span == name && fields.contains(&field))
+                .count()
+        };
+        assert_eq!(count("keygen", ("k", K.to_string())), 1);
+        assert_eq!(
+            count(
+                "encrypt_and_prove_with_keys",
+                ("block_size", "31".to_string())
+            ),
+            1
+        );
+        assert_eq!(count("verify_message", ("blocks", "2".to_string())), 1);
+        // one span per block when proving, and another when verifying
+        assert_eq!(count("block", ("index", "0".to_string())), 2);
+        assert_eq!(count("block", ("index", "1".to_string())), 2);
+        assert_eq!(count("prove", ("k", K.to_string())), 2);
+        assert_eq!(count("verify", ("k", K.to_string())), 2);
+        // the circuit is synthesized by the key generation and by each proof
+        assert!(count("synthesize", ("instance_offset", "0".to_string())) >= 3);
+    }
+
+    #[test]
+    fn too_many_blocks() {
+        use super::encrypt_and_prove_with_max_blocks;
+
+        // the messages are rejected before the keys are generated and any block is proven,
+        // which would take minutes for the second one
+        let keypair = ElGamalKeypair::new();
+        // 62 bytes and a block of padding
+        let message = "a".repeat(2 * 31);
+        match encrypt_and_prove_with_max_blocks(&message, &keypair.public_key, 31, 2) {
+            Err(MessageError::TooManyBlocks { blocks, max_blocks }) => {
+                assert_eq!((blocks, max_blocks), (3, 2));
+            }
+            _ => panic!("a message of 3 blocks is above the limit of 2 blocks"),
+        }
+
+        // with the keys of the caller, the limit is checked before any block is proven
+        let params = Params::new(K);
+        let circuit = VeEncCircuit {
+            elgamal_public_key: keypair.public_key,
+            ..Default::default()
+        };
+        let (_, pk) = keygen(&params, &circuit).unwrap();
+        assert!(matches!(
+            encrypt_and_prove_with_keys(&params, &pk, &message, &keypair.public_key, 31, 2),
+            Err(MessageError::TooManyBlocks {
+                blocks: 3,
+                max_blocks: 2
+            })
+        ));
+
+        let message = "a".repeat(DEFAULT_MAX_BLOCKS * 31);
+        let error = encrypt_and_prove(&message, &keypair.public_key, 31).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the message has 1025 blocks, more than the limit of 1024 blocks"
+        );
+    }
+
+    #[test]
+    fn round_trip_with_recommended_k() {
+        use ff::Field;
+        use pasta_curves::pallas;
+        use rand::rngs::OsRng;
+
+        let keypair = ElGamalKeypair::new();
+        let message = "A message of two blocks, proven without choosing K.";
+        let encrypted = encrypt_and_prove(message, &keypair.public_key, 31).unwrap();
+        assert_eq!(encrypted.proofs.len(), 2);
+
+        let params = Params::new(recommended_k(1));
+        let circuit = VeEncCircuit {
+            elgamal_public_key: keypair.public_key,
+            ..Default::default()
+        };
+        let vk = keygen_vk(&params, &circuit).unwrap();
+        assert!(verify_message(&params, &vk, &encrypted).is_ok());
+        assert_eq!(
+            decrypt_message(&encrypted, &keypair.private_key).unwrap(),
+            message
+        );
+
+        // an r_encode which is not derived from the public key and c1 is rejected
+        let mut tampered = encrypted.clone();
+        tampered.blocks[1].r_encode = pallas::Base::random(OsRng);
+        assert!(matches!(
+            verify_message(&params, &vk, &tampered),
+            Err(MessageError::InvalidREncode { block: 1 })
+        ));
+    }
+
+    #[test]
+    fn framed_round_trip() {
+        use crate::elgamal::extended_elgamal::extended_elgamal_encrypt;
+        use crate::encode::framing::frame_message;
+
+        let keypair = ElGamalKeypair::new();
+        // 50 bytes, which is not a multiple of the block size, ending with null bytes
+        let mut data = b"binary data with trailing null bytes".to_vec();
+        data.resize(50, 0);
+
+        let blocks: Vec<_> = frame_message(&data, 31)
+            .unwrap()
+            .into_iter()
+            .map(|m| extended_elgamal_encrypt(&keypair.public_key, m).unwrap().0)
+            .collect();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(
+            decrypt_framed(&blocks, 31, &keypair.private_key).unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn decrypt_to_string() {
+        use crate::elgamal::extended_elgamal::{extended_elgamal_encrypt, DataInTransmit};
+        use crate::encode::bytes::{encode_bytes, EncodeError};
+
+        let keypair = ElGamalKeypair::new();
+        let encrypt = |bytes: &[u8]| -> Vec<DataInTransmit> {
+            encode_bytes(bytes, 31)
+                .unwrap()
+                .into_iter()
+                .map(|m| extended_elgamal_encrypt(&keypair.public_key, m).unwrap().0)
+                .collect()
+        };
+
+        // a message of two blocks, with a multibyte character across the blocks
+        let message = "a message of 35 bytes, ending in é";
+        let blocks = encrypt(message.as_bytes());
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            try_decrypt_to_string(&keypair.private_key, &blocks, 31).unwrap(),
+            message
+        );
+
+        // bytes which are not UTF-8, and the blocks of a message out of order
+        let blocks = encrypt(&[0xff, 0xfe]);
+        assert!(matches!(
+            try_decrypt_to_string(&keypair.private_key, &blocks, 31),
+            Err(MessageError::Encode(EncodeError::InvalidUtf8))
+        ));
+        let mut blocks = encrypt(message.as_bytes());
+        blocks.swap(0, 1);
+        assert!(matches!(
+            try_decrypt_to_string(&keypair.private_key, &blocks, 31),
+            Err(MessageError::Encode(_))
+        ));
+    }
+}
@@ -0,0 +1,50 @@
+//! serde helpers for pallas points and field elements.
+//! Points are encoded in compressed form (32 bytes), field elements by their canonical
+//! little-endian representation (32 bytes). Decoding rejects non-canonical field elements
+//! and points that are not on the curve.
+
+/// `#[serde(with = "crate::serialization::point")]` for `pallas::Point`
+pub(crate) mod point {
+    use group::GroupEncoding;
+    use pasta_curves::pallas;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        point: &pallas::Point,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        point.to_bytes().serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<pallas::Point, D::Error> {
+        let bytes = <[u8; 32]>::deserialize(deserializer)?;
+        Option::from(pallas::Point::from_bytes(&bytes))
+            .ok_or_else(|| D::Error::custom("invalid pallas point encoding"))
+    }
+}
+
+/// `#[serde(with = "crate::serialization::base")]` for `pallas::Base`
+pub(crate) mod base {
+    use ff::PrimeField;
+    use pasta_curves::pallas;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        element: &pallas::Base,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        element.to_repr().serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<pallas::Base, D::Error> {
+        let bytes = <[u8; 32]>::deserialize(deserializer)?;
+        Option::from(pallas::Base::from_repr(bytes))
+            .ok_or_else(|| D::Error::custom("invalid pallas base field encoding"))
+    }
+}
@@ -0,0 +1,288 @@
+/// Canonical test vectors: fixed seed -> fixed keypair -> fixed ciphertexts -> deterministic proofs.
+/// A failure of these tests is a breaking change: keys, ciphertexts or proofs produced by this
+/// version of the crate differ from the ones of the previous versions for the same seeds.
+/// A change of the proofs must bump CIRCUIT_VERSION, and any change must update the vectors below.
+///
+/// The vectors pin
+/// - the keypair derived by `ElGamalKeypair::from_seed`,
+/// - r_encode (after the retries of the encoding) and c1 of the encryptions drawn from a
+///   seeded ChaCha20Rng, for a single-block and a multi-block message,
+/// - the decryption of c2, whose sign of y depends on the square root of the encoding,
+/// - the proofs created with a seeded ChaCha20Rng: they are deterministic, carry the header of
+///   this version and verify against the pinned ciphertexts,
+/// - the bytes of these proofs and a BLAKE2b digest of the verifying key, committed in the
+///   fixtures `single_block` and `multi_block` (see `fixtures`). The single-block proof is also
+///   verified without the prover by `circuits::tests::verify_only`.
+///
+/// The points are compressed pallas points (`GroupEncoding::to_bytes`), the field elements are
+/// little-endian canonical encodings.
+
+use crate::circuits::verifiable_encryption::{circuit_from_witness, VeEncCircuit, VeEncInstance};
+use crate::circuits::verifiable_encryption_batch::{VeEncBatchCircuit, VeEncBatchInstance};
+use crate::circuits::{
+    find_minimum_k, keygen, prove_encryption_with_rng, verify_encryption, CIRCUIT_VERSION,
+    PROOF_HEADER_SIZE, PROOF_MAGIC,
+};
+use crate::elgamal::elgamal::ElGamalKeypair;
+use crate::elgamal::extended_elgamal::{
+    extended_elgamal_decrypt, extended_elgamal_encrypt_with_rng,
+};
+use crate::encode::bytes::encode_bytes;
+use crate::fixtures::{self, Fixture};
+use blake2b_simd::Params as Blake2bParams;
+use ff::PrimeField;
+use group::GroupEncoding;
+use halo2_proofs::plonk::{Circuit, VerifyingKey};
+use halo2_proofs::poly::commitment::Params;
+use pasta_curves::{pallas, vesta};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+const KEYPAIR_SEED: [u8; 32] = [0x2a; 32];
+const SINGLE_BLOCK_SEED: [u8; 32] = [0x01; 32];
+const MULTI_BLOCK_SEED: [u8; 32] = [0x02; 32];
+const PROOF_SEED: [u8; 32] = [0x03; 32];
+
+const SINGLE_BLOCK_MESSAGE: u64 = 42;
+// 61 bytes: two blocks of 31 bytes, the second one with a byte of padding
+const MULTI_BLOCK_MESSAGE: &[u8] = b"Test vectors pin the keypair, the ciphertexts and the proofs.";
+const BLOCK_SIZE: usize = 31;
+
+const PRIVATE_KEY: [u8; 32] = [
+    0xcd, 0xbb, 0xc0, 0x5f, 0x59, 0x41, 0x48, 0xc2,
+    0xdf, 0xa3, 0x55, 0x4a, 0x4a, 0xc2, 0x85, 0xaf,
+    0x28, 0xd2, 0xd8, 0x3c, 0xdd, 0x60, 0xa9, 0x2f,
+    0xb1, 0x1e, 0x8f, 0xf6, 0x85, 0xf8, 0xf3, 0x07,
+];
+
+const PUBLIC_KEY: [u8; 32] = [
+    0xa4, 0x0f, 0x4e, 0x95, 0x4d, 0x6f, 0x31, 0x86,
+    0xeb, 0x98, 0x16, 0xbb, 0x84, 0xae, 0x66, 0x69,
+    0xf4, 0xa4, 0x3b, 0xbd, 0xf1, 0x44, 0x30, 0xca,
+    0xd7, 0x70, 0xf7, 0x05, 0xa7, 0x22, 0x02, 0xbe,
+];
+
+// (r_encode, c1) of each block
+type CiphertextVector = ([u8; 32], [u8; 32]);
+
+// the first r_encode is on the curve
+const SINGLE_BLOCK: [CiphertextVector; 1] = [(
+    [
+        0x4b, 0xb7, 0xfb, 0x5d, 0x41, 0xc9, 0x86, 0x4e,
+        0x9a, 0xd5, 0xbb, 0x7c, 0x2c, 0x91, 0x3d, 0x1e,
+        0x5f, 0x1d, 0xb3, 0x8a, 0x22, 0xd1, 0x65, 0xd1,
+        0x8d, 0x0a, 0x4d, 0x4d, 0xb3, 0x81, 0x9e, 0x2a,
+    ],
+    [
+        0x78, 0x48, 0xc1, 0xd3, 0x50, 0xbb, 0xe2, 0xc2,
+        0x26, 0x86, 0xad, 0x49, 0x72, 0x1c, 0x2a, 0x5b,
+        0x65, 0xa3, 0x4e, 0xaa, 0x4e, 0x73, 0x6a, 0x96,
+        0x13, 0x40, 0xbf, 0xb3, 0x98, 0xca, 0x35, 0x95,
+    ],
+)];
+
+// both blocks are encoded at the second attempt, so r_encode is the sampled value plus one
+const MULTI_BLOCK: [CiphertextVector; 2] = [
+    (
+        [
+            0xc1, 0xb5, 0x9c, 0x0e, 0x6a, 0x7a, 0x9c, 0xa3,
+            0x66, 0x5d, 0xb1, 0xe9, 0x9c, 0x06, 0x1c, 0xd6,
+            0x2c, 0xe8, 0x0f, 0xb2, 0x29, 0x94, 0x8c, 0xa3,
+            0x81, 0xe0, 0xa0, 0x02, 0x7a, 0xdb, 0x9d, 0x11,
+        ],
+        [
+            0x5f, 0xc2, 0xe0, 0x01, 0x19, 0x7c, 0x30, 0xdc,
+            0x46, 0x3c, 0x1e, 0x58, 0xf2, 0x20, 0x29, 0x5c,
+            0x02, 0x34, 0xc9, 0xc8, 0x18, 0xbd, 0xad, 0xc7,
+            0x48, 0x97, 0x03, 0x6f, 0x8d, 0x1e, 0xdf, 0xad,
+        ],
+    ),
+    (
+        [
+            0xec, 0xb1, 0x0b, 0x7f, 0x83, 0x52, 0x61, 0x9f,
+            0x60, 0x03, 0xd6, 0xa5, 0x69, 0x23, 0xe0, 0x5e,
+            0x38, 0x52, 0x35, 0x9e, 0xe5, 0x01, 0x8b, 0x59,
+            0xe0, 0x5a, 0x2d, 0x1f, 0x8c, 0x9b, 0x80, 0x35,
+        ],
+        [
+            0xaa, 0xb2, 0xa2, 0xfb, 0xe4, 0x40, 0x94, 0xc4,
+            0x0a, 0xce, 0xa1, 0x4e, 0xc6, 0x67, 0x9b, 0xba,
+            0x5f, 0x39, 0x61, 0x11, 0xed, 0x00, 0x93, 0xb6,
+            0x3d, 0xc9, 0x2a, 0x9b, 0x7e, 0xc2, 0x60, 0x1d,
+        ],
+    ),
+];
+
+fn keypair() -> ElGamalKeypair {
+    ElGamalKeypair::from_seed(KEYPAIR_SEED)
+}
+
+// Encrypt the blocks in order with randomness drawn from a ChaCha20Rng seeded with `seed`,
+// and check the ciphertexts against the vectors.
+fn encrypt_blocks(
+    messages: &[pallas::Base],
+    seed: [u8; 32],
+    expected: &[CiphertextVector],
+) -> Vec<VeEncCircuit> {
+    assert_eq!(messages.len(), expected.len(), "breaking change: number of blocks");
+    let keypair = keypair();
+    let mut rng = ChaCha20Rng::from_seed(seed);
+
+    messages
+        .iter()
+        .zip(expected)
+        .enumerate()
+        .map(|(i, (m, (r_encode, c1)))| {
+            let (data_in_transmit, witness) =
+                extended_elgamal_encrypt_with_rng(&keypair.public_key, *m, &mut rng).unwrap();
+            assert_eq!(
+                data_in_transmit.r_encode().to_repr(),
+                *r_encode,
+                "breaking change: r_encode of block {}",
+                i
+            );
+            assert_eq!(
+                data_in_transmit.c1().to_bytes(),
+                *c1,
+                "breaking change: c1 of block {}",
+                i
+            );
+            assert_eq!(
+                extended_elgamal_decrypt(&keypair.private_key, data_in_transmit.clone()),
+                Ok(*m),
+                "breaking change: decryption of block {}",
+                i
+            );
+
+            circuit_from_witness(data_in_transmit, keypair.public_key, &witness).unwrap()
+        })
+        .collect()
+}
+
+fn single_block() -> Vec<VeEncCircuit> {
+    encrypt_blocks(
+        &[pallas::Base::from(SINGLE_BLOCK_MESSAGE)],
+        SINGLE_BLOCK_SEED,
+        &SINGLE_BLOCK,
+    )
+}
+
+fn multi_block() -> Vec<VeEncCircuit> {
+    encrypt_blocks(
+        &encode_bytes(MULTI_BLOCK_MESSAGE, BLOCK_SIZE).unwrap(),
+        MULTI_BLOCK_SEED,
+        &MULTI_BLOCK,
+    )
+}
+
+fn instance(blocks: &[VeEncCircuit]) -> Vec<vesta::Scalar> {
+    let [instance] = VeEncBatchInstance::new(
+        blocks
+            .iter()
+            .map(|block| {
+                VeEncInstance::new(block.data_in_transmit.clone(), block.elgamal_public_key)
+            })
+            .collect(),
+    )
+    .to_halo2_instance()
+    .unwrap();
+    instance
+}
+
+// Write the fixture with VE_UPDATE_FIXTURES, check it against the committed one otherwise.
+fn check_fixture(name: &str, fixture: Fixture) {
+    if fixtures::update() {
+        fixtures::write(name, &fixture);
+        return;
+    }
+    let expected = fixtures::read(name);
+    assert!(
+        fixture.keys().eq(expected.keys()),
+        "breaking change: entries of the fixture {}",
+        name
+    );
+    for (key, value) in &fixture {
+        assert!(expected[key] == *value, "breaking change: {} of the fixture {}", key, name);
+    }
+}
+
+// Digest of the verifying key: the constraint system, the fixed commitments and the
+// permutation, as pinned by halo2 in the transcript.
+fn vk_digest(vk: &VerifyingKey<vesta::Affine>) -> Vec<u8> {
+    Blake2bParams::new()
+        .hash_length(32)
+        .hash(format!("{:?}", vk.pinned()).as_bytes())
+        .as_bytes()
+        .to_vec()
+}
+
+// Create two proofs from the same seed, check that they are the same and verify.
+// Returns the fixture of the proof and of the verifying key.
+fn check_proof<C: Circuit<pallas::Base> + Clone>(
+    circuit: C,
+    instance: &[vesta::Scalar],
+) -> Fixture {
+    let empty_circuit = circuit.without_witnesses();
+    let params = Params::new(find_minimum_k(&empty_circuit).unwrap());
+    let (vk, pk) = keygen(&params, &empty_circuit).unwrap();
+    let instance = [instance];
+
+    let prove = || {
+        prove_encryption_with_rng(
+            &params,
+            &pk,
+            circuit.clone(),
+            &instance,
+            ChaCha20Rng::from_seed(PROOF_SEED),
+        )
+        .unwrap()
+    };
+    let proof = prove();
+    assert_eq!(proof, prove(), "breaking change: proofs are not deterministic");
+    assert_eq!(
+        proof[..PROOF_HEADER_SIZE - 1],
+        [&PROOF_MAGIC[..], &[CIRCUIT_VERSION]].concat()[..],
+        "breaking change: proof header"
+    );
+    assert!(
+        verify_encryption(&params, &vk, &instance, &proof).is_ok(),
+        "breaking change: the proof of the test vector does not verify"
+    );
+    Fixture::from([
+        ("proof".to_string(), proof),
+        ("vk_digest".to_string(), vk_digest(&vk)),
+    ])
+}
+
+#[test]
+fn keypair_vector() {
+    let keypair = keypair();
+    assert_eq!(
+        keypair.private_key.to_repr(),
+        PRIVATE_KEY,
+        "breaking change: private key derived from the seed"
+    );
+    assert_eq!(
+        keypair.public_key.to_bytes(),
+        PUBLIC_KEY,
+        "breaking change: public key derived from the seed"
+    );
+}
+
+#[test]
+fn single_block_vector() {
+    let blocks = single_block();
+    let instance = instance(&blocks);
+    let mut fixture = check_proof(blocks[0].clone(), &instance);
+    fixture.insert("public_key".to_string(), blocks[0].elgamal_public_key.to_bytes().to_vec());
+    fixture.insert("data_in_transmit".to_string(), blocks[0].data_in_transmit.to_bytes());
+    check_fixture("single_block", fixture);
+}
+
+#[test]
+fn multi_block_vector() {
+    let blocks = multi_block();
+    let instance = instance(&blocks);
+    let fixture = check_proof(VeEncBatchCircuit::new(blocks), &instance);
+    check_fixture("multi_block", fixture);
+}
@@ -0,0 +1,179 @@
+//! Hex and base64 encodings of serialized proofs and of `DataInTransmit`, to paste them
+//! into configuration files, command lines or logs.
+//! Hex is lowercase without prefix, base64 is the standard alphabet with padding.
+//! Decoding rejects malformed text, proofs shorter than their header or without the magic
+//! bytes, and data that `DataInTransmit::from_bytes` rejects. The circuit version and K of
+//! a proof are checked by the verification, against the params.
+
+use crate::circuits::{PROOF_HEADER_SIZE, PROOF_MAGIC};
+use crate::elgamal::extended_elgamal::{DataInTransmit, DataInTransmitError};
+use std::fmt;
+
+// Errors returned when decoding text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TextError {
+    InvalidHex,                       // not an even number of hexadecimal digits
+    InvalidBase64,                    // not standard padded base64
+    InvalidProof,                     // too short for a proof, or no PROOF_MAGIC
+    InvalidData(DataInTransmitError), // the bytes are not a DataInTransmit
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextError::InvalidHex => write!(f, "invalid hex"),
+            TextError::InvalidBase64 => write!(f, "invalid base64"),
+            TextError::InvalidProof => write!(f, "not a verifiable encryption proof"),
+            TextError::InvalidData(error) => write!(f, "invalid data in transmit: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+impl From<DataInTransmitError> for TextError {
+    fn from(error: DataInTransmitError) -> Self {
+        TextError::InvalidData(error)
+    }
+}
+
+// A proof is its header followed by a non-empty halo2 proof.
+fn check_proof(proof: Vec<u8>) -> Result<Vec<u8>, TextError> {
+    if proof.len() <= PROOF_HEADER_SIZE || proof[..PROOF_MAGIC.len()] != PROOF_MAGIC {
+        return Err(TextError::InvalidProof);
+    }
+    Ok(proof)
+}
+
+/// Hex encoding of a proof created by `prove_encryption`
+#[cfg(feature = "hex")]
+pub fn proof_to_hex(proof: &[u8]) -> String {
+    hex::encode(proof)
+}
+
+/// Proof encoded by [`proof_to_hex`]. Upper case digits are accepted.
+#[cfg(feature = "hex")]
+pub fn proof_from_hex(text: &str) -> Result<Vec<u8>, TextError> {
+    check_proof(hex::decode(text.trim()).map_err(|_| TextError::InvalidHex)?)
+}
+
+/// Base64 encoding of a proof created by `prove_encryption`
+#[cfg(feature = "base64")]
+pub fn proof_to_base64(proof: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(proof)
+}
+
+/// Proof encoded by [`proof_to_base64`]
+#[cfg(feature = "base64")]
+pub fn proof_from_base64(text: &str) -> Result<Vec<u8>, TextError> {
+    use base64::Engine;
+    let proof = base64::engine::general_purpose::STANDARD
+        .decode(text.trim())
+        .map_err(|_| TextError::InvalidBase64)?;
+    check_proof(proof)
+}
+
+impl DataInTransmit {
+    /// Hex encoding of [`DataInTransmit::to_bytes`]
+    #[cfg(feature = "hex")]
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    /// Data encoded by [`DataInTransmit::to_hex`]. Upper case digits are accepted.
+    #[cfg(feature = "hex")]
+    pub fn from_hex(text: &str) -> Result<Self, TextError> {
+        let bytes = hex::decode(text.trim()).map_err(|_| TextError::InvalidHex)?;
+        Ok(DataInTransmit::from_bytes(&bytes)?)
+    }
+
+    /// Base64 encoding of [`DataInTransmit::to_bytes`]
+    #[cfg(feature = "base64")]
+    pub fn to_base64(&self) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.encode(self.to_bytes())
+    }
+
+    /// Data encoded by [`DataInTransmit::to_base64`]
+    #[cfg(feature = "base64")]
+    pub fn from_base64(text: &str) -> Result<Self, TextError> {
+        use base64::Engine;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(text.trim())
+            .map_err(|_| TextError::InvalidBase64)?;
+        Ok(DataInTransmit::from_bytes(&bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextError;
+    use crate::circuits::{CIRCUIT_VERSION, PROOF_MAGIC};
+    use crate::elgamal::elgamal::ElGamalKeypair;
+    use crate::elgamal::extended_elgamal::{
+        extended_elgamal_encrypt, DataInTransmit, DataInTransmitError,
+    };
+    use pasta_curves::pallas;
+
+    fn data_in_transmit() -> DataInTransmit {
+        let keypair = ElGamalKeypair::new();
+        extended_elgamal_encrypt(&keypair.public_key, pallas::Base::from(42))
+            .unwrap()
+            .0
+    }
+
+    // the header of a proof for K = 11, followed by the bytes of the halo2 proof
+    fn proof() -> Vec<u8> {
+        let mut proof = PROOF_MAGIC.to_vec();
+        proof.extend([CIRCUIT_VERSION, 11]);
+        proof.extend((0..=255u8).cycle().take(1000));
+        proof
+    }
+
+    #[cfg(feature = "hex")]
+    #[test]
+    fn hex_round_trip() {
+        use super::{proof_from_hex, proof_to_hex};
+
+        let proof = proof();
+        let text = proof_to_hex(&proof);
+        assert_eq!(proof_from_hex(&text).unwrap(), proof);
+        assert_eq!(proof_from_hex(&text.to_uppercase()).unwrap(), proof);
+        assert_eq!(proof_from_hex(&text[1..]), Err(TextError::InvalidHex));
+        assert_eq!(proof_from_hex("zz"), Err(TextError::InvalidHex));
+        assert_eq!(proof_from_hex(&text[..12]), Err(TextError::InvalidProof));
+        assert_eq!(proof_from_hex(&text[2..]), Err(TextError::InvalidProof));
+
+        let data = data_in_transmit();
+        let text = data.to_hex();
+        assert_eq!(text.len(), 192);
+        assert_eq!(DataInTransmit::from_hex(&text).unwrap().to_bytes(), data.to_bytes());
+        assert!(matches!(
+            DataInTransmit::from_hex(&text[..190]),
+            Err(TextError::InvalidData(DataInTransmitError::InvalidLength { .. }))
+        ));
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn base64_round_trip() {
+        use super::{proof_from_base64, proof_to_base64};
+
+        let proof = proof();
+        let text = proof_to_base64(&proof);
+        assert_eq!(proof_from_base64(&text).unwrap(), proof);
+        assert_eq!(proof_from_base64(&text[1..]), Err(TextError::InvalidBase64));
+        assert_eq!(proof_from_base64("not base64!"), Err(TextError::InvalidBase64));
+        assert_eq!(proof_from_base64("AAAA"), Err(TextError::InvalidProof));
+
+        let data = data_in_transmit();
+        let text = data.to_base64();
+        assert_eq!(text.len(), 128);
+        assert_eq!(DataInTransmit::from_base64(&text).unwrap().to_bytes(), data.to_bytes());
+        assert!(matches!(
+            DataInTransmit::from_base64("AAAA"),
+            Err(TextError::InvalidData(DataInTransmitError::InvalidLength { .. }))
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+//! Builds of the crate for targets other than the host, with the features documented in the
+//! README. Each test runs `cargo build` for its target, and is skipped when the target is not
+//! installed (`rustup target add <target>`). They are ignored by default, as they build the
+//! crate and its dependencies again:
+//! `cargo test --test build_targets -- --ignored`
+use std::path::Path;
+use std::process::Command;
+
+// Build the library for target with the given cargo arguments, in a target directory of its
+// own so that the build of the tests is not invalidated.
+fn build_for_target(target: &str, args: &[&str]) {
+    let installed = Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).lines().any(|t| t == target))
+        .unwrap_or(false);
+    if !installed {
+        eprintln!("skipped: the target {} is not installed", target);
+        return;
+    }
+
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let status = Command::new(env!("CARGO"))
+        .current_dir(manifest_dir)
+        .args(["build", "--lib", "--target", target])
+        .args(args)
+        .env("CARGO_TARGET_DIR", Path::new(manifest_dir).join("target").join(target))
+        .status()
+        .expect("cargo runs");
+    assert!(status.success(), "the build for {} failed", target);
+}
+
+// The verification API in the browser: std for the circuits, without the threads of
+// `multicore` and without the prover.
+#[test]
+#[ignore]
+fn wasm32_verifier() {
+    build_for_target("wasm32-unknown-unknown", &["--no-default-features", "--features", "std"]);
+}
+
+// The encoding and the ElGamal arithmetic under no_std + alloc, for a target without std, so
+// that a dependency or a module which needs std fails the build.
+#[test]
+#[ignore]
+fn thumbv7em_no_std() {
+    build_for_target("thumbv7em-none-eabihf", &["--no-default-features"]);
+}
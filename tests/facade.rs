@@ -0,0 +1,58 @@
+//! keygen -> encrypt -> prove -> verify -> decrypt through the byte API only.
+#![cfg(feature = "prover")]
+
+use halo2_verifiable_encryption::facade::{self, Error, ENCRYPTION_RECORD_BYTES};
+
+#[test]
+fn round_trip() {
+    let keypair = facade::keygen();
+    assert_eq!(keypair.len(), 64);
+    let public_key = facade::public_key(&keypair).unwrap();
+    assert_eq!(public_key.len(), 32);
+
+    // 41 bytes: the length block and two data blocks
+    let data = b"file contents, with a trailing null byte\0".to_vec();
+    let encryption = facade::encrypt_file(&public_key, &data).unwrap();
+    assert_eq!(encryption.len(), 3 * ENCRYPTION_RECORD_BYTES);
+
+    let ciphertext = facade::prove(&public_key, &encryption).unwrap();
+    facade::verify(&public_key, &ciphertext).unwrap();
+    assert_eq!(facade::decrypt(&keypair, &ciphertext).unwrap(), data);
+
+    // the ciphertext does not verify for another public key, from which its r_encode is not
+    // derived, nor decrypts with another keypair
+    let other = facade::keygen();
+    let other_public_key = facade::public_key(&other).unwrap();
+    assert_eq!(
+        facade::verify(&other_public_key, &ciphertext),
+        Err(Error::InvalidCiphertext)
+    );
+    assert_ne!(facade::decrypt(&other, &ciphertext).ok(), Some(data));
+
+    // an r_encode which is not derived from the public key and c1 of its block: the byte 16 of
+    // the r_encode of the first block, after the block count and the two points
+    let mut tampered = ciphertext.clone();
+    tampered[4 + 64 + 16] ^= 1;
+    assert_eq!(
+        facade::verify(&public_key, &tampered),
+        Err(Error::InvalidCiphertext)
+    );
+
+    // malformed inputs
+    assert_eq!(
+        facade::verify(&public_key, &ciphertext[1..]),
+        Err(Error::InvalidCiphertext)
+    );
+    assert_eq!(
+        facade::prove(&public_key, &encryption[1..]),
+        Err(Error::InvalidEncryption)
+    );
+    assert_eq!(
+        facade::encrypt_file(&public_key[1..], b""),
+        Err(Error::InvalidPublicKey)
+    );
+    assert!(matches!(
+        facade::public_key(&keypair[1..]),
+        Err(Error::InvalidKeypair(_))
+    ));
+}
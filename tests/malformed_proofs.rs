@@ -0,0 +1,157 @@
+//! Fuzz-style tests of the verifier against malformed input, as sent by a malicious prover:
+//! random bytes, and mutations of a valid ciphertext and of its proof, are rejected with an
+//! error and never panic. The mutations are seeded from a ciphertext created by the facade.
+#![cfg(feature = "prover")]
+
+use halo2_proofs::plonk::{Circuit, VerifyingKey};
+use halo2_proofs::poly::commitment::Params;
+use halo2_verifiable_encryption::circuits::verifiable_encryption::{
+    VeEncCircuitBuilder, VeEncInstance, K,
+};
+use halo2_verifiable_encryption::circuits::{keygen_vk, verify_encryption, PROOF_HEADER_SIZE};
+use halo2_verifiable_encryption::elgamal::elgamal::ElGamalKeypair;
+use halo2_verifiable_encryption::elgamal::extended_elgamal::{
+    DataInTransmit, DataInTransmitError, DATA_IN_TRANSMIT_BYTES,
+};
+use halo2_verifiable_encryption::facade;
+use pasta_curves::{pallas, vesta};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::Index;
+use std::sync::OnceLock;
+
+// A valid single-block ciphertext, its block and its proof, with the keys to verify it.
+struct Seed {
+    public_key: Vec<u8>,
+    elgamal_public_key: pallas::Point,
+    ciphertext: Vec<u8>,
+    data_in_transmit: Vec<u8>,
+    proof: Vec<u8>,
+    params: Params<vesta::Affine>,
+    vk: VerifyingKey<vesta::Affine>,
+}
+
+impl Seed {
+    fn verify(&self, data_in_transmit: DataInTransmit, proof: &[u8]) -> bool {
+        let instance = match VeEncInstance::new(data_in_transmit, self.elgamal_public_key)
+            .to_halo2_instance()
+        {
+            Ok(instance) => instance,
+            Err(_) => return false,
+        };
+        let instance: Vec<&[vesta::Scalar]> = instance.iter().map(|c| &c[..]).collect();
+        verify_encryption(&self.params, &self.vk, &instance, proof).is_ok()
+    }
+}
+
+// Created once, as proving is much slower than the verification of each case.
+fn seed() -> &'static Seed {
+    static SEED: OnceLock<Seed> = OnceLock::new();
+    SEED.get_or_init(|| {
+        let keypair = facade::keygen();
+        let public_key = facade::public_key(&keypair).unwrap();
+        let elgamal_public_key = ElGamalKeypair::from_bytes(&keypair).unwrap().public_key;
+
+        // no data: a single block, the length block
+        let encryption = facade::encrypt_file(&public_key, b"").unwrap();
+        let ciphertext = facade::prove(&public_key, &encryption).unwrap();
+
+        // count || data_in_transmit || proof length || proof, see the facade
+        let data_in_transmit = ciphertext[4..4 + DATA_IN_TRANSMIT_BYTES].to_vec();
+        let proof = ciphertext[8 + DATA_IN_TRANSMIT_BYTES..].to_vec();
+
+        let params = Params::new(K);
+        let (circuit, _, _) = VeEncCircuitBuilder::new(pallas::Base::zero(), elgamal_public_key)
+            .build()
+            .unwrap();
+        let vk = keygen_vk(&params, &circuit.without_witnesses()).unwrap();
+
+        let seed = Seed {
+            public_key,
+            elgamal_public_key,
+            ciphertext,
+            data_in_transmit,
+            proof,
+            params,
+            vk,
+        };
+        let data_in_transmit = DataInTransmit::from_bytes(&seed.data_in_transmit).unwrap();
+        assert!(seed.verify(data_in_transmit, &seed.proof));
+        facade::verify(&seed.public_key, &seed.ciphertext).unwrap();
+        seed
+    })
+}
+
+// Flip the bits of mask in the byte at index
+fn corrupt(bytes: &[u8], index: Index, mask: u8) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    let index = index.index(bytes.len());
+    bytes[index] ^= mask;
+    bytes
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn prop_random_proof(proof in vec(any::<u8>(), 0..4096), with_header in any::<bool>()) {
+        let seed = seed();
+        let data_in_transmit = DataInTransmit::from_bytes(&seed.data_in_transmit).unwrap();
+        let proof = if with_header {
+            // random bytes after a valid header, to reach the transcript
+            [&seed.proof[..PROOF_HEADER_SIZE], &proof[..]].concat()
+        } else {
+            proof
+        };
+        prop_assert!(!seed.verify(data_in_transmit, &proof));
+    }
+
+    #[test]
+    fn prop_corrupted_proof(index in any::<Index>(), mask in 1..=u8::MAX) {
+        let seed = seed();
+        let data_in_transmit = DataInTransmit::from_bytes(&seed.data_in_transmit).unwrap();
+        prop_assert!(!seed.verify(data_in_transmit, &corrupt(&seed.proof, index, mask)));
+    }
+
+    #[test]
+    fn prop_truncated_proof(length in any::<Index>()) {
+        let seed = seed();
+        let data_in_transmit = DataInTransmit::from_bytes(&seed.data_in_transmit).unwrap();
+        let proof = &seed.proof[..length.index(seed.proof.len())];
+        prop_assert!(!seed.verify(data_in_transmit, proof));
+    }
+
+    #[test]
+    fn prop_random_data_in_transmit(bytes in vec(any::<u8>(), 0..2 * DATA_IN_TRANSMIT_BYTES)) {
+        match DataInTransmit::from_bytes(&bytes) {
+            Ok(_) => prop_assert_eq!(bytes.len(), DATA_IN_TRANSMIT_BYTES),
+            Err(DataInTransmitError::InvalidLength { expected, actual }) => {
+                prop_assert_eq!((expected, actual), (DATA_IN_TRANSMIT_BYTES, bytes.len()));
+            }
+            Err(_) => prop_assert_eq!(bytes.len(), DATA_IN_TRANSMIT_BYTES),
+        }
+    }
+
+    // a corrupted block is either rejected by the parser, or is another ciphertext, which the
+    // proof is not for
+    #[test]
+    fn prop_corrupted_data_in_transmit(index in any::<Index>(), mask in 1..=u8::MAX) {
+        let seed = seed();
+        let bytes = corrupt(&seed.data_in_transmit, index, mask);
+        if let Ok(data_in_transmit) = DataInTransmit::from_bytes(&bytes) {
+            prop_assert!(!seed.verify(data_in_transmit, &seed.proof));
+        }
+    }
+
+    #[test]
+    fn prop_corrupted_ciphertext(index in any::<Index>(), mask in 1..=u8::MAX) {
+        let seed = seed();
+        let ciphertext = corrupt(&seed.ciphertext, index, mask);
+        prop_assert!(facade::verify(&seed.public_key, &ciphertext).is_err());
+    }
+
+    #[test]
+    fn prop_random_ciphertext(ciphertext in vec(any::<u8>(), 0..4096)) {
+        prop_assert!(facade::verify(&seed().public_key, &ciphertext).is_err());
+    }
+}